@@ -0,0 +1,482 @@
+//! Expectations about the bus traffic, checked against the statistics
+//! collected for a while. Expectations are written in TOML:
+//!
+//! ```toml
+//! # Seconds to listen, unless overridden on the command line. Without it,
+//! # listen until the input ends (e.g. a replayed file).
+//! duration = 10
+//! # Fail if an id not listed below is seen
+//! allow_unknown_ids = false
+//! # Fail if more error frames are seen
+//! max_error_frames = 0
+//! # Maximum jitter, in percent of the average period, for every listed id.
+//! # Like the average period, it covers the frames of the --window only.
+//! max_jitter = 5.0
+//!
+//! [[id]]
+//! id = "18FEF100"
+//! # Average period over the --window, in milliseconds. The shortest and
+//! # longest periods of the whole capture must be in the tolerance too.
+//! period = 100
+//! # Accepted deviation from the period, in percent (default 10)
+//! tolerance = 10
+//! # Overrides the global maximum jitter
+//! max_jitter = 2.0
+//! # Minimum number of frames (default 1)
+//! min_count = 50
+//! ```
+
+use canbusnoop_core::CanId;
+use canbusnoop_db::{MultiStats, Stats, Window};
+use serde::Deserialize;
+use std::collections::BTreeSet;
+use std::fmt::Display;
+use std::path::Path;
+use std::time::Duration;
+
+const DEFAULT_TOLERANCE: f64 = 10.;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Expectations {
+    #[serde(default, deserialize_with = "deserialize_duration")]
+    duration: Option<Duration>,
+    #[serde(default = "default_allow_unknown_ids")]
+    allow_unknown_ids: bool,
+    max_error_frames: Option<usize>,
+    max_jitter: Option<f64>,
+    #[serde(default, rename = "id")]
+    ids: Vec<IdExpectation>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct IdExpectation {
+    #[serde(deserialize_with = "deserialize_can_id")]
+    id: CanId,
+    period: Option<f64>,
+    tolerance: Option<f64>,
+    max_jitter: Option<f64>,
+    min_count: Option<usize>,
+}
+
+fn default_allow_unknown_ids() -> bool {
+    true
+}
+
+fn deserialize_can_id<'de, D: serde::Deserializer<'de>>(d: D) -> Result<CanId, D::Error> {
+    let s = String::deserialize(d)?;
+    s.parse().map_err(serde::de::Error::custom)
+}
+
+fn deserialize_duration<'de, D: serde::Deserializer<'de>>(
+    d: D,
+) -> Result<Option<Duration>, D::Error> {
+    let secs = f64::deserialize(d)?;
+    Duration::try_from_secs_f64(secs)
+        .map(Some)
+        .map_err(|_| serde::de::Error::custom(format!("invalid duration: {}", secs)))
+}
+
+impl Expectations {
+    pub(crate) fn load(path: &Path) -> Result<Expectations, Box<dyn std::error::Error>> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+        let expectations: Expectations = toml::from_str(&text)
+            .map_err(|e| format!("invalid expectations in {}: {}", path.display(), e))?;
+        expectations
+            .validate()
+            .map_err(|e| format!("invalid expectations in {}: {}", path.display(), e))?;
+        Ok(expectations)
+    }
+
+    /// Reject the values which can't be met
+    fn validate(&self) -> Result<(), String> {
+        let positive = |name: &str, value: Option<f64>| match value {
+            Some(x) if !x.is_finite() || x <= 0. => Err(format!("{} must be positive", name)),
+            _ => Ok(()),
+        };
+        let not_negative = |name: &str, value: Option<f64>| match value {
+            Some(x) if !x.is_finite() || x < 0. => Err(format!("{} must not be negative", name)),
+            _ => Ok(()),
+        };
+
+        not_negative("max_jitter", self.max_jitter)?;
+        for expected in &self.ids {
+            let name = |field: &str| format!("{} of {}", field, expected.id);
+            positive(&name("period"), expected.period)?;
+            not_negative(&name("tolerance"), expected.tolerance)?;
+            not_negative(&name("max_jitter"), expected.max_jitter)?;
+        }
+        Ok(())
+    }
+
+    /// How long to listen before checking, `None` to wait for the end of the
+    /// input
+    pub(crate) fn duration(&self) -> Option<Duration> {
+        self.duration
+    }
+
+    pub(crate) fn check(&self, stats: &MultiStats) -> Report {
+        let mut report = Report::default();
+
+        for expected in &self.ids {
+            let stats = stats.iter().find(|(&id, _)| id == expected.id);
+            self.check_id(&mut report, expected, stats.map(|(_, s)| s));
+        }
+
+        if !self.allow_unknown_ids {
+            let known: BTreeSet<CanId> = self.ids.iter().map(|x| x.id).collect();
+            let unknown: Vec<_> = stats.iter().filter(|(id, _)| !known.contains(id)).collect();
+            if unknown.is_empty() {
+                report.push(true, "no unknown ids".to_string());
+            }
+            for (id, stats) in unknown {
+                report.push(
+                    false,
+                    format!("{} unknown id ({} frames)", id, stats.count()),
+                );
+            }
+        }
+
+        if let Some(max) = self.max_error_frames {
+            let count = stats.errors().count();
+            report.push(
+                count <= max,
+                format!("{} error frames, expected at most {}", count, max),
+            );
+        }
+
+        report
+    }
+
+    fn check_id(&self, report: &mut Report, expected: &IdExpectation, stats: Option<&Stats>) {
+        let id = expected.id;
+        let min_count = expected.min_count.unwrap_or(1);
+        let count = stats.map(|s| s.count()).unwrap_or_default();
+        report.push(
+            count >= min_count,
+            format!("{} {} frames, expected at least {}", id, count, min_count),
+        );
+
+        let Some(stats) = stats else {
+            return;
+        };
+
+        if let Some(period) = expected.period {
+            let tolerance = expected.tolerance.unwrap_or(DEFAULT_TOLERANCE);
+            let min = period * (1. - tolerance / 100.);
+            let max = period * (1. + tolerance / 100.);
+            let expected = format!("expected {} ms ±{}%", period, tolerance);
+            match stats.avg_period() {
+                Some(avg) => {
+                    let avg = avg.as_secs_f64() * 1000.;
+                    report.push(
+                        (min..=max).contains(&avg),
+                        format!(
+                            "{} period {:.1} ms over {}, {}",
+                            id,
+                            avg,
+                            window_name(stats.window()),
+                            expected
+                        ),
+                    );
+                }
+                None => report.push(false, format!("{} period unknown, {}", id, expected)),
+            }
+
+            // A dropout or a burst anywhere in the capture
+            if let (Some(shortest), Some(longest)) = (stats.min_period(), stats.max_period()) {
+                let shortest = shortest.as_secs_f64() * 1000.;
+                let longest = longest.as_secs_f64() * 1000.;
+                report.push(
+                    min <= shortest && longest <= max,
+                    format!(
+                        "{} periods from {:.1} to {:.1} ms, {}",
+                        id, shortest, longest, expected
+                    ),
+                );
+            }
+        }
+
+        if let Some(max) = expected.max_jitter.or(self.max_jitter) {
+            match relative_jitter(stats) {
+                Some(jitter) => report.push(
+                    jitter <= max,
+                    format!(
+                        "{} jitter {:.2}% over {}, expected below {}%",
+                        id,
+                        jitter,
+                        window_name(stats.window()),
+                        max
+                    ),
+                ),
+                None => report.push(false, format!("{} jitter unknown", id)),
+            }
+        }
+    }
+}
+
+/// The frames the average period and the jitter are computed on
+fn window_name(window: Window) -> String {
+    match window {
+        Window::Count(n) => format!("the last {} frames", n),
+        Window::Time(_) => format!("the last {}", window),
+    }
+}
+
+/// Jitter in percent of the average period
+fn relative_jitter(stats: &Stats) -> Option<f64> {
+    stats.period_stats().map(|p| p.jitter * 100.)
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Report {
+    checks: Vec<(bool, String)>,
+}
+
+impl Report {
+    fn push(&mut self, passed: bool, message: String) {
+        self.checks.push((passed, message));
+    }
+
+    pub(crate) fn passed(&self) -> bool {
+        self.checks.iter().all(|(passed, _)| *passed)
+    }
+
+    /// 0 if all the checks passed, else 1
+    pub(crate) fn exit_code(&self) -> i32 {
+        match self.passed() {
+            true => 0,
+            false => 1,
+        }
+    }
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (passed, message) in &self.checks {
+            let status = if *passed { "PASS" } else { "FAIL" };
+            writeln!(f, "{} {}", status, message)?;
+        }
+        let failed = self.checks.iter().filter(|(passed, _)| !passed).count();
+        write!(f, "{} of {} checks failed", failed, self.checks.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use canbusnoop_core::Frame;
+
+    fn expectations(toml: &str) -> Expectations {
+        let expectations: Expectations = toml::from_str(toml).unwrap();
+        expectations.validate().unwrap();
+        expectations
+    }
+
+    /// Frames of `id` at the times in milliseconds
+    fn push(stats: &mut MultiStats, id: &str, times: impl IntoIterator<Item = u64>) {
+        let id: CanId = id.parse().unwrap();
+        for t in times {
+            stats.push(Frame::new(id, vec![0; 8], Duration::from_millis(t)));
+        }
+    }
+
+    fn messages(report: &Report) -> Vec<(bool, &str)> {
+        report
+            .checks
+            .iter()
+            .map(|(passed, message)| (*passed, message.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn parses_expectations() {
+        let e = expectations(
+            r#"
+            duration = 2.5
+            allow_unknown_ids = false
+            max_error_frames = 0
+            max_jitter = 5.0
+
+            [[id]]
+            id = "18FEF100"
+            period = 100
+            tolerance = 10
+            max_jitter = 2.0
+            min_count = 50
+
+            [[id]]
+            id = "123"
+            "#,
+        );
+        assert_eq!(e.duration(), Some(Duration::from_millis(2500)));
+        assert!(!e.allow_unknown_ids);
+        assert_eq!(e.ids.len(), 2);
+        assert_eq!(e.ids[0].id, CanId::extended(0x18FE_F100).unwrap());
+        assert_eq!(e.ids[0].period, Some(100.));
+        assert_eq!(e.ids[1].id, CanId::standard(0x123).unwrap());
+        assert_eq!(e.ids[1].min_count, None);
+
+        let e = expectations("");
+        assert_eq!(e.duration(), None);
+        assert!(e.allow_unknown_ids);
+    }
+
+    #[test]
+    fn rejects_invalid_expectations() {
+        for toml in [
+            "duration = -1",
+            "unknown = 1",
+            "[[id]]\nid = \"XYZ\"",
+            "[[id]]\nid = \"123\"\nperiod = \"fast\"",
+        ] {
+            assert!(toml::from_str::<Expectations>(toml).is_err(), "{}", toml);
+        }
+
+        for (toml, error) in [
+            ("max_jitter = -1.0", "max_jitter must not be negative"),
+            (
+                "[[id]]\nid = \"123\"\nperiod = -100",
+                "period of 123 must be positive",
+            ),
+            (
+                "[[id]]\nid = \"123\"\nperiod = 0",
+                "period of 123 must be positive",
+            ),
+            (
+                "[[id]]\nid = \"123\"\ntolerance = -5",
+                "tolerance of 123 must not be negative",
+            ),
+        ] {
+            let e: Expectations = toml::from_str(toml).unwrap();
+            assert_eq!(e.validate(), Err(error.to_string()), "{}", toml);
+        }
+    }
+
+    #[test]
+    fn passes() {
+        let e = expectations(
+            r#"
+            allow_unknown_ids = false
+            max_error_frames = 0
+            max_jitter = 5.0
+
+            [[id]]
+            id = "100"
+            period = 10
+            min_count = 10
+            "#,
+        );
+        let mut stats = MultiStats::default();
+        push(&mut stats, "100", (0..20).map(|i| i * 10));
+
+        let report = e.check(&stats);
+        assert_eq!(
+            messages(&report),
+            [
+                (true, "100 20 frames, expected at least 10"),
+                (
+                    true,
+                    "100 period 10.0 ms over the last 100 frames, expected 10 ms ±10%"
+                ),
+                (
+                    true,
+                    "100 periods from 10.0 to 10.0 ms, expected 10 ms ±10%"
+                ),
+                (
+                    true,
+                    "100 jitter 0.00% over the last 100 frames, expected below 5%"
+                ),
+                (true, "no unknown ids"),
+                (true, "0 error frames, expected at most 0"),
+            ]
+        );
+        assert!(report.passed());
+        assert_eq!(report.exit_code(), 0);
+        assert!(report.to_string().ends_with("0 of 6 checks failed"));
+    }
+
+    #[test]
+    fn fails() {
+        let e = expectations(
+            r#"
+            [[id]]
+            id = "100"
+            period = 10
+
+            [[id]]
+            id = "200"
+            "#,
+        );
+        let mut stats = MultiStats::default();
+        // A burst and a dropout at the start, which don't change the average
+        push(&mut stats, "100", [0, 5, 20]);
+        push(&mut stats, "100", (1..=200).map(|i| 20 + i * 10));
+
+        let report = e.check(&stats);
+        assert_eq!(
+            messages(&report),
+            [
+                (true, "100 203 frames, expected at least 1"),
+                (
+                    true,
+                    "100 period 10.0 ms over the last 100 frames, expected 10 ms ±10%"
+                ),
+                (
+                    false,
+                    "100 periods from 5.0 to 15.0 ms, expected 10 ms ±10%"
+                ),
+                (false, "200 0 frames, expected at least 1"),
+            ]
+        );
+        assert!(!report.passed());
+        assert_eq!(report.exit_code(), 1);
+        assert!(report.to_string().ends_with("2 of 4 checks failed"));
+    }
+
+    #[test]
+    fn reports_unknown_ids() {
+        let e = expectations(
+            r#"
+            allow_unknown_ids = false
+
+            [[id]]
+            id = "100"
+            "#,
+        );
+        let mut stats = MultiStats::default();
+        push(&mut stats, "100", [0]);
+        push(&mut stats, "18FEF100", [0, 10, 20]);
+
+        let report = e.check(&stats);
+        assert_eq!(
+            messages(&report),
+            [
+                (true, "100 1 frames, expected at least 1"),
+                (false, "18FEF100 unknown id (3 frames)"),
+            ]
+        );
+        assert_eq!(report.exit_code(), 1);
+    }
+
+    #[test]
+    fn load_errors() {
+        let dir = std::env::temp_dir().join(format!("canbusnoop-assert-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("expectations.toml");
+
+        let e = Expectations::load(&dir.join("missing.toml")).unwrap_err();
+        assert!(e.to_string().starts_with("cannot read"), "{}", e);
+
+        std::fs::write(&path, "[[id]]\nid = \"100\"\ntolerance = -1\n").unwrap();
+        let e = Expectations::load(&path).unwrap_err();
+        assert!(e.to_string().starts_with("invalid expectations"), "{}", e);
+
+        std::fs::write(&path, "[[id]]\nid = \"100\"\nperiod = 10\n").unwrap();
+        assert!(Expectations::load(&path).is_ok());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,50 +1,203 @@
 #![allow(dead_code)]
 
+mod assertions;
+mod stream;
+
+use assertions::Expectations;
 use canbusnoop_core::Frame;
-use canbusnoop_interface::{CanBusReader, Config};
-use canbusnoop_ui::launch;
-use clap::Parser;
-use futures_channel::mpsc::{unbounded, UnboundedSender};
+use canbusnoop_db::Window;
+use canbusnoop_interface::{CanBusReader, Config, Playback, Recorder, Speed, TxRequest};
+use canbusnoop_protocol::isotp::IsoTpChannel;
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use futures_channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
+use futures_util::stream::FusedStream;
+use futures_util::StreamExt;
+use std::path::PathBuf;
+use std::time::Duration;
 
-/// Read CAN frames from the CAN bus and send them to the UI
+/// Read CAN frames from the CAN bus and send them to the UI, transmit the
+/// frames requested by the UI
 async fn can_read_task(
     can_interface: String,
+    playback: Playback,
+    record: Option<PathBuf>,
     rx_sender: UnboundedSender<Frame>,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let config = Config::new(can_interface)?;
+    mut tx_receiver: UnboundedReceiver<TxRequest>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let config = Config::new(can_interface)?.with_playback(playback);
     let mut reader = CanBusReader::new(config)?;
+    let mut recorder = record.map(|path| Recorder::create(&path)).transpose()?;
+    let mut writer = match reader.writer() {
+        Ok(writer) => Some(writer),
+        Err(e) => {
+            log::warn!("Transmission disabled: {}", e);
+            // The UI is told when it tries to send
+            tx_receiver.close();
+            None
+        }
+    };
 
-    while let Some(frame) = reader.read().await {
-        rx_sender.unbounded_send(frame).unwrap();
+    loop {
+        tokio::select! {
+            frame = reader.read() => {
+                let Some(frame) = frame? else {
+                    break;
+                };
+                if let Some(recorder) = &mut recorder {
+                    recorder.write(&frame)?;
+                }
+                // The frontend is gone, e.g. the output was piped to head
+                if rx_sender.unbounded_send(frame).is_err() {
+                    break;
+                }
+            }
+            Some(request) = tx_receiver.next(), if writer.is_some() => {
+                if let Some(writer) = &mut writer {
+                    if let Err(e) = writer.handle(request).await {
+                        log::error!("Cannot transmit: {}", e);
+                    }
+                }
+            }
+        }
     }
 
     Ok(())
 }
 
-/// Create a tokio runtime and run the can_read_task
-fn can_read_thread_fun(can_interface: String, rx_sender: UnboundedSender<Frame>) {
-    tokio::runtime::Builder::new_multi_thread()
+/// Create a tokio runtime and run the can_read_task. Returns false if the
+/// interface cannot be opened or read, after logging the error.
+fn can_read_thread_fun(
+    can_interface: String,
+    playback: Playback,
+    record: Option<PathBuf>,
+    rx_sender: UnboundedSender<Frame>,
+    tx_receiver: UnboundedReceiver<TxRequest>,
+) -> bool {
+    let name = can_interface.clone();
+    let result = tokio::runtime::Builder::new_multi_thread()
         .enable_all()
         .build()
         .unwrap()
-        .block_on(async {
-            can_read_task(can_interface, rx_sender).await.unwrap();
-        })
+        .block_on(can_read_task(
+            can_interface,
+            playback,
+            record,
+            rx_sender,
+            tx_receiver,
+        ));
+
+    match result {
+        Ok(()) => true,
+        Err(e) => {
+            log::error!("Cannot read {}: {}", name, e);
+            false
+        }
+    }
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let cli = Cli::parse();
+    // Decoding options used only by the desktop window
+    let gui = cli.frontend.is_gui() && cli.command.is_none();
+    let gui_only = [
+        ("--dbc", cli.dbc.is_some()),
+        ("--isotp", !cli.isotp.is_empty()),
+    ];
+    if let Some((arg, _)) = gui_only.into_iter().find(|&(_, set)| set && !gui) {
+        Cli::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                format!("{} is only supported by the desktop window", arg),
+            )
+            .exit();
+    }
+    let frontend = cli.frontend;
+    let interval = Duration::from_millis(cli.interval);
     let can_interface = cli.can_interface;
+    let record = cli.record;
+    let window = cli.window;
+    let playback = Playback {
+        speed: cli.speed,
+        looping: cli.looping,
+    };
 
     setup_env_logger();
 
+    let expectations = match &cli.command {
+        Some(Command::Assert { expectations, .. }) => match Expectations::load(expectations) {
+            Ok(expectations) => Some(expectations),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(2);
+            }
+        },
+        None => None,
+    };
+
     let (rx_sender, rx_receiver) = unbounded::<Frame>();
+    let (tx_sender, tx_receiver) = unbounded::<TxRequest>();
 
-    std::thread::spawn(move || {
-        let _ = can_read_thread_fun(can_interface, rx_sender);
+    let reader = std::thread::spawn(move || {
+        can_read_thread_fun(can_interface, playback, record, rx_sender, tx_receiver)
     });
 
-    launch(rx_receiver);
+    if let Some(expectations) = expectations {
+        drop(tx_sender);
+        let duration = match cli.command {
+            Some(Command::Assert {
+                duration: Some(duration),
+                ..
+            }) => Some(duration),
+            _ => expectations.duration(),
+        };
+        let mut rx_receiver = rx_receiver;
+        let stats = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?
+            .block_on(stream::collect_stats(&mut rx_receiver, duration, window));
+        // The reader has stopped: a failure is not a check failing
+        if rx_receiver.is_terminated() && !reader.join().unwrap_or(false) {
+            std::process::exit(2);
+        }
+        let report = expectations.check(&stats);
+        println!("{}", report);
+        std::process::exit(report.exit_code());
+    }
+
+    match frontend {
+        #[cfg(feature = "gui")]
+        Frontend::Gui => {
+            let dbc = match &cli.dbc {
+                Some(path) => canbusnoop_dbc::Dbc::load(path)
+                    .map_err(|e| format!("cannot load {}: {}", path.display(), e))?,
+                None => Default::default(),
+            };
+            canbusnoop_ui::launch(rx_receiver, tx_sender, dbc, cli.isotp, window)
+        }
+        Frontend::Tui => {
+            // The terminal interface does not transmit
+            drop(tx_sender);
+            canbusnoop_tui::launch(rx_receiver, window)?
+        }
+        Frontend::Text | Frontend::Json => {
+            drop(tx_sender);
+            let result = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()?
+                .block_on(async {
+                    match frontend {
+                        Frontend::Json => stream::print_json(rx_receiver).await,
+                        _ => stream::print_stats(rx_receiver, interval, window).await,
+                    }
+                });
+            match result {
+                // The reader of the pipe (e.g. head) is gone, not an error
+                Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => {}
+                result => result?,
+            }
+        }
+    }
 
     Ok(())
 }
@@ -62,7 +215,97 @@ fn setup_env_logger() {
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 struct Cli {
-    /// CAN bus interface
+    /// CAN bus interface: a SocketCAN interface name (e.g. vcan0),
+    /// socketcan:NAME, file:PATH or demo
     #[arg(short = 'i', long, default_value = "demo")]
     can_interface: String,
+
+    /// Replay speed of a file: 1 for real-time, N for N times faster, max for
+    /// as fast as possible
+    #[arg(long, default_value = "1")]
+    speed: Speed,
+
+    /// Restart replaying a file when the end is reached
+    #[arg(long = "loop")]
+    looping: bool,
+
+    /// Record captured traffic to a file: .log (candump), .asc (Vector ASC),
+    /// .pcap or .pcapng
+    #[arg(short = 'r', long)]
+    record: Option<PathBuf>,
+
+    /// DBC file used to decode messages and signals in the desktop window
+    #[arg(long)]
+    dbc: Option<PathBuf>,
+
+    /// ISO-TP request and response ids (hex) of a tester and an ECU, to
+    /// decode their UDS messages in the desktop window. Addressing is
+    /// normal, extended or mixed, e.g. 7E0:7E8 or 6F1:612,extended. Can be
+    /// repeated.
+    #[arg(long, value_name = "REQUEST:RESPONSE[,ADDRESSING]")]
+    isotp: Vec<IsoTpChannel>,
+
+    /// User interface
+    #[arg(long, value_enum, default_value_t)]
+    frontend: Frontend,
+
+    /// Frames considered by the period statistics of each id: a number of
+    /// frames (e.g. 100) or a time (e.g. 500ms, 2s)
+    #[arg(long, default_value_t)]
+    window: Window,
+
+    /// Milliseconds between two statistics snapshots with `--frontend text`
+    #[arg(long, default_value_t = 1000, value_parser = clap::value_parser!(u64).range(1..))]
+    interval: u64,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Listen to the bus, then check the traffic against an expectations
+    /// file. Exits with status 1 if any check fails, 2 if the expectations
+    /// file is not valid or the bus cannot be read.
+    Assert {
+        /// Expectations file (TOML)
+        expectations: PathBuf,
+
+        /// Seconds to listen, overrides the duration in the expectations file
+        #[arg(long, value_parser = parse_duration)]
+        duration: Option<Duration>,
+    },
+}
+
+/// Seconds, e.g. 10 or 0.5
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    s.parse()
+        .ok()
+        .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+        .ok_or_else(|| format!("invalid duration: {}", s))
+}
+
+#[derive(Clone, Copy, Default, ValueEnum)]
+enum Frontend {
+    /// Desktop window
+    #[cfg(feature = "gui")]
+    #[cfg_attr(feature = "gui", default)]
+    Gui,
+    /// Terminal, for headless hosts and remote sessions
+    #[cfg_attr(not(feature = "gui"), default)]
+    Tui,
+    /// Statistics snapshots printed to stdout
+    Text,
+    /// Every frame printed to stdout as a line of JSON
+    Json,
+}
+
+impl Frontend {
+    fn is_gui(self) -> bool {
+        match self {
+            #[cfg(feature = "gui")]
+            Frontend::Gui => true,
+            _ => false,
+        }
+    }
 }
@@ -0,0 +1,99 @@
+//! Non-interactive output to stdout, for scripts and log collectors
+
+use canbusnoop_core::{Direction, Frame};
+use canbusnoop_db::{MultiStats, Window};
+use futures_channel::mpsc::UnboundedReceiver;
+use futures_util::StreamExt;
+use std::io::{self, Write};
+use std::time::Duration;
+
+/// Print a `MultiStats` snapshot every `interval`, and a last one when the
+/// reader stops
+pub(crate) async fn print_stats(
+    mut rx_receiver: UnboundedReceiver<Frame>,
+    interval: Duration,
+    window: Window,
+) -> io::Result<()> {
+    let mut stats = MultiStats::new(window);
+    let mut ticker = tokio::time::interval(interval);
+    // The first tick completes immediately, there is nothing to print yet
+    ticker.tick().await;
+
+    loop {
+        tokio::select! {
+            frame = rx_receiver.next() => match frame {
+                Some(frame) => stats.push(frame),
+                None => break,
+            },
+            _ = ticker.tick() => write_stats(&stats)?,
+        }
+    }
+
+    write_stats(&stats)
+}
+
+fn write_stats(stats: &MultiStats) -> io::Result<()> {
+    writeln!(io::stdout().lock(), "{}", stats)
+}
+
+/// Collect statistics for `duration`, or until the reader stops
+pub(crate) async fn collect_stats(
+    rx_receiver: &mut UnboundedReceiver<Frame>,
+    duration: Option<Duration>,
+    window: Window,
+) -> MultiStats {
+    let mut stats = MultiStats::new(window);
+    let timeout = async {
+        match duration {
+            Some(duration) => tokio::time::sleep(duration).await,
+            None => std::future::pending().await,
+        }
+    };
+    tokio::pin!(timeout);
+
+    loop {
+        tokio::select! {
+            frame = rx_receiver.next() => match frame {
+                Some(frame) => stats.push(frame),
+                None => break,
+            },
+            _ = &mut timeout => break,
+        }
+    }
+
+    stats
+}
+
+/// Print every frame as a line of JSON
+pub(crate) async fn print_json(mut rx_receiver: UnboundedReceiver<Frame>) -> io::Result<()> {
+    while let Some(frame) = rx_receiver.next().await {
+        // stdout is line buffered, each frame reaches the pipe immediately
+        writeln!(io::stdout(), "{}", frame_to_json(&frame))?;
+    }
+
+    Ok(())
+}
+
+fn frame_to_json(frame: &Frame) -> serde_json::Value {
+    let id = frame.id();
+    let fd = frame.fd_flags();
+    let data: String = frame.data().iter().map(|b| format!("{:02x}", b)).collect();
+    let direction = match frame.direction() {
+        Direction::Rx => "rx",
+        Direction::Tx => "tx",
+    };
+
+    serde_json::json!({
+        "timestamp": frame.timestamp().as_secs_f64(),
+        "interface": frame.interface(),
+        "id": id.id(),
+        "extended": id.is_extended(),
+        "rtr": id.is_rtr(),
+        "error": id.is_error(),
+        "fd": fd.is_some(),
+        "brs": fd.map(|f| f.brs).unwrap_or_default(),
+        "esi": fd.map(|f| f.esi).unwrap_or_default(),
+        "direction": direction,
+        "data": data,
+    })
+}
@@ -0,0 +1,354 @@
+use crate::Frame;
+use std::fmt::Display;
+
+// Error classes, in the id of an error frame (linux/can/error.h)
+const CAN_ERR_TX_TIMEOUT: u32 = 0x0000_0001;
+const CAN_ERR_LOSTARB: u32 = 0x0000_0002;
+const CAN_ERR_CRTL: u32 = 0x0000_0004;
+const CAN_ERR_PROT: u32 = 0x0000_0008;
+const CAN_ERR_TRX: u32 = 0x0000_0010;
+const CAN_ERR_ACK: u32 = 0x0000_0020;
+const CAN_ERR_BUSOFF: u32 = 0x0000_0040;
+const CAN_ERR_BUSERROR: u32 = 0x0000_0080;
+const CAN_ERR_RESTARTED: u32 = 0x0000_0100;
+const CAN_ERR_CNT: u32 = 0x0000_0200;
+
+/// Bus error, decoded from an error frame
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct BusError {
+    /// TX timeout (by netdevice driver)
+    pub tx_timeout: bool,
+
+    /// Lost arbitration, with the bit number in the bitstream (0 if unknown)
+    pub lost_arbitration: Option<u8>,
+
+    /// Controller problems
+    pub controller: Option<ControllerProblem>,
+
+    /// Protocol violations
+    pub protocol: Option<ProtocolViolation>,
+
+    /// Transceiver status, as reported by the driver
+    pub transceiver: Option<u8>,
+
+    /// Received no ACK on transmission
+    pub no_ack: bool,
+
+    /// Bus off
+    pub bus_off: bool,
+
+    /// Bus error (may flood!)
+    pub bus_error: bool,
+
+    /// Controller restarted
+    pub restarted: bool,
+
+    /// TX and RX error counters
+    pub counters: Option<ErrorCounters>,
+}
+
+/// Controller problems, reported in the second byte of an error frame
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ControllerProblem {
+    pub rx_overflow: bool,
+    pub tx_overflow: bool,
+    pub rx_warning: bool,
+    pub tx_warning: bool,
+    pub rx_passive: bool,
+    pub tx_passive: bool,
+    /// Recovered to error active state
+    pub active: bool,
+}
+
+/// Protocol violation, reported in the third and fourth byte of an error frame
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ProtocolViolation {
+    /// Single bit error
+    pub bit: bool,
+    /// Frame format error
+    pub form: bool,
+    /// Bit stuffing error
+    pub stuff: bool,
+    /// Unable to send dominant bit
+    pub bit0: bool,
+    /// Unable to send recessive bit
+    pub bit1: bool,
+    /// Bus overload
+    pub overload: bool,
+    /// Active error announcement
+    pub active: bool,
+    /// Error occurred on transmission
+    pub tx: bool,
+    /// Location in the frame, see linux/can/error.h
+    pub location: u8,
+}
+
+/// Transmit and receive error counters
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ErrorCounters {
+    pub tx: u8,
+    pub rx: u8,
+}
+
+impl BusError {
+    /// Decode an error frame. Returns `None` if `frame` is not an error frame.
+    pub fn from_frame(frame: &Frame) -> Option<BusError> {
+        let id = frame.id();
+
+        if !id.is_error() {
+            return None;
+        }
+
+        let class = id.id();
+        let data = frame.data();
+        let byte = |i: usize| data.get(i).copied().unwrap_or(0);
+        let has = |flag: u32| class & flag != 0;
+
+        let controller = has(CAN_ERR_CRTL).then(|| {
+            let b = byte(1);
+            ControllerProblem {
+                rx_overflow: b & 0x01 != 0,
+                tx_overflow: b & 0x02 != 0,
+                rx_warning: b & 0x04 != 0,
+                tx_warning: b & 0x08 != 0,
+                rx_passive: b & 0x10 != 0,
+                tx_passive: b & 0x20 != 0,
+                active: b & 0x40 != 0,
+            }
+        });
+
+        let protocol = has(CAN_ERR_PROT).then(|| {
+            let b = byte(2);
+            ProtocolViolation {
+                bit: b & 0x01 != 0,
+                form: b & 0x02 != 0,
+                stuff: b & 0x04 != 0,
+                bit0: b & 0x08 != 0,
+                bit1: b & 0x10 != 0,
+                overload: b & 0x20 != 0,
+                active: b & 0x40 != 0,
+                tx: b & 0x80 != 0,
+                location: byte(3),
+            }
+        });
+
+        let counters = has(CAN_ERR_CNT).then(|| ErrorCounters {
+            tx: byte(6),
+            rx: byte(7),
+        });
+
+        Some(BusError {
+            tx_timeout: has(CAN_ERR_TX_TIMEOUT),
+            lost_arbitration: has(CAN_ERR_LOSTARB).then(|| byte(0)),
+            controller,
+            protocol,
+            transceiver: has(CAN_ERR_TRX).then(|| byte(4)),
+            no_ack: has(CAN_ERR_ACK),
+            bus_off: has(CAN_ERR_BUSOFF),
+            bus_error: has(CAN_ERR_BUSERROR),
+            restarted: has(CAN_ERR_RESTARTED),
+            counters,
+        })
+    }
+}
+
+impl ControllerProblem {
+    /// Returns true if the controller is in error passive state
+    pub fn is_passive(&self) -> bool {
+        self.rx_passive || self.tx_passive
+    }
+
+    /// Returns true if the controller reached the error warning level
+    pub fn is_warning(&self) -> bool {
+        self.rx_warning || self.tx_warning
+    }
+
+    /// Returns true if a buffer overflowed
+    pub fn is_overflow(&self) -> bool {
+        self.rx_overflow || self.tx_overflow
+    }
+}
+
+impl ProtocolViolation {
+    /// Returns a human readable name of the location in the frame
+    pub fn location_name(&self) -> &'static str {
+        match self.location {
+            0x03 => "start of frame",
+            0x02 => "ID bits 28-21",
+            0x06 => "ID bits 20-18",
+            0x04 => "substitute RTR",
+            0x05 => "identifier extension",
+            0x07 => "ID bits 17-13",
+            0x0F => "ID bits 12-5",
+            0x0E => "ID bits 4-0",
+            0x0C => "RTR",
+            0x0D => "reserved bit 1",
+            0x09 => "reserved bit 0",
+            0x0B => "data length code",
+            0x0A => "data section",
+            0x08 => "CRC sequence",
+            0x18 => "CRC delimiter",
+            0x19 => "ACK slot",
+            0x1B => "ACK delimiter",
+            0x1A => "end of frame",
+            0x12 => "intermission",
+            _ => "unspecified",
+        }
+    }
+}
+
+impl Display for BusError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut parts: Vec<String> = Vec::new();
+
+        if self.bus_off {
+            parts.push("bus off".into());
+        }
+        if let Some(c) = &self.controller {
+            if c.is_passive() {
+                parts.push("error passive".into());
+            } else if c.is_warning() {
+                parts.push("error warning".into());
+            }
+            if c.is_overflow() {
+                parts.push("overflow".into());
+            }
+            if c.active {
+                parts.push("error active".into());
+            }
+        }
+        if let Some(p) = &self.protocol {
+            parts.push(format!("protocol violation at {}", p.location_name()));
+        }
+        if let Some(bit) = self.lost_arbitration {
+            parts.push(format!("lost arbitration at bit {}", bit));
+        }
+        if self.no_ack {
+            parts.push("no ACK".into());
+        }
+        if self.tx_timeout {
+            parts.push("TX timeout".into());
+        }
+        if let Some(t) = self.transceiver {
+            parts.push(format!("transceiver 0x{:02X}", t));
+        }
+        if self.bus_error {
+            parts.push("bus error".into());
+        }
+        if self.restarted {
+            parts.push("restarted".into());
+        }
+        if let Some(c) = &self.counters {
+            parts.push(format!("TEC={} REC={}", c.tx, c.rx));
+        }
+
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CanId;
+    use std::time::Duration;
+
+    // Details of the error classes (linux/can/error.h)
+    const CAN_ERR_CRTL_RX_PASSIVE: u8 = 0x10;
+    const CAN_ERR_CRTL_TX_WARNING: u8 = 0x08;
+    const CAN_ERR_CRTL_ACTIVE: u8 = 0x40;
+    const CAN_ERR_PROT_STUFF: u8 = 0x04;
+    const CAN_ERR_PROT_TX: u8 = 0x80;
+    const CAN_ERR_PROT_LOC_ACK: u8 = 0x19;
+    const CAN_ERR_TRX_CANH_SHORT_TO_GND: u8 = 0x05;
+
+    fn error_frame(class: u32, data: [u8; 8]) -> Frame {
+        let id = CanId::from_raw(CanId::ERR_FLAG | class);
+        Frame::new(id, data.to_vec(), Duration::ZERO)
+    }
+
+    #[test]
+    fn not_an_error_frame() {
+        let frame = Frame::new(CanId::standard(0x004).unwrap(), vec![0; 8], Duration::ZERO);
+        assert_eq!(BusError::from_frame(&frame), None);
+    }
+
+    #[test]
+    fn controller_problems() {
+        let data = [
+            0,
+            CAN_ERR_CRTL_RX_PASSIVE | CAN_ERR_CRTL_TX_WARNING,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+        ];
+        let error = BusError::from_frame(&error_frame(CAN_ERR_CRTL, data)).unwrap();
+        let controller = error.controller.unwrap();
+        assert!(controller.rx_passive && controller.tx_warning);
+        assert!(controller.is_passive() && controller.is_warning());
+        assert!(!controller.is_overflow());
+        assert_eq!(error.to_string(), "error passive");
+
+        let data = [0, CAN_ERR_CRTL_ACTIVE, 0, 0, 0, 0, 0, 0];
+        let error = BusError::from_frame(&error_frame(CAN_ERR_CRTL, data)).unwrap();
+        assert_eq!(error.to_string(), "error active");
+    }
+
+    #[test]
+    fn protocol_violation() {
+        let data = [
+            0,
+            0,
+            CAN_ERR_PROT_STUFF | CAN_ERR_PROT_TX,
+            CAN_ERR_PROT_LOC_ACK,
+            0,
+            0,
+            0,
+            0,
+        ];
+        let error = BusError::from_frame(&error_frame(CAN_ERR_PROT, data)).unwrap();
+        let protocol = error.protocol.unwrap();
+        assert!(protocol.stuff && protocol.tx);
+        assert!(!protocol.bit && !protocol.form);
+        assert_eq!(protocol.location_name(), "ACK slot");
+        assert_eq!(error.controller, None);
+        assert_eq!(error.to_string(), "protocol violation at ACK slot");
+    }
+
+    #[test]
+    fn classes_and_counters() {
+        let class = CAN_ERR_LOSTARB
+            | CAN_ERR_TRX
+            | CAN_ERR_ACK
+            | CAN_ERR_BUSOFF
+            | CAN_ERR_TX_TIMEOUT
+            | CAN_ERR_BUSERROR
+            | CAN_ERR_RESTARTED
+            | CAN_ERR_CNT;
+        let data = [7, 0, 0, 0, CAN_ERR_TRX_CANH_SHORT_TO_GND, 0, 128, 5];
+        let error = BusError::from_frame(&error_frame(class, data)).unwrap();
+        assert_eq!(error.lost_arbitration, Some(7));
+        assert_eq!(error.transceiver, Some(CAN_ERR_TRX_CANH_SHORT_TO_GND));
+        assert!(error.no_ack && error.bus_off && error.tx_timeout);
+        assert!(error.bus_error && error.restarted);
+        assert_eq!(error.counters, Some(ErrorCounters { tx: 128, rx: 5 }));
+        assert_eq!(
+            error.to_string(),
+            "bus off, lost arbitration at bit 7, no ACK, TX timeout, transceiver 0x05, \
+             bus error, restarted, TEC=128 REC=5"
+        );
+    }
+
+    #[test]
+    fn short_error_frames() {
+        let frame = Frame::new(
+            CanId::from_raw(CanId::ERR_FLAG | CAN_ERR_CNT),
+            vec![],
+            Duration::ZERO,
+        );
+        let error = BusError::from_frame(&frame).unwrap();
+        assert_eq!(error.counters, Some(ErrorCounters { tx: 0, rx: 0 }));
+    }
+}
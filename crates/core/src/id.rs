@@ -0,0 +1,208 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// CAN identifier, standard (11 bit) or extended (29 bit), with the RTR and
+/// ERR flags.
+///
+/// The raw representation is the same used by SocketCAN: the identifier in the
+/// lower bits, plus the EFF/RTR/ERR flags in the upper three bits. Ordering
+/// follows the raw value, so standard ids come before extended ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct CanId(u32);
+
+impl CanId {
+    /// Extended frame format flag
+    pub const EFF_FLAG: u32 = 0x8000_0000;
+
+    /// Remote transmission request flag
+    pub const RTR_FLAG: u32 = 0x4000_0000;
+
+    /// Error frame flag
+    pub const ERR_FLAG: u32 = 0x2000_0000;
+
+    /// Mask of a standard (11 bit) identifier
+    pub const SFF_MASK: u32 = 0x0000_07FF;
+
+    /// Mask of an extended (29 bit) identifier
+    pub const EFF_MASK: u32 = 0x1FFF_FFFF;
+
+    /// Create a CAN identifier from the 32 bit CAN_ID + EFF/RTR/ERR flags
+    pub fn from_raw(raw: u32) -> CanId {
+        if raw & Self::EFF_FLAG != 0 {
+            CanId(raw & (Self::EFF_FLAG | Self::RTR_FLAG | Self::ERR_FLAG | Self::EFF_MASK))
+        } else {
+            CanId(raw & (Self::RTR_FLAG | Self::ERR_FLAG | Self::SFF_MASK))
+        }
+    }
+
+    /// Create a standard (11 bit) identifier. Returns `None` if `id` does not
+    /// fit in 11 bits.
+    pub fn standard(id: u16) -> Option<CanId> {
+        let id = id as u32;
+        (id <= Self::SFF_MASK).then_some(CanId(id))
+    }
+
+    /// Create an extended (29 bit) identifier. Returns `None` if `id` does not
+    /// fit in 29 bits.
+    pub fn extended(id: u32) -> Option<CanId> {
+        (id <= Self::EFF_MASK).then_some(CanId(id | Self::EFF_FLAG))
+    }
+
+    /// Returns the same identifier, with the RTR flag set or cleared
+    pub fn with_rtr(self, rtr: bool) -> CanId {
+        if rtr {
+            CanId(self.0 | Self::RTR_FLAG)
+        } else {
+            CanId(self.0 & !Self::RTR_FLAG)
+        }
+    }
+
+    /// Returns the 32 bit CAN_ID + EFF/RTR/ERR flags
+    pub fn raw(&self) -> u32 {
+        self.0
+    }
+
+    /// Returns the 11 or 29 bit identifier, without flags
+    pub fn id(&self) -> u32 {
+        if self.is_extended() {
+            self.0 & Self::EFF_MASK
+        } else {
+            self.0 & Self::SFF_MASK
+        }
+    }
+
+    /// Returns true if this is an extended (29 bit) identifier
+    pub fn is_extended(&self) -> bool {
+        self.0 & Self::EFF_FLAG != 0
+    }
+
+    /// Returns true if this is the identifier of a remote transmission request
+    pub fn is_rtr(&self) -> bool {
+        self.0 & Self::RTR_FLAG != 0
+    }
+
+    /// Returns true if this is the identifier of an error frame
+    pub fn is_error(&self) -> bool {
+        self.0 & Self::ERR_FLAG != 0
+    }
+}
+
+/// Formats the identifier as hex, 3 digits for standard ids and 8 digits for
+/// extended ids. Flags are not included.
+impl Display for CanId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Padded as a whole, so width and alignment apply to the id
+        let s = if self.is_extended() {
+            format!("{:08X}", self.id())
+        } else {
+            format!("{:03X}", self.id())
+        };
+        f.pad(&s)
+    }
+}
+
+/// Parses the identifier from hex, with an optional `0x` prefix. Like
+/// [`Display`], more than 3 digits make an extended id, as do values that do
+/// not fit in 11 bits.
+impl FromStr for CanId {
+    type Err = ParseCanIdError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParseCanIdError(s.to_string());
+        let digits = s.trim();
+        let digits = digits
+            .strip_prefix("0x")
+            .or_else(|| digits.strip_prefix("0X"))
+            .unwrap_or(digits);
+        let id = u32::from_str_radix(digits, 16).map_err(|_| err())?;
+        if digits.len() > 3 || id > Self::SFF_MASK {
+            CanId::extended(id).ok_or_else(err)
+        } else {
+            Ok(CanId(id))
+        }
+    }
+}
+
+/// Error returned when a string is not a valid CAN identifier
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseCanIdError(String);
+
+impl Display for ParseCanIdError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid CAN id: {:?}", self.0)
+    }
+}
+
+impl std::error::Error for ParseCanIdError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_raw_flags() {
+        let id = CanId::from_raw(0x8000_0123);
+        assert!(id.is_extended());
+        assert_eq!(id.id(), 0x123);
+        assert!(!id.is_rtr() && !id.is_error());
+
+        let id = CanId::from_raw(0x4000_0123);
+        assert!(!id.is_extended());
+        assert!(id.is_rtr());
+
+        let id = CanId::from_raw(0x2000_0004);
+        assert!(id.is_error());
+        assert_eq!(id.id(), 0x004);
+
+        // Standard ids keep 11 bits
+        assert_eq!(CanId::from_raw(0x0000_1FFF).id(), 0x7FF);
+        assert_eq!(CanId::from_raw(0xFFFF_FFFF).id(), 0x1FFF_FFFF);
+    }
+
+    #[test]
+    fn constructors() {
+        assert_eq!(CanId::standard(0x7FF).map(|id| id.raw()), Some(0x7FF));
+        assert_eq!(CanId::standard(0x800), None);
+        assert_eq!(
+            CanId::extended(0x1FFF_FFFF).map(|id| id.raw()),
+            Some(0x9FFF_FFFF)
+        );
+        assert_eq!(CanId::extended(0x2000_0000), None);
+
+        let rtr = CanId::standard(0x123).unwrap().with_rtr(true);
+        assert!(rtr.is_rtr());
+        assert!(!rtr.with_rtr(false).is_rtr());
+    }
+
+    #[test]
+    fn parses_hex() {
+        assert_eq!("123".parse(), Ok(CanId::standard(0x123).unwrap()));
+        assert_eq!("0x7FF".parse(), Ok(CanId::standard(0x7FF).unwrap()));
+        assert_eq!("0100".parse(), Ok(CanId::extended(0x100).unwrap()));
+        assert_eq!(
+            "18FEF100".parse(),
+            Ok(CanId::extended(0x18FE_F100).unwrap())
+        );
+        assert!("3FFFFFFF".parse::<CanId>().is_err());
+        assert!("".parse::<CanId>().is_err());
+        assert!("xyz".parse::<CanId>().is_err());
+    }
+
+    #[test]
+    fn standard_and_extended_are_distinct() {
+        let standard = CanId::standard(0x100).unwrap();
+        let extended = CanId::extended(0x100).unwrap();
+        assert_eq!(standard.id(), extended.id());
+        assert_ne!(standard, extended);
+        assert!(standard < extended);
+        assert_eq!(standard.to_string(), "100");
+        assert_eq!(extended.to_string(), "00000100");
+    }
+
+    #[test]
+    fn display_pads() {
+        let id = CanId::standard(0x12).unwrap();
+        assert_eq!(format!("{:>5}|", id), "  012|");
+        assert_eq!(format!("{:<5}|", id), "012  |");
+    }
+}
@@ -0,0 +1,194 @@
+use crate::CanId;
+use std::fmt::Display;
+
+/// The fields of a J1939 identifier (29 bit):
+///
+/// ```text
+/// | priority (3) | EDP (1) | DP (1) | PF (8) | PS (8) | SA (8) |
+/// ```
+///
+/// When PF < 240 (PDU1) PS is the destination address, otherwise (PDU2) it
+/// is the group extension and part of the PGN.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct J1939Id {
+    raw: u32,
+}
+
+impl J1939Id {
+    /// Decode an extended CAN identifier. Returns `None` for standard ids and
+    /// error frames.
+    pub fn from_can_id(id: CanId) -> Option<J1939Id> {
+        (id.is_extended() && !id.is_error()).then_some(J1939Id { raw: id.id() })
+    }
+
+    /// Priority, 0 is the highest
+    pub fn priority(&self) -> u8 {
+        ((self.raw >> 26) & 0x7) as u8
+    }
+
+    /// Extended data page
+    pub fn edp(&self) -> bool {
+        (self.raw >> 25) & 1 != 0
+    }
+
+    /// Data page
+    pub fn dp(&self) -> bool {
+        (self.raw >> 24) & 1 != 0
+    }
+
+    /// PDU format (PF)
+    pub fn pdu_format(&self) -> u8 {
+        (self.raw >> 16) as u8
+    }
+
+    /// PDU specific (PS): destination address or group extension
+    pub fn pdu_specific(&self) -> u8 {
+        (self.raw >> 8) as u8
+    }
+
+    pub fn source_address(&self) -> u8 {
+        self.raw as u8
+    }
+
+    /// PDU1 messages are sent to a specific destination
+    pub fn is_pdu1(&self) -> bool {
+        self.pdu_format() < 240
+    }
+
+    /// Destination address, only for PDU1 messages
+    pub fn destination_address(&self) -> Option<u8> {
+        self.is_pdu1().then(|| self.pdu_specific())
+    }
+
+    /// Group extension, only for PDU2 messages
+    pub fn group_extension(&self) -> Option<u8> {
+        (!self.is_pdu1()).then(|| self.pdu_specific())
+    }
+
+    /// Parameter group number. The destination address of PDU1 messages is
+    /// not part of it.
+    pub fn pgn(&self) -> Pgn {
+        let ps = self.group_extension().unwrap_or(0) as u32;
+        let page = (self.raw >> 24) & 0x3;
+        Pgn((page << 16) | ((self.pdu_format() as u32) << 8) | ps)
+    }
+}
+
+/// Parameter group number, 18 bit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Pgn(u32);
+
+impl Pgn {
+    pub fn new(pgn: u32) -> Pgn {
+        Pgn(pgn & 0x3_FFFF)
+    }
+
+    pub fn value(&self) -> u32 {
+        self.0
+    }
+
+    /// Acronym of well known parameter groups
+    pub fn name(&self) -> Option<&'static str> {
+        let name = match self.0 {
+            0 => "TSC1",
+            256 => "TC1",
+            57344 => "CM1",
+            59392 => "ACK",
+            59904 => "RQST",
+            60160 => "TP.DT",
+            60416 => "TP.CM",
+            60928 => "AC",
+            61184 => "PropA",
+            61440 => "ERC1",
+            61441 => "EBC1",
+            61442 => "ETC1",
+            61443 => "EEC2",
+            61444 => "EEC1",
+            61445 => "ETC2",
+            64965 => "ECUID",
+            65132 => "TCO1",
+            65215 => "EBC2",
+            65217 => "VDHR",
+            65226 => "DM1",
+            65227 => "DM2",
+            65228 => "DM3",
+            65242 => "SOFT",
+            65247 => "EEC3",
+            65248 => "VD",
+            65253 => "HOURS",
+            65254 => "TD",
+            65257 => "LFC",
+            65259 => "CI",
+            65260 => "VI",
+            65262 => "ET1",
+            65263 => "EFL/P1",
+            65265 => "CCVS",
+            65266 => "LFE",
+            65269 => "AMB",
+            65270 => "IC1",
+            65271 => "VEP1",
+            65272 => "TRF1",
+            65276 => "DD",
+            65280..=65535 => "PropB",
+            _ => return None,
+        };
+        Some(name)
+    }
+}
+
+impl Display for Pgn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn j1939(id: u32) -> J1939Id {
+        J1939Id::from_can_id(CanId::extended(id).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn pdu1() {
+        let id = j1939(0x18EA_00F9);
+        assert!(id.is_pdu1());
+        assert_eq!(id.priority(), 6);
+        assert_eq!(id.pgn(), Pgn::new(59904));
+        assert_eq!(id.pgn().name(), Some("RQST"));
+        assert_eq!(id.destination_address(), Some(0x00));
+        assert_eq!(id.group_extension(), None);
+        assert_eq!(id.source_address(), 0xF9);
+    }
+
+    #[test]
+    fn pdu2() {
+        let id = j1939(0x18FE_F100);
+        assert!(!id.is_pdu1());
+        assert_eq!(id.pgn(), Pgn::new(65265));
+        assert_eq!(id.destination_address(), None);
+        assert_eq!(id.group_extension(), Some(0xF1));
+        assert_eq!(id.source_address(), 0x00);
+    }
+
+    #[test]
+    fn data_pages() {
+        let id = j1939(0x0D00_0110);
+        assert!(id.dp() && !id.edp());
+        assert_eq!(id.priority(), 3);
+        assert_eq!(id.pgn().value(), 0x1_0000);
+        assert_eq!(id.destination_address(), Some(0x01));
+
+        let id = j1939(0x0EFE_F100);
+        assert!(!id.dp() && id.edp());
+        assert_eq!(id.pgn().value(), 0x2_FEF1);
+    }
+
+    #[test]
+    fn not_j1939() {
+        assert_eq!(J1939Id::from_can_id(CanId::standard(0x100).unwrap()), None);
+        let error = CanId::from_raw(CanId::EFF_FLAG | CanId::ERR_FLAG | 0x100);
+        assert_eq!(J1939Id::from_can_id(error), None);
+    }
+}
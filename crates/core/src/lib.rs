@@ -1,20 +1,88 @@
+mod bus_error;
+mod id;
+mod j1939;
+
+pub use bus_error::{BusError, ControllerProblem, ErrorCounters, ProtocolViolation};
+pub use id::{CanId, ParseCanIdError};
+pub use j1939::{J1939Id, Pgn};
+use std::fmt::Display;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
 /// CAN Frame
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Frame {
-    /// 32 bit CAN_ID + EFF/RTR/ERR flags
-    id: u32,
+    /// CAN identifier, with EFF/RTR/ERR flags
+    id: CanId,
 
     /// buffer for data
     data: Vec<u8>,
+
+    /// capture time, since UNIX epoch
+    timestamp: Duration,
+
+    /// CAN FD flags, `None` for classic CAN frames
+    fd: Option<FdFlags>,
+
+    /// name of the interface the frame was captured on
+    interface: Option<Arc<str>>,
+
+    /// received or transmitted
+    direction: Direction,
+}
+
+/// Direction of a frame, as seen by the capturing node
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    #[default]
+    Rx,
+    Tx,
+}
+
+/// CAN FD frame flags
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FdFlags {
+    /// Bit rate switch, the data phase was sent at the higher bit rate
+    pub brs: bool,
+
+    /// Error state indicator, the transmitter is error passive
+    pub esi: bool,
+}
+
+/// `FD`, or `FD BRS` with bit rate switch
+impl Display for FdFlags {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(if self.brs { "FD BRS" } else { "FD" })
+    }
 }
 
 impl Frame {
-    pub fn new(id: u32, data: Vec<u8>) -> Frame {
-        Frame { id, data }
+    /// Create a classic CAN frame
+    pub fn new(id: CanId, data: Vec<u8>, timestamp: Duration) -> Frame {
+        Frame {
+            id,
+            data,
+            timestamp,
+            fd: None,
+            interface: None,
+            direction: Direction::Rx,
+        }
     }
 
-    /// Returns the 32 bit CAN_ID + EFF/RTR/ERR flags
-    pub fn id(&self) -> u32 {
+    /// Create a CAN FD frame
+    pub fn new_fd(id: CanId, data: Vec<u8>, flags: FdFlags, timestamp: Duration) -> Frame {
+        Frame {
+            id,
+            data,
+            timestamp,
+            fd: Some(flags),
+            interface: None,
+            direction: Direction::Rx,
+        }
+    }
+
+    /// Returns the CAN identifier
+    pub fn id(&self) -> CanId {
         self.id
     }
 
@@ -22,4 +90,68 @@ impl Frame {
     pub fn data(&self) -> &[u8] {
         &self.data
     }
+
+    /// Returns the data length code, the smallest one fitting the data.
+    /// `None` for messages reassembled from several frames, longer than a
+    /// CAN FD frame.
+    pub fn dlc(&self) -> Option<u8> {
+        FD_LENGTHS
+            .iter()
+            .position(|&n| n >= self.data.len())
+            .map(|dlc| dlc as u8)
+    }
+
+    /// Returns the capture time, since UNIX epoch
+    pub fn timestamp(&self) -> Duration {
+        self.timestamp
+    }
+
+    /// Set the capture time, since UNIX epoch
+    pub fn with_timestamp(mut self, timestamp: Duration) -> Frame {
+        self.timestamp = timestamp;
+        self
+    }
+
+    /// Returns true if this is a CAN FD frame
+    pub fn is_fd(&self) -> bool {
+        self.fd.is_some()
+    }
+
+    /// Returns the CAN FD flags, `None` for classic CAN frames
+    pub fn fd_flags(&self) -> Option<FdFlags> {
+        self.fd
+    }
+
+    /// Set the name of the interface the frame was captured on
+    pub fn with_interface(mut self, interface: Arc<str>) -> Frame {
+        self.interface = Some(interface);
+        self
+    }
+
+    /// Returns the name of the interface the frame was captured on
+    pub fn interface(&self) -> Option<&str> {
+        self.interface.as_deref()
+    }
+
+    /// Set the direction of the frame
+    pub fn with_direction(mut self, direction: Direction) -> Frame {
+        self.direction = direction;
+        self
+    }
+
+    /// Returns the direction of the frame, received or transmitted
+    pub fn direction(&self) -> Direction {
+        self.direction
+    }
+}
+
+/// Valid CAN FD payload lengths, indexed by DLC
+pub const FD_LENGTHS: [usize; 16] = [0, 1, 2, 3, 4, 5, 6, 7, 8, 12, 16, 20, 24, 32, 48, 64];
+
+/// Returns the current system time, since UNIX epoch. Used as a fallback when
+/// a reader cannot provide a better capture timestamp.
+pub fn now() -> Duration {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
 }
@@ -0,0 +1,235 @@
+use std::fmt::Display;
+
+/// Frames compared before a byte is classified as a counter or checksum
+const MIN_SAMPLES: usize = 16;
+
+/// Fraction of the frames which must match to classify a byte
+const MIN_RATIO: f64 = 0.9;
+
+/// What a byte of the payload looks like, from the values seen so far
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteKind {
+    /// Incremented by one at each frame
+    Counter,
+    /// The low nibble is incremented by one at each frame
+    NibbleCounter,
+    /// CRC-8 SAE J1850 of the other bytes
+    Crc8J1850,
+    /// XOR of the other bytes
+    Xor,
+}
+
+impl Display for ByteKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            ByteKind::Counter => "counter",
+            ByteKind::NibbleCounter => "nibble counter",
+            ByteKind::Crc8J1850 => "CRC-8 SAE J1850",
+            ByteKind::Xor => "XOR checksum",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// Per-bit change statistics of the payload of an id, to find signals in
+/// messages without a DBC. Bits are numbered from the least significant bit
+/// of the first byte.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct BitStats {
+    frames: usize,
+    last: Vec<u8>,
+    /// Frames compared with the previous one, by byte
+    samples: Vec<usize>,
+    /// Times each bit changed from the previous frame
+    toggles: Vec<usize>,
+    /// Times each byte was the previous one plus one
+    counter: Vec<usize>,
+    /// Times the low nibble of each byte was the previous one plus one
+    nibble_counter: Vec<usize>,
+    /// Times each byte was the CRC of the other bytes, only counted for
+    /// the first and last bytes
+    crc8: Vec<usize>,
+    /// Times each byte was the XOR of the other bytes
+    xor: Vec<usize>,
+}
+
+impl BitStats {
+    pub(crate) fn push(&mut self, data: &[u8]) {
+        self.frames += 1;
+
+        if data.len() > self.samples.len() {
+            let bytes = data.len();
+            self.samples.resize(bytes, 0);
+            self.toggles.resize(bytes * 8, 0);
+            self.counter.resize(bytes, 0);
+            self.nibble_counter.resize(bytes, 0);
+            self.crc8.resize(bytes, 0);
+            self.xor.resize(bytes, 0);
+        }
+
+        // Only the bytes of both payloads are compared
+        let previous = match self.frames {
+            1 => &[][..],
+            _ => &self.last[..],
+        };
+        for (i, (&b, &prev)) in data.iter().zip(previous).enumerate() {
+            self.samples[i] += 1;
+            let changed = b ^ prev;
+            for bit in 0..8 {
+                if changed & (1 << bit) != 0 {
+                    self.toggles[i * 8 + bit] += 1;
+                }
+            }
+            if b == prev.wrapping_add(1) {
+                self.counter[i] += 1;
+            }
+            if b & 0x0F == prev.wrapping_add(1) & 0x0F {
+                self.nibble_counter[i] += 1;
+            }
+        }
+
+        // A checksum is at the start or the end of the payload. A byte is the
+        // XOR of the others when the XOR of the whole payload is 0, which
+        // can't tell the start from the end.
+        if data.len() > 1 && data.iter().fold(0, |acc, b| acc ^ b) == 0 {
+            self.xor[0] += 1;
+            self.xor[data.len() - 1] += 1;
+        }
+        if let Some((&first, rest)) = data.split_first() {
+            if first == crc8_j1850(rest.iter().copied()) {
+                self.crc8[0] += 1;
+            }
+        }
+        if let Some((&last, rest)) = data.split_last().filter(|_| data.len() > 1) {
+            if last == crc8_j1850(rest.iter().copied()) {
+                self.crc8[data.len() - 1] += 1;
+            }
+        }
+
+        self.last = data.to_vec();
+    }
+
+    /// Number of frames
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+
+    /// Number of bytes, of the longest payload
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// Fraction of the frames in which the bit changed, from 0 to 1
+    pub fn toggle_rate(&self, bit: usize) -> f64 {
+        match self.samples.get(bit / 8) {
+            Some(&samples) if samples > 0 => self.toggles[bit] as f64 / samples as f64,
+            _ => 0.,
+        }
+    }
+
+    /// Returns true if the bit never changed, after two frames at least
+    pub fn is_constant(&self, bit: usize) -> bool {
+        self.samples.get(bit / 8).is_some_and(|&s| s > 0) && self.toggles[bit] == 0
+    }
+
+    /// Value of the bit in the last frame
+    pub fn value(&self, bit: usize) -> Option<bool> {
+        let byte = self.last.get(bit / 8)?;
+        Some(byte & (1 << (bit % 8)) != 0)
+    }
+
+    /// What the byte looks like, `None` if it doesn't look like a counter
+    /// or a checksum, or there are too few frames to tell
+    pub fn byte_kind(&self, byte: usize) -> Option<ByteKind> {
+        let samples = *self.samples.get(byte)?;
+        let constant = (0..8).all(|bit| self.is_constant(byte * 8 + bit));
+        if samples < MIN_SAMPLES || constant {
+            return None;
+        }
+
+        // A counter wrapping at 16 also looks like a byte counter most of
+        // the time, but its high nibble never changes
+        let high_nibble_constant = (4..8).all(|bit| self.is_constant(byte * 8 + bit));
+        let matches = |hits: usize, n: usize| hits as f64 >= n as f64 * MIN_RATIO;
+        if matches(self.counter[byte], samples) && !high_nibble_constant {
+            Some(ByteKind::Counter)
+        } else if matches(self.crc8[byte], self.frames) {
+            Some(ByteKind::Crc8J1850)
+        } else if matches(self.xor[byte], self.frames) {
+            Some(ByteKind::Xor)
+        } else if matches(self.nibble_counter[byte], samples) {
+            Some(ByteKind::NibbleCounter)
+        } else {
+            None
+        }
+    }
+}
+
+/// CRC-8 SAE J1850: polynomial 0x1D, initial value and final XOR 0xFF
+fn crc8_j1850(data: impl IntoIterator<Item = u8>) -> u8 {
+    let crc = data.into_iter().fold(0xFF, |crc, b| {
+        (0..8).fold(crc ^ b, |crc, _| match crc & 0x80 {
+            0 => crc << 1,
+            _ => (crc << 1) ^ 0x1D,
+        })
+    });
+    crc ^ 0xFF
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Payloads with a counter at byte 0 and varying bytes after it
+    fn payloads() -> impl Iterator<Item = Vec<u8>> {
+        (0..32u8).map(|i| vec![i, i.wrapping_mul(37), i ^ 0x5A, 0x10])
+    }
+
+    #[test]
+    fn crc8() {
+        // SAE J1850 check value
+        assert_eq!(crc8_j1850(*b"123456789"), 0x4B);
+    }
+
+    #[test]
+    fn finds_counters_and_checksums() {
+        let mut crc = BitStats::default();
+        let mut xor = BitStats::default();
+        for data in payloads() {
+            let mut with_crc = data.clone();
+            with_crc.push(crc8_j1850(data.iter().copied()));
+            crc.push(&with_crc);
+
+            let mut with_xor = data.clone();
+            with_xor.push(data.iter().fold(0, |acc, b| acc ^ b));
+            xor.push(&with_xor);
+        }
+
+        assert_eq!(crc.frames(), 32);
+        assert_eq!(crc.byte_kind(0), Some(ByteKind::Counter));
+        assert_eq!(crc.byte_kind(1), None);
+        // Constant
+        assert_eq!(crc.byte_kind(3), None);
+        assert!(crc.is_constant(3 * 8 + 4));
+        assert_eq!(crc.value(3 * 8 + 4), Some(true));
+        assert_eq!(crc.byte_kind(4), Some(ByteKind::Crc8J1850));
+        assert_eq!(xor.byte_kind(1), None);
+        assert_eq!(xor.byte_kind(2), None);
+        assert_eq!(xor.byte_kind(4), Some(ByteKind::Xor));
+    }
+
+    #[test]
+    fn finds_nibble_counters() {
+        let mut bits = BitStats::default();
+        for i in 0..32u8 {
+            bits.push(&[0xA0 | (i & 0x0F)]);
+        }
+        assert_eq!(bits.byte_kind(0), Some(ByteKind::NibbleCounter));
+        assert_eq!(bits.toggle_rate(0), 1.);
+        assert_eq!(bits.toggle_rate(7), 0.);
+    }
+}
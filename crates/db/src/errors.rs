@@ -0,0 +1,195 @@
+use canbusnoop_core::{BusError, ErrorCounters, Frame};
+use std::fmt::Display;
+use std::time::Duration;
+
+/// Counters of bus errors, decoded from error frames
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ErrorStats {
+    count: usize,
+    bus_off: usize,
+    error_passive: usize,
+    error_warning: usize,
+    overflow: usize,
+    protocol_violation: usize,
+    lost_arbitration: usize,
+    no_ack: usize,
+    tx_timeout: usize,
+    transceiver: usize,
+    bus_error: usize,
+    restarted: usize,
+    counters: Option<ErrorCounters>,
+    last_time: Option<Duration>,
+    last_error: Option<BusError>,
+}
+
+impl ErrorStats {
+    /// Total number of error frames
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn bus_off(&self) -> usize {
+        self.bus_off
+    }
+
+    pub fn error_passive(&self) -> usize {
+        self.error_passive
+    }
+
+    pub fn error_warning(&self) -> usize {
+        self.error_warning
+    }
+
+    pub fn overflow(&self) -> usize {
+        self.overflow
+    }
+
+    pub fn protocol_violation(&self) -> usize {
+        self.protocol_violation
+    }
+
+    pub fn lost_arbitration(&self) -> usize {
+        self.lost_arbitration
+    }
+
+    pub fn no_ack(&self) -> usize {
+        self.no_ack
+    }
+
+    pub fn tx_timeout(&self) -> usize {
+        self.tx_timeout
+    }
+
+    pub fn transceiver(&self) -> usize {
+        self.transceiver
+    }
+
+    pub fn bus_error(&self) -> usize {
+        self.bus_error
+    }
+
+    pub fn restarted(&self) -> usize {
+        self.restarted
+    }
+
+    /// Last reported TX/RX error counters
+    pub fn counters(&self) -> Option<ErrorCounters> {
+        self.counters
+    }
+
+    /// Capture time of the last error frame
+    pub fn last_time(&self) -> Option<Duration> {
+        self.last_time
+    }
+
+    /// Last decoded error
+    pub fn last_error(&self) -> Option<&BusError> {
+        self.last_error.as_ref()
+    }
+
+    pub(crate) fn push(&mut self, frame: &Frame) {
+        let Some(error) = BusError::from_frame(frame) else {
+            return;
+        };
+
+        log::debug!("{:?}", &error);
+
+        self.count += 1;
+        self.last_time = Some(frame.timestamp());
+
+        let count_if = |counter: &mut usize, cond: bool| {
+            if cond {
+                *counter += 1;
+            }
+        };
+
+        count_if(&mut self.bus_off, error.bus_off);
+        count_if(&mut self.no_ack, error.no_ack);
+        count_if(&mut self.tx_timeout, error.tx_timeout);
+        count_if(&mut self.bus_error, error.bus_error);
+        count_if(&mut self.restarted, error.restarted);
+        count_if(&mut self.lost_arbitration, error.lost_arbitration.is_some());
+        count_if(&mut self.protocol_violation, error.protocol.is_some());
+        count_if(&mut self.transceiver, error.transceiver.is_some());
+
+        if let Some(c) = &error.controller {
+            count_if(&mut self.error_passive, c.is_passive());
+            count_if(&mut self.error_warning, c.is_warning());
+            count_if(&mut self.overflow, c.is_overflow());
+        }
+
+        if error.counters.is_some() {
+            self.counters = error.counters;
+        }
+
+        self.last_error = Some(error);
+    }
+}
+
+impl Display for ErrorStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "errors={} bus-off={} passive={} warning={} overflow={} protocol={} arbitration={} no-ack={} tx-timeout={} transceiver={} bus-error={} restarted={}",
+            self.count,
+            self.bus_off,
+            self.error_passive,
+            self.error_warning,
+            self.overflow,
+            self.protocol_violation,
+            self.lost_arbitration,
+            self.no_ack,
+            self.tx_timeout,
+            self.transceiver,
+            self.bus_error,
+            self.restarted,
+        )?;
+
+        if let Some(c) = &self.counters {
+            write!(f, " TEC={} REC={}", c.tx, c.rx)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use canbusnoop_core::CanId;
+
+    fn error_frame(class: u32, data: [u8; 8]) -> Frame {
+        let id = CanId::from_raw(CanId::ERR_FLAG | class);
+        Frame::new(id, data.to_vec(), Duration::from_millis(10))
+    }
+
+    #[test]
+    fn counts_errors() {
+        let mut stats = ErrorStats::default();
+        // Controller problem (warning), with the error counters
+        stats.push(&error_frame(0x204, [0, 0x04, 0, 0, 0, 0, 97, 12]));
+        // Bus error, restarted
+        stats.push(&error_frame(0x180, [0; 8]));
+        // Transceiver
+        stats.push(&error_frame(0x010, [0, 0, 0, 0, 0x04, 0, 0, 0]));
+        // Not an error frame
+        stats.push(&Frame::new(
+            CanId::standard(0x10).unwrap(),
+            vec![],
+            Duration::ZERO,
+        ));
+
+        assert_eq!(stats.count(), 3);
+        assert_eq!(stats.error_warning(), 1);
+        assert_eq!(stats.bus_error(), 1);
+        assert_eq!(stats.restarted(), 1);
+        assert_eq!(stats.transceiver(), 1);
+        assert_eq!(stats.counters(), Some(ErrorCounters { tx: 97, rx: 12 }));
+        assert_eq!(stats.last_time(), Some(Duration::from_millis(10)));
+        assert_eq!(
+            stats.to_string(),
+            "errors=3 bus-off=0 passive=0 warning=1 overflow=0 protocol=0 arbitration=0 \
+             no-ack=0 tx-timeout=0 transceiver=1 bus-error=1 restarted=1 TEC=97 REC=12"
+        );
+    }
+}
@@ -1,23 +1,71 @@
-use canbusnoop_core::Frame;
+mod bits;
+mod errors;
+mod series;
+mod trace;
+mod window;
+
+pub use bits::{BitStats, ByteKind};
+pub use errors::ErrorStats;
+pub use series::{SeriesStore, TimeSeries};
+pub use trace::Trace;
+pub use window::{ParseWindowError, PeriodStats, Window};
+
+use window::MAX_WINDOW_FRAMES;
+
+use canbusnoop_core::{CanId, FdFlags, Frame, J1939Id};
+use canbusnoop_protocol::canopen::CanOpen;
+use canbusnoop_protocol::j1939::{Diagnostics, TransportEvent, TransportReassembler};
+use canbusnoop_protocol::nmea2000::FastPacketReassembler;
 use std::collections::{BTreeMap, VecDeque};
 use std::fmt::Display;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Stats {
-    started_at: Instant,
+    window: Window,
+    first_time: Option<Duration>,
     count: usize,
-    last_time: Option<Instant>,
+    last_time: Option<Duration>,
     last_period: Option<Duration>,
     min_period: Option<Duration>,
     max_period: Option<Duration>,
-    avg_period: Option<Duration>,
-    throughput: Option<f64>,
-    period_history: VecDeque<Duration>,
-    period_jitter: f64,
+    /// Capture time of the frames in the window, from the oldest. The frame
+    /// before the first one in a time window is kept for its period.
+    times: VecDeque<Duration>,
+    fd: Option<FdFlags>,
+    dlc: Option<u8>,
+    last_data: Vec<u8>,
+    /// Time the payload last changed
+    last_change: Option<Duration>,
+    /// Time each byte of the payload last changed, `None` if it never did
+    byte_changes: Vec<Option<Duration>>,
+    bits: BitStats,
 }
 
 impl Stats {
+    pub fn new(window: Window) -> Stats {
+        Stats {
+            window,
+            first_time: None,
+            count: 0,
+            last_time: None,
+            last_period: None,
+            min_period: None,
+            max_period: None,
+            times: VecDeque::new(),
+            fd: None,
+            dlc: None,
+            last_data: Vec::new(),
+            last_change: None,
+            byte_changes: Vec::new(),
+            bits: BitStats::default(),
+        }
+    }
+
+    pub fn window(&self) -> Window {
+        self.window
+    }
+
     pub fn count(&self) -> usize {
         self.count
     }
@@ -26,62 +74,138 @@ impl Stats {
         self.last_period
     }
 
+    /// Shortest period since the first frame
     pub fn min_period(&self) -> Option<Duration> {
         self.min_period
     }
 
+    /// Longest period since the first frame
     pub fn max_period(&self) -> Option<Duration> {
         self.max_period
     }
 
+    /// Mean period in the window
     pub fn avg_period(&self) -> Option<Duration> {
-        self.avg_period
+        self.period_stats().map(|p| p.mean)
+    }
+
+    /// Statistics of the periods in the window. The periods are sorted at
+    /// each call, get them once when more than a value is needed.
+    pub fn period_stats(&self) -> Option<PeriodStats> {
+        let periods: Vec<_> = self
+            .times
+            .iter()
+            .zip(self.times.iter().skip(1))
+            .map(|(a, b)| b.saturating_sub(*a))
+            .collect();
+        PeriodStats::new(&periods)
+    }
+
+    /// Frames per second in the window, from the mean period
+    pub fn frequency(&self) -> Option<f64> {
+        self.period_stats()?.frequency()
     }
 
+    /// Frames per second received in the window. With a time window, the
+    /// frames are divided by the window length, so pauses lower the rate.
+    /// Until the window is full, the periods are divided by the time since
+    /// the first frame.
     pub fn throughput(&self) -> Option<f64> {
-        self.throughput
+        let (&oldest, &newest) = (self.times.front()?, self.times.back()?);
+        let (frames, span) = match self.window {
+            Window::Time(window) => {
+                let frames = self
+                    .times
+                    .iter()
+                    .filter(|&&t| newest.saturating_sub(t) < window)
+                    .count();
+                let elapsed = newest.saturating_sub(self.first_time?);
+                match elapsed < window {
+                    true => (frames.saturating_sub(1), elapsed),
+                    false => (frames, window),
+                }
+            }
+            Window::Count(_) => (self.times.len() - 1, newest.saturating_sub(oldest)),
+        };
+        let secs = span.as_secs_f64();
+        (secs > 0.).then(|| frames as f64 / secs)
     }
 
+    /// Mean absolute difference between consecutive periods in the window,
+    /// relative to the mean period
     pub fn period_jitter(&self) -> f64 {
-        self.period_jitter
+        self.period_stats().map(|p| p.jitter).unwrap_or_default()
+    }
+
+    /// Returns true if the last frame was a CAN FD frame
+    pub fn is_fd(&self) -> bool {
+        self.fd.is_some()
+    }
+
+    /// Returns the CAN FD flags of the last frame, `None` for classic CAN
+    pub fn fd_flags(&self) -> Option<FdFlags> {
+        self.fd
+    }
+
+    /// Returns the data length code of the last frame, `None` for messages
+    /// reassembled from several frames
+    pub fn dlc(&self) -> Option<u8> {
+        self.dlc
+    }
+
+    /// Returns the payload of the last frame
+    pub fn last_data(&self) -> &[u8] {
+        &self.last_data
+    }
+
+    /// Returns the time the payload last changed, the time of the first
+    /// frame if it never did
+    pub fn last_change(&self) -> Option<Duration> {
+        self.last_change
+    }
+
+    /// Returns the time each byte of the last payload changed. Bytes which
+    /// never changed since the first frame are `None`.
+    pub fn byte_changes(&self) -> &[Option<Duration>] {
+        &self.byte_changes
+    }
+
+    /// Returns the change statistics of the bits of the payload
+    pub fn bits(&self) -> &BitStats {
+        &self.bits
     }
 }
 
 impl Default for Stats {
     fn default() -> Self {
-        Self {
-            started_at: Instant::now(),
-            count: Default::default(),
-            last_time: Default::default(),
-            last_period: Default::default(),
-            min_period: Default::default(),
-            max_period: Default::default(),
-            avg_period: Default::default(),
-            throughput: Default::default(),
-            period_history: Default::default(),
-            period_jitter: 0.,
-        }
+        Stats::new(Window::default())
     }
 }
 
 impl Display for Stats {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let spinner = Spinner(self.count);
+        let periods = self.period_stats();
+        let period = |p: fn(&PeriodStats) -> Duration| {
+            periods.as_ref().map(p).map(fmt_period).unwrap_or_default()
+        };
         write!(
             f,
-            "({} {:6}, {:6}, {:6}, {:6}, {:6}, {:6.1}Hz, {:6.1}, {:6.1}%)",
+            "({} {:6}, {:6}, {:>9}, {:>9}, {:>9}, {:>9}, p50 {:>9}, p95 {:>9}, p99 {:>9}, sd {:>9}, {:8.1}Hz, {:8.1}Hz, {:6.1}%)",
             spinner,
+            self.fd.map(|f| f.to_string()).unwrap_or("CAN".to_string()),
             self.count,
             self.last_period.map(fmt_period).unwrap_or_default(),
             self.min_period.map(fmt_period).unwrap_or_default(),
             self.max_period.map(fmt_period).unwrap_or_default(),
-            self.avg_period.map(fmt_period).unwrap_or_default(),
-            self.avg_period
-                .map(|x| x.as_secs_f64())
-                .and_then(|s| if s != 0. { Some(1. / (s as f64)) } else { None })
-                .unwrap_or_default(),
-            self.throughput.map(|x| x.to_string()).unwrap_or_default(),
-            self.period_jitter * 100.,
+            period(|p| p.mean),
+            period(|p| p.p50),
+            period(|p| p.p95),
+            period(|p| p.p99),
+            period(|p| p.stddev),
+            periods.and_then(|p| p.frequency()).unwrap_or_default(),
+            self.throughput().unwrap_or_default(),
+            periods.map(|p| p.jitter).unwrap_or_default() * 100.,
         )
     }
 }
@@ -89,11 +213,22 @@ impl Display for Stats {
 impl Stats {
     fn push(&mut self, frame: Frame) {
         log::debug!("{:?}", &frame);
+        self.push_time(&frame);
+        self.push_payload(&frame);
+    }
 
-        let now = Instant::now();
+    /// Count the frame and its period
+    fn push_time(&mut self, frame: &Frame) {
+        // Use the capture timestamp, so periods reflect the bus timing and not
+        // the time the frame spent in the channel
+        let now = frame.timestamp();
+        self.first_time.get_or_insert(now);
 
         self.count += 1;
-        self.last_period = self.last_time.map(|last_time| now - last_time);
+        self.fd = frame.fd_flags();
+        self.last_period = self
+            .last_time
+            .map(|last_time| now.saturating_sub(last_time));
         self.last_time = Some(now);
 
         if let Some(last_period) = self.last_period {
@@ -107,96 +242,250 @@ impl Stats {
                     .map(|x| x.max(last_period))
                     .unwrap_or(last_period),
             );
+        }
 
-            if self.period_history.len() > 10 {
-                self.period_history.pop_back();
-            }
+        self.times.push_back(now);
+        self.trim_window(now);
+    }
 
-            self.period_history.push_back(last_period);
+    /// Keep the payload of the frame, or of a message reassembled from
+    /// several frames
+    fn push_payload(&mut self, frame: &Frame) {
+        self.dlc = frame.dlc();
+        self.push_data(frame.data(), frame.timestamp());
+        self.bits.push(frame.data());
+    }
 
-            if !self.period_history.is_empty() {
-                let sum: u64 = self
-                    .period_history
-                    .iter()
-                    .map(|x| x.as_millis() as u64)
-                    .sum();
-                let n: u64 = self.period_history.len() as _;
-                self.avg_period = Some(Duration::from_millis(sum / n));
+    /// Drop the oldest frames, which are out of the window
+    fn trim_window(&mut self, now: Duration) {
+        match self.window {
+            // N periods, between N + 1 frames
+            Window::Count(n) => {
+                while self.times.len() > n.max(1) + 1 {
+                    self.times.pop_front();
+                }
+            }
+            // The periods ending in the window
+            Window::Time(window) => {
+                while self
+                    .times
+                    .get(1)
+                    .is_some_and(|&t| now.saturating_sub(t) >= window)
+                {
+                    self.times.pop_front();
+                }
             }
         }
 
-        // Calculate throughput: messages per second
-        // This is calculated only when a message is received
-        let time_since_start = now - self.started_at;
-        let secs_since_start = time_since_start.as_secs_f64();
-        self.throughput = match self.count {
-            0 | 1 => None,
-            _ => Some(self.count as f64 / secs_since_start),
-        };
+        while self.times.len() > MAX_WINDOW_FRAMES {
+            self.times.pop_front();
+        }
+    }
 
-        self.period_jitter = calculate_jitter(self.period_history.iter());
+    /// Keep the payload and the time its bytes changed. The bytes of the
+    /// first frame are not changes, bytes added by a longer frame are.
+    fn push_data(&mut self, data: &[u8], now: Duration) {
+        if self.last_change.is_none() {
+            self.last_data = data.to_vec();
+            self.last_change = Some(now);
+            self.byte_changes = vec![None; data.len()];
+            return;
+        }
+
+        if self.last_data == data {
+            return;
+        }
+
+        self.last_change = Some(now);
+        self.byte_changes.resize(data.len(), Some(now));
+        for (i, &b) in data.iter().enumerate() {
+            if self.last_data.get(i) != Some(&b) {
+                self.byte_changes[i] = Some(now);
+            }
+        }
+        self.last_data = data.to_vec();
     }
 }
 
 #[derive(Debug, Default, Clone, PartialEq)]
 pub struct MultiStats {
-    stats: BTreeMap<u32, Stats>,
+    stats: BTreeMap<CanId, Stats>,
     total_count: usize,
+    errors: ErrorStats,
+    transport: TransportReassembler,
+    diagnostics: Diagnostics,
+    canopen: CanOpen,
+    fast_packet: FastPacketReassembler,
+    /// Window of the period statistics of each id
+    window: Window,
 }
 
 impl MultiStats {
+    pub fn new(window: Window) -> MultiStats {
+        MultiStats {
+            window,
+            ..Default::default()
+        }
+    }
+
+    /// Window of the period statistics of each id
+    pub fn window(&self) -> Window {
+        self.window
+    }
+
     pub fn push(&mut self, frame: Frame) {
+        // Error frames are not traffic, they have their own counters
+        if frame.id().is_error() {
+            self.errors.push(&frame);
+            return;
+        }
+
+        // The frames of a NMEA 2000 fast packet are counted, but they are
+        // only chunks: the row shows the payload of the whole message
+        if FastPacketReassembler::is_fast_packet(&frame) {
+            let message = self.fast_packet.push(&frame);
+            self.total_count += 1;
+            let stats = self.stats_mut(frame.id());
+            stats.push_time(&frame);
+            if let Some(message) = message {
+                stats.push_payload(&message);
+            }
+            return;
+        }
+
+        // Messages reassembled from J1939 transport sessions are counted as
+        // if they were received in one frame
+        let messages: Vec<_> = self
+            .transport
+            .push(&frame)
+            .into_iter()
+            .filter_map(|event| match event {
+                TransportEvent::Message(message) => Some(message.to_frame()),
+                TransportEvent::Failed { .. } => None,
+            })
+            .collect();
+
+        self.push_frame(frame);
+
+        for message in messages {
+            self.push_frame(message);
+        }
+    }
+
+    fn push_frame(&mut self, frame: Frame) {
         self.total_count += 1;
+        self.diagnostics.push(&frame);
+        self.canopen.push(&frame);
 
-        let id = frame.id();
+        self.stats_mut(frame.id()).push(frame);
+    }
 
-        let s = self.stats.get_mut(&id);
-        if let Some(s) = s {
-            s.push(frame)
-        } else {
-            let mut s = Stats::default();
-            s.push(frame);
-            self.stats.insert(id, s);
-        };
+    fn stats_mut(&mut self, id: CanId) -> &mut Stats {
+        let window = self.window;
+        self.stats.entry(id).or_insert_with(|| Stats::new(window))
     }
 
     pub fn count(&self) -> usize {
         self.total_count
     }
 
-    pub fn iter(&self) -> impl Iterator<Item = (&u32, &Stats)> {
+    pub fn iter(&self) -> impl Iterator<Item = (&CanId, &Stats)> {
         self.stats.iter()
     }
 
-    pub fn into_iter(self) -> impl Iterator<Item = (u32, Stats)> {
+    #[allow(clippy::should_implement_trait)]
+    pub fn into_iter(self) -> impl Iterator<Item = (CanId, Stats)> {
         self.stats.into_iter()
     }
 
+    pub fn errors(&self) -> &ErrorStats {
+        &self.errors
+    }
+
+    /// J1939 transport sessions
+    pub fn transport(&self) -> &TransportReassembler {
+        &self.transport
+    }
+
+    /// J1939 trouble codes, by source address
+    pub fn diagnostics(&self) -> &Diagnostics {
+        &self.diagnostics
+    }
+
+    /// CANopen nodes, by node id
+    pub fn canopen(&self) -> &CanOpen {
+        &self.canopen
+    }
+
+    /// NMEA 2000 fast packets
+    pub fn fast_packet(&self) -> &FastPacketReassembler {
+        &self.fast_packet
+    }
+
     pub fn clear(&mut self) {
         self.total_count = 0;
         self.stats.clear();
+        self.errors = ErrorStats::default();
+        self.transport.clear();
+        self.diagnostics.clear();
+        self.canopen.clear();
+        self.fast_packet.clear();
     }
 }
 
 impl Display for MultiStats {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let stats = &self.stats;
-        let mut stats: Vec<_> = stats.into_iter().collect();
+        let mut stats: Vec<_> = stats.iter().collect();
         stats.sort_by_key(|(&k, _)| k);
         for (k, v) in stats {
-            let data_page = (k >> 24) & 1;
-            let pdu_format = (k >> 16) & 0xFF;
-            let pdu_specific = (k >> 8) & 0xFF;
-            let pgn = (data_page << 16) + (pdu_format << 8) + pdu_specific;
-            let _ = writeln!(f, "0x{:08X} PGN={:8} {}", k, pgn, v);
+            let j1939 = match J1939Id::from_can_id(*k) {
+                Some(j1939) => {
+                    let da = match j1939.destination_address() {
+                        Some(da) => format!("{:02X}", da),
+                        None => "--".to_string(),
+                    };
+                    format!(
+                        "PGN={:6} SA={:02X} DA={}",
+                        j1939.pgn(),
+                        j1939.source_address(),
+                        da
+                    )
+                }
+                None => String::new(),
+            };
+            let _ = writeln!(f, "0x{:<8} {:22} {}", k, j1939, v);
+        }
+        if self.errors.count() > 0 {
+            let _ = writeln!(f, "{}", self.errors);
+        }
+        let tp = &self.transport;
+        if tp.completed() + tp.aborted() + tp.timed_out() + tp.bad_sequence() > 0 {
+            let _ = writeln!(
+                f,
+                "J1939 TP: {} messages, {} aborted, {} timed out, {} bad sequence",
+                tp.completed(),
+                tp.aborted(),
+                tp.timed_out(),
+                tp.bad_sequence()
+            );
+        }
+        let fp = &self.fast_packet;
+        if fp.completed() + fp.dropped() > 0 {
+            let _ = writeln!(
+                f,
+                "NMEA 2000 fast packets: {} messages, {} dropped",
+                fp.completed(),
+                fp.dropped()
+            );
         }
         Ok(())
     }
 }
 
-fn fmt_period(x: Duration) -> String {
-    let ms = x.as_millis();
-    format!("{:6?}", ms)
+/// Milliseconds, with microsecond precision
+pub fn fmt_period(x: Duration) -> String {
+    format!("{:.3}", x.as_secs_f64() * 1000.)
 }
 
 struct Spinner(usize);
@@ -214,43 +503,274 @@ impl Display for Spinner {
     }
 }
 
-fn calculate_jitter<'a>(mut periods: impl Iterator<Item = &'a Duration>) -> f64 {
-    let mut previous_period = match periods.next() {
-        Some(period) => period.as_secs_f64(),
-        None => return 0.0, // Return 0 if there are no periods
-    };
-    let mut differences = Vec::new();
-    // Calculate the differences between adjacent periods
-    for period in periods {
-        let period = period.as_secs_f64();
-        let difference = (period - previous_period).abs();
-        differences.push(difference);
-        previous_period = period;
-    }
-    // Calculate the average absolute difference
-    let sum: f64 = differences.iter().sum();
-    if differences.is_empty() {
-        return 0.0;
-    }
-    let average_difference = sum / (differences.len() as f64);
-    // Calculate and return the jitter value
-    average_difference
-}
-
 impl MultiStats {
+    /// Keep only the ids matching the filter `f` and mask `m`. Flags are not
+    /// considered, only the 11 or 29 bit identifier.
     pub fn filter_by_can_id(self, f: u32, m: u32) -> Self {
-        let Self { stats, .. } = self;
+        let Self {
+            stats,
+            errors,
+            transport,
+            diagnostics,
+            canopen,
+            fast_packet,
+            window,
+            ..
+        } = self;
         let mut total_count = 0;
         let stats = stats
             .into_iter()
             .filter(|(id, _)| {
-                let ok = (id & m) == (f & m);
+                let ok = (id.id() & m) == (f & m);
                 if ok {
                     total_count += 1;
                 }
                 ok
             })
             .collect();
-        Self { stats, total_count }
+        Self {
+            stats,
+            total_count,
+            errors,
+            transport,
+            diagnostics,
+            canopen,
+            fast_packet,
+            window,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn push_at(stats: &mut Stats, times_us: impl IntoIterator<Item = u64>) {
+        let id = CanId::standard(0x100).unwrap();
+        for t in times_us {
+            stats.push(Frame::new(id, vec![0; 8], Duration::from_micros(t)));
+        }
+    }
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn count_window_keeps_the_newest_periods() {
+        let mut stats = Stats::new(Window::Count(3));
+        // Ten periods of 10 ms, then three of 20 ms
+        push_at(&mut stats, (0..=10).map(|i| i * 10_000));
+        push_at(&mut stats, (1..=3).map(|i| 100_000 + i * 20_000));
+
+        assert_eq!(stats.avg_period(), Some(ms(20)));
+        assert_eq!(stats.period_stats().unwrap().count, 3);
+        assert_eq!(stats.min_period(), Some(ms(10)));
+        assert_eq!(stats.max_period(), Some(ms(20)));
+        assert_eq!(stats.last_period(), Some(ms(20)));
+    }
+
+    #[test]
+    fn sub_millisecond_periods() {
+        let mut stats = Stats::new(Window::Count(10));
+        push_at(&mut stats, (0..20).map(|i| i * 250));
+
+        assert_eq!(stats.avg_period(), Some(Duration::from_micros(250)));
+        let frequency = stats.frequency().unwrap();
+        assert!((frequency - 4000.).abs() < 1e-6, "{}", frequency);
+        assert_eq!(fmt_period(Duration::from_micros(250)), "0.250");
+    }
+
+    #[test]
+    fn percentiles_and_standard_deviation() {
+        let periods: Vec<_> = (1..=100).rev().map(ms).collect();
+        let p = PeriodStats::new(&periods).unwrap();
+
+        assert_eq!(p.count, 100);
+        assert_eq!(p.min, ms(1));
+        assert_eq!(p.max, ms(100));
+        assert_eq!(p.p50, ms(50));
+        assert_eq!(p.p95, ms(95));
+        assert_eq!(p.p99, ms(99));
+        assert_eq!(p.mean, Duration::from_micros(50_500));
+        // Population standard deviation of 1..=100 is sqrt(833.25)
+        let stddev = p.stddev.as_secs_f64() * 1000.;
+        assert!((stddev - 833.25f64.sqrt()).abs() < 1e-6, "{}", stddev);
+    }
+
+    #[test]
+    fn constant_periods_have_no_deviation() {
+        let mut stats = Stats::new(Window::Count(100));
+        push_at(&mut stats, (0..50).map(|i| i * 5_000));
+
+        let p = stats.period_stats().unwrap();
+        assert_eq!(p.stddev, Duration::ZERO);
+        assert_eq!(p.p50, ms(5));
+        assert_eq!(p.p99, ms(5));
+        assert_eq!(p.jitter, 0.);
+    }
+
+    #[test]
+    fn jitter_is_relative_to_the_mean() {
+        let mut stats = Stats::new(Window::Count(100));
+        // Alternating 9 ms and 11 ms
+        let times = (0..21).map(|i| i / 2 * 20_000 + (i % 2) * 9_000);
+        push_at(&mut stats, times);
+
+        assert_eq!(stats.avg_period(), Some(ms(10)));
+        assert!((stats.period_jitter() - 0.2).abs() < 1e-9);
+    }
+
+    #[test]
+    fn time_window_follows_the_recent_rate() {
+        let mut stats = Stats::new(Window::Time(ms(200)));
+        // 1 s at 100 Hz, then 1 s at 10 Hz
+        push_at(&mut stats, (0..100).map(|i| i * 10_000));
+        push_at(&mut stats, (1..=10).map(|i| 990_000 + i * 100_000));
+
+        assert_eq!(stats.avg_period(), Some(ms(100)));
+        let frequency = stats.frequency().unwrap();
+        assert!((frequency - 10.).abs() < 1e-9, "{}", frequency);
+        // Frames at 1.89 s and 1.99 s, the one at 1.79 s is out of the window
+        let throughput = stats.throughput().unwrap();
+        assert!((throughput - 10.).abs() < 1e-9, "{}", throughput);
+        assert_eq!(stats.min_period(), Some(ms(10)));
+    }
+
+    #[test]
+    fn time_window_keeps_the_period_entering_it() {
+        let mut stats = Stats::new(Window::Time(ms(100)));
+        // Slower than the window
+        push_at(&mut stats, (0..5).map(|i| i * 500_000));
+
+        assert_eq!(stats.avg_period(), Some(ms(500)));
+        assert_eq!(stats.period_stats().unwrap().count, 1);
+    }
+
+    #[test]
+    fn throughput_of_a_time_window_counts_pauses() {
+        let mut stats = Stats::new(Window::Time(ms(1000)));
+        push_at(&mut stats, (0..=100).map(|i| i * 10_000));
+        // Nothing for 900 ms, then a frame
+        push_at(&mut stats, [1_900_000]);
+
+        // Ten frames from 0.91 s to 1.0 s, and the one at 1.9 s
+        let throughput = stats.throughput().unwrap();
+        assert!((throughput - 11.).abs() < 1e-9, "{}", throughput);
+        // Eleven periods from 0.9 s to 1.9 s
+        let mean = stats.avg_period().unwrap().as_secs_f64();
+        assert!((mean - 1. / 11.).abs() < 1e-9, "{}", mean);
+    }
+
+    #[test]
+    fn throughput_of_a_time_window_before_it_is_full() {
+        let mut stats = Stats::new(Window::Time(ms(1000)));
+        push_at(&mut stats, [0, 10_000]);
+
+        // One period of 10 ms
+        let throughput = stats.throughput().unwrap();
+        assert!((throughput - 100.).abs() < 1e-9, "{}", throughput);
+    }
+
+    #[test]
+    fn no_periods_with_a_single_frame() {
+        let mut stats = Stats::new(Window::default());
+        push_at(&mut stats, [1_000]);
+
+        assert_eq!(stats.count(), 1);
+        assert_eq!(stats.avg_period(), None);
+        assert_eq!(stats.period_stats(), None);
+        assert_eq!(stats.frequency(), None);
+        assert_eq!(stats.throughput(), None);
+        assert_eq!(stats.period_jitter(), 0.);
+    }
+
+    #[test]
+    fn multi_stats_uses_the_window() {
+        let mut stats = MultiStats::new(Window::Count(2));
+        let id = CanId::standard(0x100).unwrap();
+        for t in [0, 10, 30, 60] {
+            stats.push(Frame::new(id, vec![], ms(t)));
+        }
+
+        let (_, s) = stats.iter().next().unwrap();
+        assert_eq!(s.window(), Window::Count(2));
+        assert_eq!(s.avg_period(), Some(ms(25)));
+    }
+
+    #[test]
+    fn fast_packet_frames_are_counted() {
+        // Engine Parameters, Dynamic: 26 bytes in 4 frames
+        let id = CanId::extended(0x09F2_0110).unwrap();
+        let payload: Vec<u8> = (0..26).collect();
+        let mut data = vec![vec![0x00, 26]];
+        data[0].extend(&payload[..6]);
+        data.extend(payload[6..].chunks(7).zip(1..).map(|(chunk, i)| {
+            let mut data = vec![i];
+            data.extend(chunk);
+            data
+        }));
+
+        let mut stats = MultiStats::default();
+        for (t, data) in data.into_iter().enumerate() {
+            stats.push(Frame::new(id, data, ms(t as u64 * 10)));
+        }
+
+        assert_eq!(stats.count(), 4);
+        let (_, s) = stats.iter().next().unwrap();
+        assert_eq!(s.count(), 4);
+        assert_eq!(s.avg_period(), Some(ms(10)));
+        assert_eq!(s.last_data(), payload);
+        assert_eq!(stats.fast_packet().completed(), 1);
+    }
+
+    #[test]
+    fn no_dlc_for_reassembled_messages() {
+        let mut stats = Stats::default();
+        let id = CanId::standard(0x100).unwrap();
+        stats.push(Frame::new(id, vec![0; 20], ms(0)));
+        assert_eq!(stats.dlc(), Some(11));
+        stats.push(Frame::new(id, vec![0; 100], ms(10)));
+        assert_eq!(stats.dlc(), None);
+    }
+
+    #[test]
+    fn parse_window() {
+        assert_eq!("100".parse(), Ok(Window::Count(100)));
+        assert_eq!("10000".parse(), Ok(Window::Count(10_000)));
+        assert_eq!("2s".parse(), Ok(Window::Time(ms(2000))));
+        assert_eq!("500ms".parse(), Ok(Window::Time(ms(500))));
+        assert_eq!("1.5s".parse(), Ok(Window::Time(ms(1500))));
+        assert_eq!(
+            "250us".parse(),
+            Ok(Window::Time(Duration::from_micros(250)))
+        );
+        for s in [
+            "",
+            "0",
+            "0s",
+            "ms",
+            "10m",
+            "abc",
+            "-1",
+            "10001",
+            "99999999999999999999999s",
+        ] {
+            assert!(s.parse::<Window>().is_err(), "{:?}", s);
+        }
+    }
+
+    #[test]
+    fn display_window() {
+        for (s, window) in [
+            ("100", Window::Count(100)),
+            ("2000ms", Window::Time(ms(2000))),
+            ("250us", Window::Time(Duration::from_micros(250))),
+            ("1500us", Window::Time(Duration::from_micros(1500))),
+            ("0.0000005s", Window::Time(Duration::from_nanos(500))),
+        ] {
+            assert_eq!(window.to_string(), s);
+            assert_eq!(s.parse(), Ok(window));
+        }
     }
 }
@@ -0,0 +1,232 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::time::Duration;
+
+/// Values over time. At most `capacity` points are kept, the oldest are
+/// dropped first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeSeries {
+    points: VecDeque<(Duration, f64)>,
+    capacity: usize,
+}
+
+impl TimeSeries {
+    pub fn new(capacity: usize) -> TimeSeries {
+        TimeSeries {
+            points: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Append a point. Points are expected in time order.
+    pub fn push(&mut self, time: Duration, value: f64) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.points.len() == self.capacity {
+            self.points.pop_front();
+        }
+        self.points.push_back((time, value));
+    }
+
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.points.clear();
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Duration, f64)> + '_ {
+        self.points.iter().copied()
+    }
+
+    pub fn last(&self) -> Option<(Duration, f64)> {
+        self.points.back().copied()
+    }
+
+    /// Points with time in `from..=to`
+    pub fn range(
+        &self,
+        from: Duration,
+        to: Duration,
+    ) -> impl Iterator<Item = (Duration, f64)> + '_ {
+        let start = self.points.partition_point(|&(t, _)| t < from);
+        self.points
+            .range(start..)
+            .copied()
+            .take_while(move |&(t, _)| t <= to)
+    }
+
+    /// Minimum and maximum value in `from..=to`
+    pub fn bounds(&self, from: Duration, to: Duration) -> Option<(f64, f64)> {
+        self.range(from, to).fold(None, |acc, (_, v)| match acc {
+            None => Some((v, v)),
+            Some((min, max)) => Some((min.min(v), max.max(v))),
+        })
+    }
+
+    /// The last point at or before `time`
+    pub fn at(&self, time: Duration) -> Option<(Duration, f64)> {
+        let end = self.points.partition_point(|&(t, _)| t <= time);
+        end.checked_sub(1).map(|i| self.points[i])
+    }
+}
+
+/// A set of [`TimeSeries`], identified by keys chosen by the user of the
+/// store
+#[derive(Debug, Clone, PartialEq)]
+pub struct SeriesStore<K> {
+    series: BTreeMap<K, TimeSeries>,
+    capacity: usize,
+}
+
+impl<K: Ord> SeriesStore<K> {
+    /// Create an empty store, each series will keep at most `capacity` points
+    pub fn new(capacity: usize) -> SeriesStore<K> {
+        SeriesStore {
+            series: BTreeMap::new(),
+            capacity,
+        }
+    }
+
+    /// Start recording the series `key`. Does nothing if it already exists.
+    pub fn add(&mut self, key: K) {
+        let capacity = self.capacity;
+        self.series
+            .entry(key)
+            .or_insert_with(|| TimeSeries::new(capacity));
+    }
+
+    pub fn remove(&mut self, key: &K) {
+        self.series.remove(key);
+    }
+
+    /// Append a point to the series `key`, if it is being recorded
+    pub fn push(&mut self, key: &K, time: Duration, value: f64) {
+        if let Some(series) = self.series.get_mut(key) {
+            series.push(time, value);
+        }
+    }
+
+    pub fn get(&self, key: &K) -> Option<&TimeSeries> {
+        self.series.get(key)
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.series.keys()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &TimeSeries)> {
+        self.series.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.series.is_empty()
+    }
+
+    /// Remove all the points, keeping the series
+    pub fn clear(&mut self) {
+        for series in self.series.values_mut() {
+            series.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    fn series() -> TimeSeries {
+        let mut series = TimeSeries::new(10);
+        for (t, v) in [(10, 1.), (20, -2.), (30, 5.), (40, 3.)] {
+            series.push(ms(t), v);
+        }
+        series
+    }
+
+    #[test]
+    fn keeps_the_newest_points() {
+        let mut series = TimeSeries::new(3);
+        for t in 0..5 {
+            series.push(ms(t), t as f64);
+        }
+        assert_eq!(series.len(), 3);
+        let times: Vec<_> = series.iter().map(|(t, _)| t).collect();
+        assert_eq!(times, [ms(2), ms(3), ms(4)]);
+        assert_eq!(series.last(), Some((ms(4), 4.)));
+
+        let mut empty = TimeSeries::new(0);
+        empty.push(ms(0), 0.);
+        empty.push(ms(1), 1.);
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn range() {
+        let series = series();
+        let values =
+            |from, to| -> Vec<f64> { series.range(ms(from), ms(to)).map(|(_, v)| v).collect() };
+        assert_eq!(values(20, 30), [-2., 5.]);
+        assert_eq!(values(15, 35), [-2., 5.]);
+        assert_eq!(values(0, 100), [1., -2., 5., 3.]);
+        assert!(values(41, 100).is_empty());
+        assert!(values(30, 20).is_empty());
+    }
+
+    #[test]
+    fn bounds() {
+        let series = series();
+        assert_eq!(series.bounds(ms(0), ms(100)), Some((-2., 5.)));
+        assert_eq!(series.bounds(ms(35), ms(100)), Some((3., 3.)));
+        assert_eq!(series.bounds(ms(11), ms(19)), None);
+    }
+
+    #[test]
+    fn at() {
+        let series = series();
+        assert_eq!(series.at(ms(5)), None);
+        assert_eq!(series.at(ms(10)), Some((ms(10), 1.)));
+        assert_eq!(series.at(ms(25)), Some((ms(20), -2.)));
+        assert_eq!(series.at(ms(100)), Some((ms(40), 3.)));
+    }
+
+    #[test]
+    fn store() {
+        let mut store = SeriesStore::new(2);
+        assert!(store.is_empty());
+        store.add("a");
+        store.add("b");
+        store.push(&"a", ms(0), 1.);
+        // Not recorded
+        store.push(&"c", ms(0), 1.);
+        for t in 0..3 {
+            store.push(&"b", ms(t), t as f64);
+        }
+        // Adding again keeps the points
+        store.add("a");
+
+        assert_eq!(store.keys().copied().collect::<Vec<_>>(), ["a", "b"]);
+        assert_eq!(store.get(&"a").map(TimeSeries::len), Some(1));
+        assert_eq!(
+            store.get(&"b").and_then(TimeSeries::last),
+            Some((ms(2), 2.))
+        );
+        assert_eq!(store.get(&"b").map(TimeSeries::len), Some(2));
+        assert!(store.get(&"c").is_none());
+
+        store.clear();
+        assert_eq!(store.iter().count(), 2);
+        assert!(store.iter().all(|(_, series)| series.is_empty()));
+
+        store.remove(&"a");
+        assert_eq!(store.keys().copied().collect::<Vec<_>>(), ["b"]);
+    }
+}
@@ -0,0 +1,149 @@
+use canbusnoop_core::Frame;
+use std::collections::VecDeque;
+use std::ops::Range;
+use std::time::Duration;
+
+/// The most recent frames, in arrival order. When full, the oldest frame is
+/// dropped for each new one.
+#[derive(Debug, Clone)]
+pub struct Trace {
+    frames: VecDeque<Frame>,
+    capacity: usize,
+    /// Frames pushed since the last clear, dropped ones included
+    total: u64,
+    /// Time of the first frame since the last clear
+    start: Option<Duration>,
+}
+
+impl Trace {
+    pub fn new(capacity: usize) -> Trace {
+        Trace {
+            frames: VecDeque::with_capacity(capacity),
+            capacity,
+            total: 0,
+            start: None,
+        }
+    }
+
+    pub fn push(&mut self, frame: Frame) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.frames.len() == self.capacity {
+            self.frames.pop_front();
+        }
+        self.start.get_or_insert(frame.timestamp());
+        self.frames.push_back(frame);
+        self.total += 1;
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Frames pushed since the last clear, including the ones dropped
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// Time of the first frame pushed since the last clear
+    pub fn start(&self) -> Option<Duration> {
+        self.start
+    }
+
+    /// Frames kept, from the oldest, with their sequence number. Sequence
+    /// numbers start from 0 at the first frame pushed since the last clear.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (u64, &Frame)> + ExactSizeIterator {
+        let first = self.total - self.frames.len() as u64;
+        self.frames
+            .iter()
+            .enumerate()
+            .map(move |(i, frame)| (first + i as u64, frame))
+    }
+
+    /// Frames kept in `range` of indexes, from the oldest, with their
+    /// sequence number. The range is clamped to the frames kept.
+    pub fn range(&self, range: Range<usize>) -> impl DoubleEndedIterator<Item = (u64, &Frame)> {
+        let end = range.end.min(self.frames.len());
+        let start = range.start.min(end);
+        let first = self.total - self.frames.len() as u64 + start as u64;
+        self.frames
+            .range(start..end)
+            .enumerate()
+            .map(move |(i, frame)| (first + i as u64, frame))
+    }
+
+    /// Frames with an id matching the filter `f` and mask `m`, the same
+    /// filter used by [`crate::MultiStats::filter_by_can_id`]
+    pub fn filter(&self, f: u32, m: u32) -> impl DoubleEndedIterator<Item = (u64, &Frame)> {
+        self.iter()
+            .filter(move |(_, frame)| (frame.id().id() & m) == (f & m))
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+        self.total = 0;
+        self.start = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use canbusnoop_core::CanId;
+
+    fn trace(frames: u64) -> Trace {
+        let mut trace = Trace::new(4);
+        for i in 0..frames {
+            let id = CanId::standard(0x100 + i as u16).unwrap();
+            trace.push(Frame::new(id, vec![], Duration::from_millis(i)));
+        }
+        trace
+    }
+
+    #[test]
+    fn drops_the_oldest_frames() {
+        let trace = trace(6);
+        assert_eq!(trace.len(), 4);
+        assert_eq!(trace.total(), 6);
+        assert_eq!(trace.start(), Some(Duration::ZERO));
+        let seqs: Vec<_> = trace.iter().map(|(seq, _)| seq).collect();
+        assert_eq!(seqs, [2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn range_of_frames() {
+        let trace = trace(6);
+        let frames: Vec<_> = trace
+            .range(1..3)
+            .map(|(seq, frame)| (seq, frame.id().id()))
+            .collect();
+        assert_eq!(frames, [(3, 0x103), (4, 0x104)]);
+        assert_eq!(trace.range(3..10).count(), 1);
+        assert_eq!(trace.range(5..10).count(), 0);
+    }
+
+    #[test]
+    fn filter_frames() {
+        let trace = trace(6);
+        let seqs: Vec<_> = trace.filter(0x101, 0x001).map(|(seq, _)| seq).collect();
+        assert_eq!(seqs, [3, 5]);
+    }
+
+    #[test]
+    fn clear() {
+        let mut trace = trace(6);
+        trace.clear();
+        assert!(trace.is_empty());
+        assert_eq!(trace.total(), 0);
+        assert_eq!(trace.start(), None);
+    }
+}
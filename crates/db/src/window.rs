@@ -0,0 +1,152 @@
+use std::fmt::Display;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Upper bound of the frames kept by a window, so a fast id can't take all
+/// the memory
+pub(crate) const MAX_WINDOW_FRAMES: usize = 10_000;
+
+/// The frames considered by the period statistics of an id
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Window {
+    /// The frames received in the last period of time
+    Time(Duration),
+    /// The last frames received
+    Count(usize),
+}
+
+impl Default for Window {
+    fn default() -> Self {
+        Window::Count(100)
+    }
+}
+
+impl Display for Window {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Window::Time(d) if d.subsec_nanos() % 1_000_000 == 0 => {
+                write!(f, "{}ms", d.as_millis())
+            }
+            Window::Time(d) if d.subsec_nanos() % 1_000 == 0 => write!(f, "{}us", d.as_micros()),
+            Window::Time(d) => write!(f, "{}s", d.as_secs_f64()),
+            Window::Count(n) => write!(f, "{}", n),
+        }
+    }
+}
+
+/// Parse a number of frames (e.g. `100`, at most 10 000) or a time with a
+/// unit: `s`, `ms` or `us` (e.g. `500ms`)
+impl FromStr for Window {
+    type Err = ParseWindowError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || ParseWindowError(s.to_string());
+        let s = s.trim();
+        let split = s
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(s.len());
+        let (value, unit) = s.split_at(split);
+
+        if unit.is_empty() {
+            return match value.parse() {
+                Ok(n) if n > 0 && n <= MAX_WINDOW_FRAMES => Ok(Window::Count(n)),
+                _ => Err(error()),
+            };
+        }
+
+        let value: f64 = value.parse().map_err(|_| error())?;
+        let secs = match unit {
+            "s" => value,
+            "ms" => value / 1e3,
+            "us" => value / 1e6,
+            _ => return Err(error()),
+        };
+        match secs > 0. {
+            true => Duration::try_from_secs_f64(secs)
+                .map(Window::Time)
+                .map_err(|_| error()),
+            false => Err(error()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseWindowError(String);
+
+impl Display for ParseWindowError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "invalid window {:?}, expected a number of frames up to {} or a time (e.g. 500ms)",
+            self.0, MAX_WINDOW_FRAMES
+        )
+    }
+}
+
+impl std::error::Error for ParseWindowError {}
+
+/// Statistics of the periods in a window
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PeriodStats {
+    /// Number of periods
+    pub count: usize,
+    pub min: Duration,
+    pub max: Duration,
+    pub mean: Duration,
+    /// Standard deviation
+    pub stddev: Duration,
+    /// Median
+    pub p50: Duration,
+    pub p95: Duration,
+    pub p99: Duration,
+    /// Mean absolute difference between consecutive periods, relative to
+    /// the mean period
+    pub jitter: f64,
+}
+
+impl PeriodStats {
+    /// Returns `None` if there are no periods
+    pub fn new(periods: &[Duration]) -> Option<PeriodStats> {
+        let count = periods.len();
+        if count == 0 {
+            return None;
+        }
+
+        let secs: Vec<f64> = periods.iter().map(Duration::as_secs_f64).collect();
+        let mean = secs.iter().sum::<f64>() / count as f64;
+        let variance = secs.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / count as f64;
+        let jitter = match (count, mean > 0.) {
+            (2.., true) => {
+                let diffs: f64 = secs.windows(2).map(|w| (w[1] - w[0]).abs()).sum();
+                diffs / (count - 1) as f64 / mean
+            }
+            _ => 0.,
+        };
+
+        let mut sorted = periods.to_vec();
+        sorted.sort_unstable();
+        // Nearest rank
+        let percentile = |p: f64| {
+            let rank = (p / 100. * count as f64).ceil() as usize;
+            sorted[rank.clamp(1, count) - 1]
+        };
+
+        Some(PeriodStats {
+            count,
+            min: sorted[0],
+            max: sorted[count - 1],
+            mean: Duration::from_secs_f64(mean),
+            stddev: Duration::from_secs_f64(variance.sqrt()),
+            p50: percentile(50.),
+            p95: percentile(95.),
+            p99: percentile(99.),
+            jitter,
+        })
+    }
+
+    /// Frames per second, from the mean period
+    pub fn frequency(&self) -> Option<f64> {
+        let mean = self.mean.as_secs_f64();
+        (mean > 0.).then(|| 1. / mean)
+    }
+}
@@ -0,0 +1,200 @@
+use crate::{ByteOrder, Message, Multiplexing, Signal, ValueType};
+use std::fmt::Display;
+
+/// The value of a signal extracted from a payload
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedSignal<'a> {
+    pub signal: &'a Signal,
+    /// Raw value, sign extended for signed signals
+    pub raw: i64,
+    /// Physical value: raw value scaled by factor and offset
+    pub value: f64,
+    /// Description of the raw value, from the value table
+    pub description: Option<&'a str>,
+}
+
+impl Message {
+    /// Decode the signals present in `data`. Multiplexed signals are
+    /// included only if selected by the multiplexor, signals not fitting in
+    /// `data` are skipped.
+    pub fn decode<'a>(&'a self, data: &[u8]) -> Vec<DecodedSignal<'a>> {
+        let multiplexor = self
+            .signals
+            .iter()
+            .find(|s| s.multiplexing == Multiplexing::Multiplexor)
+            .and_then(|s| s.raw(data));
+
+        self.signals
+            .iter()
+            .filter(|s| match s.multiplexing {
+                Multiplexing::None | Multiplexing::Multiplexor => true,
+                Multiplexing::Multiplexed(n) => multiplexor == Some(n),
+            })
+            .filter_map(|s| s.decode(data))
+            .collect()
+    }
+}
+
+impl Signal {
+    /// Extract the raw bits of the signal, `None` if they do not fit in `data`
+    pub fn raw(&self, data: &[u8]) -> Option<u64> {
+        let bit = |pos: usize| -> Option<u64> {
+            let byte = data.get(pos / 8)?;
+            Some(((byte >> (pos % 8)) & 1) as u64)
+        };
+
+        let mut raw = 0u64;
+        match self.byte_order {
+            ByteOrder::LittleEndian => {
+                for i in 0..self.size {
+                    raw |= bit(self.start_bit + i)? << i;
+                }
+            }
+            ByteOrder::BigEndian => {
+                // Bits are numbered from the LSB of each byte, the signal
+                // goes from its MSB down, then on to the next byte
+                let mut pos = self.start_bit;
+                for _ in 0..self.size {
+                    raw = (raw << 1) | bit(pos)?;
+                    pos = if pos % 8 == 0 { pos + 15 } else { pos - 1 };
+                }
+            }
+        }
+        Some(raw)
+    }
+
+    pub fn decode<'a>(&'a self, data: &[u8]) -> Option<DecodedSignal<'a>> {
+        let bits = self.raw(data)?;
+
+        let (raw, value) = match self.value_type {
+            ValueType::Unsigned => (bits as i64, bits as f64),
+            ValueType::Signed => {
+                let raw = sign_extend(bits, self.size);
+                (raw, raw as f64)
+            }
+            ValueType::Float32 => (bits as i64, f32::from_bits(bits as u32) as f64),
+            ValueType::Float64 => (bits as i64, f64::from_bits(bits)),
+        };
+
+        Some(DecodedSignal {
+            signal: self,
+            raw,
+            value: value * self.factor + self.offset,
+            description: self.values.get(&raw).map(|s| s.as_str()),
+        })
+    }
+}
+
+fn sign_extend(bits: u64, size: usize) -> i64 {
+    let shift = 64 - size as u32;
+    ((bits << shift) as i64) >> shift
+}
+
+/// Formats the physical value with as many decimals as the factor has, the
+/// unit and the value description
+impl Display for DecodedSignal<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let signal = self.signal;
+        let decimals = match signal.value_type {
+            ValueType::Float32 | ValueType::Float64 => 3,
+            _ => decimals(signal.factor).max(decimals(signal.offset)),
+        };
+        write!(f, "{:.*}", decimals, self.value)?;
+        if !signal.unit.is_empty() {
+            write!(f, " {}", signal.unit)?;
+        }
+        if let Some(description) = self.description {
+            write!(f, " ({})", description)?;
+        }
+        Ok(())
+    }
+}
+
+/// Number of decimals needed to show `x` exactly, up to 9
+fn decimals(x: f64) -> usize {
+    (0..9)
+        .find(|&d| {
+            let scaled = x * 10f64.powi(d as i32);
+            (scaled - scaled.round()).abs() < 1e-6
+        })
+        .unwrap_or(9)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Dbc;
+    use canbusnoop_core::CanId;
+
+    const DBC: &str = r#"
+BO_ 256 Test: 8 Vector__XXX
+ SG_ Le16 : 8|16@1+ (1,0) [0|65535] "" Vector__XXX
+ SG_ LeUnaligned : 4|8@1+ (1,0) [0|255] "" Vector__XXX
+ SG_ Be16 : 23|16@0+ (1,0) [0|65535] "" Vector__XXX
+ SG_ BeUnaligned : 43|8@0+ (1,0) [0|255] "" Vector__XXX
+ SG_ SignedLe : 48|8@1- (0.5,10) [0|0] "" Vector__XXX
+ SG_ SignedBe : 59|12@0- (1,0) [0|0] "" Vector__XXX
+
+BO_ 512 Muxed: 3 Vector__XXX
+ SG_ Mux M : 0|8@1+ (1,0) [0|255] "" Vector__XXX
+ SG_ One m1 : 8|8@1+ (1,0) [0|255] "" Vector__XXX
+ SG_ Two m2 : 8|16@1+ (1,0) [0|65535] "" Vector__XXX
+"#;
+
+    fn decode(id: u16, data: &[u8]) -> Vec<(String, i64, f64)> {
+        let dbc: Dbc = DBC.parse().unwrap();
+        let message = dbc.message(CanId::standard(id).unwrap()).unwrap();
+        message
+            .decode(data)
+            .into_iter()
+            .map(|s| (s.signal.name.clone(), s.raw, s.value))
+            .collect()
+    }
+
+    fn raw(id: u16, data: &[u8], name: &str) -> i64 {
+        let signals = decode(id, data);
+        signals.iter().find(|(n, ..)| n == name).unwrap().1
+    }
+
+    #[test]
+    fn little_endian() {
+        let data = [0xA0, 0x34, 0x12, 0, 0, 0, 0, 0];
+        assert_eq!(raw(256, &data, "Le16"), 0x1234);
+        // Bits 4 to 11, from the high nibble of the first byte
+        assert_eq!(raw(256, &data, "LeUnaligned"), 0x4A);
+    }
+
+    #[test]
+    fn big_endian() {
+        let data = [0, 0, 0x12, 0x34, 0, 0x0A, 0xB0, 0];
+        assert_eq!(raw(256, &data, "Be16"), 0x1234);
+        // From bit 3 of byte 5 down, on to bit 4 of byte 6
+        assert_eq!(raw(256, &data, "BeUnaligned"), 0xAB);
+    }
+
+    #[test]
+    fn signed() {
+        let data = [0, 0, 0, 0, 0, 0, 0xFE, 0];
+        let signals = decode(256, &data);
+        let signed = signals.iter().find(|(n, ..)| n == "SignedLe").unwrap();
+        assert_eq!(signed.1, -2);
+        assert_eq!(signed.2, 9.);
+
+        // 12 bits from bit 3 of byte 7, on to byte 8: out of the payload
+        assert!(signals.iter().all(|(n, ..)| n != "SignedBe"));
+        let data = [0, 0, 0, 0, 0, 0, 0, 0x0F, 0xFF];
+        assert_eq!(raw(256, &data, "SignedBe"), -1);
+        let data = [0, 0, 0, 0, 0, 0, 0, 0x07, 0xFF];
+        assert_eq!(raw(256, &data, "SignedBe"), 0x7FF);
+    }
+
+    #[test]
+    fn multiplexed() {
+        let names = |data: &[u8]| -> Vec<String> {
+            decode(512, data).into_iter().map(|(n, ..)| n).collect()
+        };
+        assert_eq!(names(&[1, 5, 1]), ["Mux", "One"]);
+        assert_eq!(names(&[2, 5, 1]), ["Mux", "Two"]);
+        assert_eq!(names(&[3, 5, 1]), ["Mux"]);
+        assert_eq!(raw(512, &[2, 5, 1], "Two"), 0x0105);
+    }
+}
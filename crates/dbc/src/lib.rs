@@ -0,0 +1,108 @@
+mod decode;
+mod parser;
+
+pub use decode::DecodedSignal;
+
+use canbusnoop_core::CanId;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::str::FromStr;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("cannot read DBC file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("line {line}: {message}")]
+    Parse { line: usize, message: String },
+}
+
+/// A CAN database: messages, their signals and value tables
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Dbc {
+    messages: BTreeMap<CanId, Message>,
+}
+
+impl Dbc {
+    pub fn load(path: &Path) -> Result<Dbc, Error> {
+        let text = std::fs::read_to_string(path)?;
+        text.parse()
+    }
+
+    /// Find the message with this id. The RTR flag is ignored.
+    pub fn message(&self, id: CanId) -> Option<&Message> {
+        self.messages.get(&id.with_rtr(false))
+    }
+
+    pub fn messages(&self) -> impl Iterator<Item = &Message> {
+        self.messages.values()
+    }
+}
+
+impl FromStr for Dbc {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let messages = parser::parse(s)?;
+        let messages = messages.into_iter().map(|m| (m.id, m)).collect();
+        Ok(Dbc { messages })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    pub id: CanId,
+    pub name: String,
+    /// Payload length in bytes
+    pub size: usize,
+    /// Node sending the message, if known
+    pub transmitter: Option<String>,
+    pub signals: Vec<Signal>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Signal {
+    pub name: String,
+    /// Position of the least significant bit for little endian signals, of
+    /// the most significant bit for big endian signals
+    pub start_bit: usize,
+    /// Length in bits
+    pub size: usize,
+    pub byte_order: ByteOrder,
+    pub value_type: ValueType,
+    pub factor: f64,
+    pub offset: f64,
+    pub min: f64,
+    pub max: f64,
+    pub unit: String,
+    pub multiplexing: Multiplexing,
+    /// Descriptions of raw values
+    pub values: BTreeMap<i64, String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ByteOrder {
+    /// Intel
+    LittleEndian,
+    /// Motorola
+    BigEndian,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ValueType {
+    Unsigned,
+    Signed,
+    /// IEEE 754 single precision, the signal must be 32 bits long
+    Float32,
+    /// IEEE 754 double precision, the signal must be 64 bits long
+    Float64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Multiplexing {
+    /// Always present
+    None,
+    /// Its value selects which multiplexed signals are present
+    Multiplexor,
+    /// Present only when the multiplexor has this value
+    Multiplexed(u64),
+}
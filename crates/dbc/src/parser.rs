@@ -0,0 +1,484 @@
+use crate::{ByteOrder, Error, Message, Multiplexing, Signal, ValueType};
+use canbusnoop_core::CanId;
+use std::collections::BTreeMap;
+
+/// Set in DBC message ids to mark extended ids
+const DBC_EXTENDED_FLAG: u64 = 0x8000_0000;
+
+/// Id of `VECTOR__INDEPENDENT_SIG_MSG`, the pseudo-message holding the
+/// signals not sent in any message
+const INDEPENDENT_SIGNALS_ID: u64 = 0xC000_0000;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(String),
+    Str(String),
+    Punct(char),
+}
+
+/// A token, with the line where it starts
+type Spanned = (usize, Token);
+
+fn tokenize(s: &str) -> Result<Vec<Spanned>, Error> {
+    let mut tokens = Vec::new();
+    let mut chars = s.chars().peekable();
+    let mut line = 1;
+
+    while let Some(&c) = chars.peek() {
+        let start_line = line;
+        match c {
+            '\n' => {
+                line += 1;
+                chars.next();
+            }
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '/' => {
+                chars.next();
+                if chars.peek() != Some(&'/') {
+                    return Err(parse_error(line, "unexpected '/'"));
+                }
+                while chars.next_if(|&c| c != '\n').is_some() {}
+            }
+            '"' => {
+                chars.next();
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => text.extend(chars.next()),
+                        Some(c) => {
+                            if c == '\n' {
+                                line += 1;
+                            }
+                            text.push(c);
+                        }
+                        None => return Err(parse_error(start_line, "unterminated string")),
+                    }
+                }
+                tokens.push((start_line, Token::Str(text)));
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let mut ident = String::new();
+                while let Some(c) = chars.next_if(|&c| c.is_ascii_alphanumeric() || c == '_') {
+                    ident.push(c);
+                }
+                tokens.push((line, Token::Ident(ident)));
+            }
+            c if c.is_ascii_digit() || c == '-' || c == '+' => {
+                chars.next();
+                let mut number = String::from(c);
+                if !c.is_ascii_digit() && !chars.peek().is_some_and(|c| c.is_ascii_digit()) {
+                    // A sign on its own, as in `@1+`
+                    tokens.push((line, Token::Punct(c)));
+                    continue;
+                }
+                let mut previous = c;
+                while let Some(c) = chars.next_if(|&c| {
+                    c.is_ascii_digit()
+                        || c == '.'
+                        || c == 'e'
+                        || c == 'E'
+                        || ((c == '-' || c == '+') && (previous == 'e' || previous == 'E'))
+                }) {
+                    number.push(c);
+                    previous = c;
+                }
+                tokens.push((line, Token::Number(number)));
+            }
+            c => {
+                chars.next();
+                tokens.push((line, Token::Punct(c)));
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn parse_error(line: usize, message: &str) -> Error {
+    Error::Parse {
+        line,
+        message: message.to_string(),
+    }
+}
+
+struct Parser {
+    tokens: Vec<Spanned>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(_, t)| t)
+    }
+
+    fn line(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .or(self.tokens.last())
+            .map(|(line, _)| *line)
+            .unwrap_or(1)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).map(|(_, t)| t.clone());
+        self.pos += 1;
+        token
+    }
+
+    fn error(&self, message: &str) -> Error {
+        parse_error(self.line(), message)
+    }
+
+    fn ident(&mut self) -> Result<String, Error> {
+        match self.next() {
+            Some(Token::Ident(s)) => Ok(s),
+            _ => Err(self.error("expected an identifier")),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, Error> {
+        match self.next() {
+            Some(Token::Str(s)) => Ok(s),
+            _ => Err(self.error("expected a string")),
+        }
+    }
+
+    fn number<T: std::str::FromStr>(&mut self) -> Result<T, Error> {
+        match self.next() {
+            Some(Token::Number(s)) => s.parse().map_err(|_| self.error("invalid number")),
+            _ => Err(self.error("expected a number")),
+        }
+    }
+
+    fn punct(&mut self, expected: char) -> Result<(), Error> {
+        match self.next() {
+            Some(Token::Punct(c)) if c == expected => Ok(()),
+            _ => Err(self.error(&format!("expected '{}'", expected))),
+        }
+    }
+
+    /// Skip the rest of a statement, up to the next `;`
+    fn skip_statement(&mut self) {
+        while let Some(token) = self.next() {
+            if token == Token::Punct(';') {
+                break;
+            }
+        }
+    }
+
+    /// Skip the tokens on the current line
+    fn skip_line(&mut self, line: usize) {
+        while self.tokens.get(self.pos).is_some_and(|(l, _)| *l == line) {
+            self.pos += 1;
+        }
+    }
+}
+
+pub(crate) fn parse(s: &str) -> Result<Vec<Message>, Error> {
+    let tokens = tokenize(s)?;
+    let mut p = Parser { tokens, pos: 0 };
+    let mut messages: Vec<Message> = Vec::new();
+    // Inside the pseudo-message of the independent signals
+    let mut independent = false;
+
+    while let Some(token) = p.next() {
+        let keyword = match token {
+            Token::Ident(keyword) => keyword,
+            _ => return Err(p.error("expected a keyword")),
+        };
+
+        match keyword.as_str() {
+            "VERSION" => {
+                p.string()?;
+            }
+            "NS_" => {
+                // A list of keywords, up to the bit timing section
+                while p
+                    .peek()
+                    .is_some_and(|t| *t != Token::Ident("BS_".to_string()))
+                {
+                    p.next();
+                }
+            }
+            "BS_" | "BU_" => {
+                let line = p.tokens[p.pos - 1].0;
+                p.skip_line(line);
+            }
+            "BO_" => {
+                let message = parse_message(&mut p)?;
+                independent = message.is_none();
+                messages.extend(message);
+            }
+            "SG_" => {
+                let signal = parse_signal(&mut p)?;
+                // Independent signals are never on the bus
+                if independent {
+                    continue;
+                }
+                match messages.last_mut() {
+                    Some(message) => message.signals.push(signal),
+                    None => return Err(p.error("signal outside of a message")),
+                }
+            }
+            "VAL_" => parse_values(&mut p, &mut messages)?,
+            "SIG_VALTYPE_" => parse_value_type(&mut p, &mut messages)?,
+            _ => p.skip_statement(),
+        }
+    }
+
+    Ok(messages)
+}
+
+/// `BO_ 2364540158 EEC1: 8 Engine`, `None` for the pseudo-message of the
+/// independent signals
+fn parse_message(p: &mut Parser) -> Result<Option<Message>, Error> {
+    let id = parse_id(p)?;
+    let name = p.ident()?;
+    p.punct(':')?;
+    let size = p.number()?;
+    let transmitter = p.ident()?;
+    // Vector__XXX is the placeholder for no node
+    let transmitter = (transmitter != "Vector__XXX").then_some(transmitter);
+
+    Ok(id.map(|id| Message {
+        id,
+        name,
+        size,
+        transmitter,
+        signals: Vec::new(),
+    }))
+}
+
+/// `None` for the pseudo-message of the independent signals
+fn parse_id(p: &mut Parser) -> Result<Option<CanId>, Error> {
+    let id: u64 = p.number()?;
+    if id == INDEPENDENT_SIGNALS_ID {
+        return Ok(None);
+    }
+    let id = if id & DBC_EXTENDED_FLAG != 0 {
+        CanId::extended((id & !DBC_EXTENDED_FLAG) as u32)
+    } else {
+        u16::try_from(id).ok().and_then(CanId::standard)
+    };
+    id.map(Some).ok_or_else(|| p.error("invalid message id"))
+}
+
+/// `SG_ EngineSpeed m1 : 24|16@1+ (0.125,0) [0|8031.875] "rpm" Vector__XXX`
+fn parse_signal(p: &mut Parser) -> Result<Signal, Error> {
+    let line = p.line();
+    let name = p.ident()?;
+
+    let multiplexing = match p.peek() {
+        Some(Token::Ident(m)) => {
+            let multiplexing = match m.as_str() {
+                "M" => Multiplexing::Multiplexor,
+                // `m1M` (extended multiplexing) is also multiplexed by `m1`
+                m => m
+                    .strip_prefix('m')
+                    .map(|m| m.trim_end_matches('M'))
+                    .and_then(|m| m.parse().ok())
+                    .map(Multiplexing::Multiplexed)
+                    .ok_or_else(|| p.error("invalid multiplexer indicator"))?,
+            };
+            p.next();
+            multiplexing
+        }
+        _ => Multiplexing::None,
+    };
+
+    p.punct(':')?;
+    let start_bit = p.number()?;
+    p.punct('|')?;
+    let size = p.number()?;
+    p.punct('@')?;
+    let byte_order = match p.number::<u8>()? {
+        0 => ByteOrder::BigEndian,
+        1 => ByteOrder::LittleEndian,
+        _ => return Err(p.error("invalid byte order")),
+    };
+    let value_type = match p.next() {
+        Some(Token::Punct('+')) => ValueType::Unsigned,
+        Some(Token::Punct('-')) => ValueType::Signed,
+        _ => return Err(p.error("expected '+' or '-'")),
+    };
+    p.punct('(')?;
+    let factor = p.number()?;
+    p.punct(',')?;
+    let offset = p.number()?;
+    p.punct(')')?;
+    p.punct('[')?;
+    let min = p.number()?;
+    p.punct('|')?;
+    let max = p.number()?;
+    p.punct(']')?;
+    let unit = p.string()?;
+    // Receivers are not used
+    p.skip_line(line);
+
+    if size == 0 || size > 64 {
+        return Err(parse_error(line, "invalid signal size"));
+    }
+
+    Ok(Signal {
+        name,
+        start_bit,
+        size,
+        byte_order,
+        value_type,
+        factor,
+        offset,
+        min,
+        max,
+        unit,
+        multiplexing,
+        values: BTreeMap::new(),
+    })
+}
+
+/// `VAL_ 2364540158 EngineState 0 "Off" 1 "On" ;`
+fn parse_values(p: &mut Parser, messages: &mut [Message]) -> Result<(), Error> {
+    // Value descriptions of environment variables have no message id
+    if !matches!(p.peek(), Some(Token::Number(_))) {
+        p.skip_statement();
+        return Ok(());
+    }
+
+    let id = parse_id(p)?;
+    let name = p.ident()?;
+    let mut values = BTreeMap::new();
+    loop {
+        match p.peek() {
+            Some(Token::Punct(';')) => {
+                p.next();
+                break;
+            }
+            Some(Token::Number(_)) => {
+                let value: f64 = p.number()?;
+                let description = p.string()?;
+                values.insert(value as i64, description);
+            }
+            _ => return Err(p.error("invalid value description")),
+        }
+    }
+
+    if let Some(signal) = id.and_then(|id| find_signal(messages, id, &name)) {
+        signal.values = values;
+    }
+
+    Ok(())
+}
+
+/// `SIG_VALTYPE_ 2364540158 Temperature : 1;`
+fn parse_value_type(p: &mut Parser, messages: &mut [Message]) -> Result<(), Error> {
+    let id = parse_id(p)?;
+    let name = p.ident()?;
+    p.punct(':')?;
+    let value_type = match p.number::<u8>()? {
+        1 => ValueType::Float32,
+        2 => ValueType::Float64,
+        _ => return Err(p.error("invalid signal value type")),
+    };
+    p.punct(';')?;
+
+    if let Some(signal) = id.and_then(|id| find_signal(messages, id, &name)) {
+        signal.value_type = value_type;
+    }
+
+    Ok(())
+}
+
+fn find_signal<'a>(messages: &'a mut [Message], id: CanId, name: &str) -> Option<&'a mut Signal> {
+    messages
+        .iter_mut()
+        .find(|m| m.id == id)?
+        .signals
+        .iter_mut()
+        .find(|s| s.name == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ByteOrder, Dbc, Error, Multiplexing, ValueType};
+    use canbusnoop_core::CanId;
+
+    const DBC: &str = r#"VERSION ""
+
+NS_ :
+    CM_
+    VAL_
+
+BS_:
+
+BU_: Engine Gateway
+
+BO_ 2364540158 EEC1: 8 Engine
+ SG_ EngineState : 0|2@1+ (1,0) [0|3] "" Gateway
+ SG_ EngineSpeed : 24|16@1+ (0.125,0) [0|8031.875] "rpm" Gateway
+ SG_ Temperature : 32|32@1- (1,0) [0|0] "degC" Gateway
+
+BO_ 3221225472 VECTOR__INDEPENDENT_SIG_MSG: 0 Vector__XXX
+ SG_ Orphan : 0|8@1+ (1,0) [0|0] "" Vector__XXX
+
+BO_ 1024 Status: 2 Vector__XXX
+ SG_ Mode M : 7|8@0+ (1,0) [0|255] "" Vector__XXX
+ SG_ Level m3 : 8|8@1+ (1,0) [0|255] "%" Vector__XXX
+
+CM_ SG_ 2364540158 EngineSpeed "Actual engine speed";
+VAL_ 2364540158 EngineState 0 "Off" 1 "On" ;
+VAL_ 3221225472 Orphan 0 "Zero" ;
+SIG_VALTYPE_ 2364540158 Temperature : 1;
+"#;
+
+    #[test]
+    fn parses_messages_and_signals() {
+        let dbc: Dbc = DBC.parse().unwrap();
+        assert_eq!(dbc.messages().count(), 2);
+
+        let eec1 = dbc.message(CanId::extended(0x0CF0_04FE).unwrap()).unwrap();
+        assert_eq!(eec1.name, "EEC1");
+        assert_eq!(eec1.size, 8);
+        assert_eq!(eec1.transmitter.as_deref(), Some("Engine"));
+        assert_eq!(eec1.signals.len(), 3);
+
+        let speed = &eec1.signals[1];
+        assert_eq!((speed.start_bit, speed.size), (24, 16));
+        assert_eq!(speed.byte_order, ByteOrder::LittleEndian);
+        assert_eq!(speed.factor, 0.125);
+        assert_eq!(speed.unit, "rpm");
+        assert_eq!(eec1.signals[0].values[&1], "On");
+        assert_eq!(eec1.signals[2].value_type, ValueType::Float32);
+
+        let status = dbc.message(CanId::standard(0x400).unwrap()).unwrap();
+        assert_eq!(status.transmitter, None);
+        assert_eq!(status.signals[0].byte_order, ByteOrder::BigEndian);
+        assert_eq!(status.signals[0].multiplexing, Multiplexing::Multiplexor);
+        assert_eq!(status.signals[1].multiplexing, Multiplexing::Multiplexed(3));
+    }
+
+    #[test]
+    fn skips_independent_signals() {
+        let dbc: Dbc = DBC.parse().unwrap();
+        let signals: Vec<_> = dbc
+            .messages()
+            .flat_map(|m| &m.signals)
+            .map(|s| s.name.as_str())
+            .collect();
+        assert!(!signals.contains(&"Orphan"));
+    }
+
+    #[test]
+    fn reports_the_line_of_errors() {
+        let dbc = "VERSION \"\"\n\nBO_ 4096 Bad: 8 Vector__XXX\n";
+        match dbc.parse::<Dbc>() {
+            Err(Error::Parse { line, message }) => {
+                assert_eq!(line, 3);
+                assert_eq!(message, "invalid message id");
+            }
+            result => panic!("unexpected {:?}", result),
+        }
+    }
+}
@@ -0,0 +1,588 @@
+//! Vector ASC trace format, as written by CANalyzer and CANoe
+
+use canbusnoop_core::{CanId, Direction, FdFlags, Frame, FD_LENGTHS};
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, Lines};
+
+/// Error class of frames read from `ErrorFrame` events, which carry no details
+const ERR_CLASS_BUS_ERROR: u32 = 0x0000_0080;
+
+const WEEKDAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+#[derive(Debug, Clone, Copy)]
+enum Base {
+    Hex,
+    Dec,
+}
+
+impl Base {
+    fn radix(self) -> u32 {
+        match self {
+            Base::Hex => 16,
+            Base::Dec => 10,
+        }
+    }
+}
+
+/// Reads frames from an ASC trace
+pub(crate) struct Reader<R> {
+    lines: Lines<R>,
+    base: Base,
+    /// Timestamps are relative to the previous event, not to the start
+    relative: bool,
+    /// Start of the measurement, since UNIX epoch
+    start: Duration,
+    /// Time of the last event, since the start of the measurement
+    offset: Duration,
+    /// Interface names (the channel numbers), shared by all their frames
+    interfaces: HashMap<u32, Arc<str>>,
+}
+
+impl<R: AsyncBufRead + Unpin> Reader<R> {
+    pub(crate) fn new(inner: R) -> Self {
+        Reader {
+            lines: inner.lines(),
+            base: Base::Hex,
+            relative: false,
+            start: Duration::ZERO,
+            offset: Duration::ZERO,
+            interfaces: HashMap::new(),
+        }
+    }
+
+    /// Returns the next frame, `None` at the end of the trace. Events other
+    /// than CAN and CAN FD frames are skipped.
+    pub(crate) async fn read(&mut self) -> io::Result<Option<Frame>> {
+        while let Some(line) = self.lines.next_line().await? {
+            let mut tokens = line.split_whitespace();
+
+            let Some(first) = tokens.next() else {
+                continue;
+            };
+
+            match first.to_ascii_lowercase().as_str() {
+                "date" => {
+                    let date: Vec<&str> = tokens.collect();
+                    match parse_date(&date) {
+                        Some(start) => self.start = start,
+                        None => log::warn!("Cannot parse ASC date: {}", line),
+                    }
+                    continue;
+                }
+                "base" => {
+                    self.base = match tokens.next() {
+                        Some("dec") => Base::Dec,
+                        _ => Base::Hex,
+                    };
+                    self.relative = tokens.any(|x| x == "relative");
+                    continue;
+                }
+                _ => {}
+            }
+
+            // Everything else is an event, starting with a timestamp in seconds
+            let Some(time) = first
+                .parse::<f64>()
+                .ok()
+                .and_then(|x| Duration::try_from_secs_f64(x).ok())
+            else {
+                continue;
+            };
+
+            self.offset = if self.relative {
+                self.offset + time
+            } else {
+                time
+            };
+
+            let tokens: Vec<&str> = tokens.collect();
+
+            if let Some((channel, frame)) = parse_event(&tokens, self.base) {
+                let timestamp = self.start + self.offset;
+                let interface = self
+                    .interfaces
+                    .entry(channel)
+                    .or_insert_with(|| channel.to_string().into())
+                    .clone();
+                let frame = frame.with_timestamp(timestamp).with_interface(interface);
+                return Ok(Some(frame));
+            }
+
+            log::debug!("Skipping ASC event: {}", line);
+        }
+
+        Ok(None)
+    }
+}
+
+/// Parse the tokens of an event after the timestamp, returning the channel
+/// and the frame (with no timestamp)
+fn parse_event(tokens: &[&str], base: Base) -> Option<(u32, Frame)> {
+    match tokens {
+        ["CANFD", channel, direction, rest @ ..] => {
+            let channel = channel.parse().ok()?;
+            let direction = parse_direction(direction)?;
+            let frame = parse_fd_frame(rest, base)?.with_direction(direction);
+            Some((channel, frame))
+        }
+        [channel, error, ..] if error.eq_ignore_ascii_case("errorframe") => {
+            Some((channel.parse().ok()?, error_frame()))
+        }
+        [channel, id, direction, kind, rest @ ..] => {
+            let channel = channel.parse().ok()?;
+            let id = parse_id(id, base)?;
+            let direction = parse_direction(direction)?;
+
+            let frame = match *kind {
+                "d" => {
+                    let dlc = usize::from_str_radix(rest.first()?, base.radix()).ok()?;
+                    let data = parse_data(rest.get(1..(1 + dlc.min(8)))?, base)?;
+                    Frame::new(id, data, Duration::ZERO)
+                }
+                "r" => {
+                    let dlc = match rest.first() {
+                        Some(dlc) => usize::from_str_radix(dlc, base.radix()).ok()?,
+                        None => 0,
+                    };
+                    Frame::new(id.with_rtr(true), vec![0; dlc.min(8)], Duration::ZERO)
+                }
+                _ => return None,
+            };
+
+            Some((channel, frame.with_direction(direction)))
+        }
+        _ => None,
+    }
+}
+
+/// `id [symbolic name] brs esi dlc data_length data...`
+fn parse_fd_frame(tokens: &[&str], base: Base) -> Option<Frame> {
+    let (id, rest) = tokens.split_first()?;
+
+    if id.eq_ignore_ascii_case("errorframe") {
+        return Some(error_frame());
+    }
+
+    let id = parse_id(id, base)?;
+
+    // The symbolic name is optional, BRS is always a digit
+    let rest = match rest.first() {
+        Some(x) if x.parse::<u8>().is_ok() => rest,
+        _ => rest.get(1..)?,
+    };
+
+    let [brs, esi, _dlc, len, data @ ..] = rest else {
+        return None;
+    };
+
+    let flags = FdFlags {
+        brs: *brs == "1",
+        esi: *esi == "1",
+    };
+
+    let len: usize = len.parse().ok()?;
+    let data = parse_data(data.get(..len)?, base)?;
+
+    Some(Frame::new_fd(id, data, flags, Duration::ZERO))
+}
+
+fn error_frame() -> Frame {
+    let id = CanId::from_raw(CanId::ERR_FLAG | ERR_CLASS_BUS_ERROR);
+    Frame::new(id, vec![0; 8], Duration::ZERO)
+}
+
+/// Hex or decimal id, with a trailing `x` for extended ids
+fn parse_id(s: &str, base: Base) -> Option<CanId> {
+    match s.strip_suffix('x').or_else(|| s.strip_suffix('X')) {
+        Some(id) => CanId::extended(u32::from_str_radix(id, base.radix()).ok()?),
+        None => CanId::standard(u16::from_str_radix(s, base.radix()).ok()?),
+    }
+}
+
+fn parse_direction(s: &str) -> Option<Direction> {
+    match s {
+        "Rx" => Some(Direction::Rx),
+        "Tx" | "TxRq" => Some(Direction::Tx),
+        _ => None,
+    }
+}
+
+fn parse_data(tokens: &[&str], base: Base) -> Option<Vec<u8>> {
+    tokens
+        .iter()
+        .map(|x| u8::from_str_radix(x, base.radix()).ok())
+        .collect()
+}
+
+/// Writes frames in the ASC format, with hex base and absolute timestamps
+pub(crate) struct Writer<W: Write> {
+    inner: W,
+    /// Capture time of the first frame, the start of the measurement
+    start: Option<Duration>,
+    /// Channel numbers, assigned to interface names in order of appearance
+    channels: HashMap<String, u32>,
+}
+
+impl<W: Write> Writer<W> {
+    pub(crate) fn new(inner: W) -> Self {
+        Writer {
+            inner,
+            start: None,
+            channels: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn write(&mut self, frame: &Frame) -> io::Result<()> {
+        let start = match self.start {
+            Some(start) => start,
+            None => {
+                // Same precision of the date in the header, so timestamps
+                // are exact when the trace is read back
+                let ts = frame.timestamp();
+                let start = ts - Duration::from_nanos(ts.subsec_nanos() as u64 % 1_000_000);
+                self.write_header(start)?;
+                self.start = Some(start);
+                start
+            }
+        };
+
+        let time = frame.timestamp().saturating_sub(start).as_secs_f64();
+        let channel = self.channel(frame.interface());
+        let id = frame.id();
+        let direction = match frame.direction() {
+            Direction::Rx => "Rx",
+            Direction::Tx => "Tx",
+        };
+
+        if id.is_error() {
+            return writeln!(self.inner, "{:11.6} {}  ErrorFrame", time, channel);
+        }
+
+        let id_str = if id.is_extended() {
+            format!("{:X}x", id.id())
+        } else {
+            format!("{:X}", id.id())
+        };
+
+        let data = frame
+            .data()
+            .iter()
+            .map(|b| format!("{:02X}", b))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        if let Some(flags) = frame.fd_flags() {
+            let len = frame.data().len();
+            let dlc = FD_LENGTHS.iter().position(|&x| x >= len).unwrap_or(15);
+            let fd_flags = 0x1000 | ((flags.brs as u32) << 13) | ((flags.esi as u32) << 14);
+            return writeln!(
+                self.inner,
+                "{:11.6} CANFD {:>3} {:<4} {:>8} {} {} {:x} {:>2} {} {:>8} {:>4} {:>8X} {:>8} {:>8} {:>8} {:>8} {:>8}",
+                time,
+                channel,
+                direction,
+                id_str,
+                flags.brs as u8,
+                flags.esi as u8,
+                dlc,
+                len,
+                data,
+                0,
+                0,
+                fd_flags,
+                0,
+                0,
+                0,
+                0,
+                0,
+            );
+        }
+
+        if id.is_rtr() {
+            return writeln!(
+                self.inner,
+                "{:11.6} {}  {:<15} {:<4} r {:x}",
+                time,
+                channel,
+                id_str,
+                direction,
+                frame.data().len()
+            );
+        }
+
+        writeln!(
+            self.inner,
+            "{:11.6} {}  {:<15} {:<4} d {:x} {}",
+            time,
+            channel,
+            id_str,
+            direction,
+            frame.data().len(),
+            data
+        )
+    }
+
+    /// Write the end of the measurement. Must be called once, at the end.
+    pub(crate) fn finish(&mut self) -> io::Result<()> {
+        if self.start.is_some() {
+            writeln!(self.inner, "End TriggerBlock")?;
+        }
+        self.inner.flush()
+    }
+
+    pub(crate) fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+
+    fn write_header(&mut self, start: Duration) -> io::Result<()> {
+        let date = format_date(start);
+        writeln!(self.inner, "date {}", date)?;
+        writeln!(self.inner, "base hex  timestamps absolute")?;
+        writeln!(self.inner, "internal events logged")?;
+        writeln!(self.inner, "// version 9.0.0")?;
+        writeln!(self.inner, "Begin Triggerblock {}", date)?;
+        writeln!(self.inner, "{:11.6} Start of measurement", 0.)
+    }
+
+    /// Interfaces named after a channel number keep it, the others get the
+    /// first free channel number
+    fn channel(&mut self, interface: Option<&str>) -> u32 {
+        let interface = interface.unwrap_or_default();
+
+        if let Some(&channel) = self.channels.get(interface) {
+            return channel;
+        }
+
+        let channel = match interface.parse::<u32>() {
+            Ok(channel) if channel > 0 => channel,
+            _ => (1..)
+                .find(|x| !self.channels.values().any(|y| y == x))
+                .unwrap_or(1),
+        };
+
+        self.channels.insert(interface.to_string(), channel);
+        channel
+    }
+}
+
+/// Parse an ASC date like `Wed Jun 5 10:22:31.123 am 2024`, as UTC
+fn parse_date(tokens: &[&str]) -> Option<Duration> {
+    let (month, day, time, rest) = match tokens {
+        [_weekday, month, day, time, rest @ ..] => (month, day, time, rest),
+        _ => return None,
+    };
+
+    let (pm, year) = match rest {
+        [meridiem, year] => (meridiem.eq_ignore_ascii_case("pm"), year),
+        [year] => (false, year),
+        _ => return None,
+    };
+
+    let month = MONTHS.iter().position(|x| x.eq_ignore_ascii_case(month))? as u32 + 1;
+    let day: u32 = day.parse().ok()?;
+    let year: i64 = year.parse().ok()?;
+
+    let mut hms = time.split(':');
+    let hour: u64 = hms.next()?.parse().ok()?;
+    let min: u64 = hms.next()?.parse().ok()?;
+    let sec = hms.next()?;
+    let (sec, millis) = sec.split_once('.').unwrap_or((sec, "0"));
+    let sec: u64 = sec.parse().ok()?;
+    let millis: u64 = format!("{:0<3}", millis).get(..3)?.parse().ok()?;
+
+    // 12 hour clock, when am/pm is present
+    let hour = match (rest.len(), pm, hour) {
+        (2, false, 12) => 0,
+        (2, true, h) if h < 12 => h + 12,
+        (_, _, h) => h,
+    };
+
+    let days = u64::try_from(days_from_civil(year, month, day)).ok()?;
+    let secs = days * 86400 + hour * 3600 + min * 60 + sec;
+
+    Some(Duration::from_secs(secs) + Duration::from_millis(millis))
+}
+
+/// Format a date like `Wed Jun 05 10:22:31.123 am 2024`, as UTC
+fn format_date(t: Duration) -> String {
+    let secs = t.as_secs();
+    let days = (secs / 86400) as i64;
+    let (year, month, day) = civil_from_days(days);
+    let weekday = WEEKDAYS[((days + 4) % 7) as usize];
+    let month = MONTHS[(month - 1) as usize];
+
+    let secs_of_day = secs % 86400;
+    let hour = secs_of_day / 3600;
+    let min = (secs_of_day / 60) % 60;
+    let sec = secs_of_day % 60;
+    let millis = t.subsec_millis();
+
+    let meridiem = if hour < 12 { "am" } else { "pm" };
+    let hour = match hour % 12 {
+        0 => 12,
+        h => h,
+    };
+
+    format!(
+        "{} {} {:02} {:02}:{:02}:{:02}.{:03} {} {}",
+        weekday, month, day, hour, min, sec, millis, meridiem, year
+    )
+}
+
+/// Days since 1970-01-01 of a date in the proleptic Gregorian calendar
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let month = month as i64;
+    let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+/// Date in the proleptic Gregorian calendar of a number of days since
+/// 1970-01-01
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn read_all(trace: &[u8]) -> Vec<Frame> {
+        let mut reader = Reader::new(trace);
+        let mut frames = Vec::new();
+        while let Some(frame) = reader.read().await.unwrap() {
+            frames.push(frame);
+        }
+        frames
+    }
+
+    fn date(secs: u64, millis: u64) -> Duration {
+        Duration::from_secs(secs) + Duration::from_millis(millis)
+    }
+
+    #[tokio::test]
+    async fn round_trip() {
+        let start = Duration::from_secs(1_717_582_951);
+        let us = |us: u64| start + Duration::from_micros(us);
+        let interface: Arc<str> = "1".into();
+        let other: Arc<str> = "2".into();
+        let frames = vec![
+            Frame::new(CanId::standard(0x123).unwrap(), vec![1, 2, 3], us(0))
+                .with_interface(interface.clone()),
+            Frame::new(
+                CanId::extended(0x18FEF100).unwrap(),
+                vec![0xAA; 8],
+                us(1500),
+            )
+            .with_interface(other.clone())
+            .with_direction(Direction::Tx),
+            Frame::new(
+                CanId::standard(0x7DF).unwrap().with_rtr(true),
+                vec![0; 4],
+                us(2000),
+            )
+            .with_interface(interface.clone()),
+            Frame::new_fd(
+                CanId::standard(0x456).unwrap(),
+                (0..12).collect(),
+                FdFlags {
+                    brs: true,
+                    esi: false,
+                },
+                us(10_250),
+            )
+            .with_interface(interface.clone()),
+            error_frame()
+                .with_timestamp(us(20_000))
+                .with_interface(other),
+        ];
+
+        let mut trace = Vec::new();
+        let mut writer = Writer::new(&mut trace);
+        for frame in &frames {
+            writer.write(frame).unwrap();
+        }
+        writer.finish().unwrap();
+
+        let read = read_all(&trace).await;
+        assert_eq!(read.len(), frames.len());
+        for (a, b) in frames.iter().zip(&read) {
+            assert_eq!(a.id(), b.id());
+            assert_eq!(a.data(), b.data());
+            assert_eq!(a.timestamp(), b.timestamp());
+            assert_eq!(a.fd_flags(), b.fd_flags());
+            assert_eq!(a.interface(), b.interface());
+            assert_eq!(a.direction(), b.direction());
+        }
+    }
+
+    #[test]
+    fn parses_date() {
+        let parse = |s: &str| parse_date(&s.split_whitespace().collect::<Vec<_>>());
+
+        let morning = date(1_717_582_951, 123);
+        assert_eq!(parse("Wed Jun 5 10:22:31.123 am 2024"), Some(morning));
+        assert_eq!(parse("Wed Jun 05 10:22:31.123 2024"), Some(morning));
+        assert_eq!(
+            parse("Wed Jun 5 10:22:31.123 pm 2024"),
+            Some(date(1_717_626_151, 123))
+        );
+        assert_eq!(
+            parse("Mon Jan 1 12:05:00 am 2024"),
+            Some(date(1_704_067_500, 0))
+        );
+        assert_eq!(parse("Wed Jun 5 10:22 am 2024"), None);
+        assert_eq!(parse("Wed Foo 5 10:22:31.123 am 2024"), None);
+    }
+
+    #[test]
+    fn formats_date() {
+        let t = date(1_717_626_151, 123);
+        let s = format_date(t);
+        assert_eq!(s, "Wed Jun 05 10:22:31.123 pm 2024");
+        assert_eq!(
+            parse_date(&s.split_whitespace().collect::<Vec<_>>()),
+            Some(t)
+        );
+    }
+
+    #[tokio::test]
+    async fn reads_relative_decimal_timestamps_from_the_start_date() {
+        let trace = b"date Wed Jun 5 10:22:31.123 am 2024
+base dec  timestamps relative
+Begin Triggerblock Wed Jun 5 10:22:31.123 am 2024
+   0.000000 Start of measurement
+   0.500000 1  291             Rx   d 2 1 255
+   0.250000 1  291x            Rx   d 0
+End TriggerBlock
+";
+        let frames = read_all(trace).await;
+
+        let start = date(1_717_582_951, 123);
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].id(), CanId::standard(0x123).unwrap());
+        assert_eq!(frames[0].data(), [1, 255]);
+        assert_eq!(frames[0].timestamp(), start + Duration::from_millis(500));
+        assert_eq!(frames[1].id(), CanId::extended(0x123).unwrap());
+        assert_eq!(frames[1].timestamp(), start + Duration::from_millis(750));
+    }
+}
@@ -0,0 +1,352 @@
+//! Linux `candump -l` log format: `(timestamp) iface ID#DATA`
+
+use canbusnoop_core::{CanId, FdFlags, Frame};
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, Lines};
+
+/// Interface name used for frames without one, as candump does when reading
+/// from all the interfaces
+const ANY_INTERFACE: &str = "any";
+
+/// Writes frames in the candump log format
+pub(crate) struct Writer<W: Write> {
+    inner: W,
+}
+
+impl<W: Write> Writer<W> {
+    pub(crate) fn new(inner: W) -> Self {
+        Writer { inner }
+    }
+
+    pub(crate) fn write(&mut self, frame: &Frame) -> io::Result<()> {
+        writeln!(self.inner, "{}", format_frame(frame))
+    }
+
+    pub(crate) fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Format a frame as a single candump log line, without the line terminator
+pub(crate) fn format_frame(frame: &Frame) -> String {
+    let ts = frame.timestamp();
+    let interface = frame.interface().unwrap_or(ANY_INTERFACE);
+    let id = frame.id();
+
+    let mut line = format!(
+        "({}.{:06}) {} {}",
+        ts.as_secs(),
+        ts.subsec_micros(),
+        interface,
+        format_id(id)
+    );
+
+    if let Some(flags) = frame.fd_flags() {
+        let flags = (flags.brs as u8) | ((flags.esi as u8) << 1);
+        line.push_str(&format!("##{:X}", flags));
+    } else if id.is_rtr() {
+        line.push_str("#R");
+        if !frame.data().is_empty() {
+            line.push_str(&format!("{:X}", frame.data().len()));
+        }
+        return line;
+    } else {
+        line.push('#');
+    }
+
+    for b in frame.data() {
+        line.push_str(&format!("{:02X}", b));
+    }
+
+    line
+}
+
+/// Error frames keep the ERR flag in the id, like candump does
+fn format_id(id: CanId) -> String {
+    if id.is_error() {
+        format!("{:08X}", id.id() | CanId::ERR_FLAG)
+    } else {
+        id.to_string()
+    }
+}
+
+/// Reads frames from a candump log
+pub(crate) struct Reader<R> {
+    lines: Lines<R>,
+    line_number: usize,
+    /// Interface names, shared by all the frames of the same interface
+    interfaces: HashMap<String, Arc<str>>,
+}
+
+impl<R: AsyncBufRead + Unpin> Reader<R> {
+    pub(crate) fn new(inner: R) -> Self {
+        Reader {
+            lines: inner.lines(),
+            line_number: 0,
+            interfaces: HashMap::new(),
+        }
+    }
+
+    /// Returns the next frame, `None` at the end of the log. Invalid lines are
+    /// skipped.
+    pub(crate) async fn read(&mut self) -> io::Result<Option<Frame>> {
+        while let Some(line) = self.lines.next_line().await? {
+            self.line_number += 1;
+
+            let line = line.trim();
+
+            if line.is_empty() {
+                continue;
+            }
+
+            match parse_line(line) {
+                Ok((interface, frame)) => {
+                    let interface = self
+                        .interfaces
+                        .entry(interface.to_string())
+                        .or_insert_with(|| interface.into())
+                        .clone();
+                    return Ok(Some(frame.with_interface(interface)));
+                }
+                Err(e) => log::warn!("candump log line {}: {}", self.line_number, e),
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub(crate) enum ParseError {
+    #[error("invalid timestamp")]
+    InvalidTimestamp,
+    #[error("missing interface")]
+    MissingInterface,
+    #[error("invalid id")]
+    InvalidId,
+    #[error("invalid data")]
+    InvalidData,
+}
+
+/// Parse a candump log line, returning the interface name and the frame
+fn parse_line(line: &str) -> Result<(&str, Frame), ParseError> {
+    let mut fields = line.split_whitespace();
+
+    let timestamp = fields
+        .next()
+        .and_then(|x| x.strip_prefix('('))
+        .and_then(|x| x.strip_suffix(')'))
+        .ok_or(ParseError::InvalidTimestamp)?;
+    let timestamp = parse_timestamp(timestamp)?;
+
+    let interface = fields.next().ok_or(ParseError::MissingInterface)?;
+
+    let frame = fields.next().ok_or(ParseError::InvalidId)?;
+    let (id, payload) = frame.split_once('#').ok_or(ParseError::InvalidId)?;
+    let id = parse_id(id)?;
+
+    let frame = if let Some(payload) = payload.strip_prefix('#') {
+        // CAN FD: flags nibble, then data
+        let mut chars = payload.chars();
+        let flags = chars
+            .next()
+            .and_then(|c| c.to_digit(16))
+            .ok_or(ParseError::InvalidData)?;
+        let flags = FdFlags {
+            brs: flags & 0x01 != 0,
+            esi: flags & 0x02 != 0,
+        };
+        let data = parse_data(chars.as_str())?;
+        Frame::new_fd(id, data, flags, timestamp)
+    } else if let Some(len) = payload.strip_prefix('R') {
+        // Remote transmission request, with optional length
+        let len = match len {
+            "" => 0,
+            len => usize::from_str_radix(len, 16).map_err(|_| ParseError::InvalidData)?,
+        };
+        Frame::new(id.with_rtr(true), vec![0; len.min(8)], timestamp)
+    } else {
+        let data = parse_data(payload)?;
+        Frame::new(id, data, timestamp)
+    };
+
+    Ok((interface, frame))
+}
+
+fn parse_timestamp(s: &str) -> Result<Duration, ParseError> {
+    let (secs, frac) = s.split_once('.').unwrap_or((s, ""));
+
+    let secs: u64 = secs.parse().map_err(|_| ParseError::InvalidTimestamp)?;
+
+    if frac.len() > 9 || !frac.chars().all(|c| c.is_ascii_digit()) {
+        return Err(ParseError::InvalidTimestamp);
+    }
+
+    // Fraction of a second, right padded to nanoseconds
+    let nanos = format!("{:0<9}", frac)
+        .parse()
+        .map_err(|_| ParseError::InvalidTimestamp)?;
+
+    Ok(Duration::new(secs, nanos))
+}
+
+/// 3 hex digits for standard ids, 8 for extended ids and error frames
+fn parse_id(s: &str) -> Result<CanId, ParseError> {
+    let raw = u32::from_str_radix(s, 16).map_err(|_| ParseError::InvalidId)?;
+
+    match s.len() {
+        1..=3 => CanId::standard(raw as u16).ok_or(ParseError::InvalidId),
+        8 if raw & CanId::ERR_FLAG != 0 => Ok(CanId::from_raw(raw & !CanId::EFF_FLAG)),
+        8 => CanId::extended(raw).ok_or(ParseError::InvalidId),
+        _ => Err(ParseError::InvalidId),
+    }
+}
+
+/// Hex data, optionally with `.` separators between bytes
+fn parse_data(s: &str) -> Result<Vec<u8>, ParseError> {
+    let digits: Vec<u8> = s.bytes().filter(|&c| c != b'.').collect();
+
+    let pairs = digits.chunks_exact(2);
+
+    if !pairs.remainder().is_empty() {
+        return Err(ParseError::InvalidData);
+    }
+
+    pairs
+        .map(|pair| {
+            let pair = std::str::from_utf8(pair).map_err(|_| ParseError::InvalidData)?;
+            u8::from_str_radix(pair, 16).map_err(|_| ParseError::InvalidData)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frames() -> Vec<Frame> {
+        let us = |us: u64| Duration::from_secs(1_700_000_000) + Duration::from_micros(us);
+        let vcan0: Arc<str> = "vcan0".into();
+        vec![
+            Frame::new(CanId::standard(0x123).unwrap(), vec![0xDE, 0xAD], us(1)),
+            Frame::new(CanId::standard(0x7FF).unwrap(), vec![], us(10)),
+            Frame::new(
+                CanId::extended(0x18FE_F100).unwrap(),
+                vec![0x11; 8],
+                us(999_999),
+            ),
+            Frame::new(
+                CanId::standard(0x7DF).unwrap().with_rtr(true),
+                vec![],
+                us(1_000_000),
+            ),
+            Frame::new(
+                CanId::extended(0x100).unwrap().with_rtr(true),
+                vec![0; 3],
+                us(1_000_001),
+            ),
+            Frame::new_fd(
+                CanId::standard(0x456).unwrap(),
+                (0..12).collect(),
+                FdFlags {
+                    brs: true,
+                    esi: true,
+                },
+                us(2_000_000),
+            ),
+            Frame::new_fd(
+                CanId::extended(0x1234_5678).unwrap(),
+                vec![0xAB; 64],
+                FdFlags::default(),
+                us(2_000_500),
+            ),
+            Frame::new(
+                CanId::from_raw(CanId::ERR_FLAG | 0x204),
+                vec![0, 0x08, 0, 0, 0, 0, 96, 0],
+                us(3_000_000),
+            ),
+        ]
+        .into_iter()
+        .map(|frame| frame.with_interface(vcan0.clone()))
+        .collect()
+    }
+
+    #[test]
+    fn formats_lines() {
+        let lines: Vec<_> = frames().iter().map(format_frame).collect();
+        assert_eq!(
+            lines,
+            [
+                "(1700000000.000001) vcan0 123#DEAD",
+                "(1700000000.000010) vcan0 7FF#",
+                "(1700000000.999999) vcan0 18FEF100#1111111111111111",
+                "(1700000001.000000) vcan0 7DF#R",
+                "(1700000001.000001) vcan0 00000100#R3",
+                "(1700000002.000000) vcan0 456##3000102030405060708090A0B",
+                &format!("(1700000002.000500) vcan0 12345678##0{}", "AB".repeat(64)),
+                "(1700000003.000000) vcan0 20000204#0008000000006000",
+            ]
+        );
+
+        let frame = Frame::new(CanId::standard(0x1).unwrap(), vec![], Duration::ZERO);
+        assert_eq!(format_frame(&frame), "(0.000000) any 001#");
+    }
+
+    #[tokio::test]
+    async fn round_trip() {
+        let frames = frames();
+        let mut log = Vec::new();
+        let mut writer = Writer::new(&mut log);
+        for frame in &frames {
+            writer.write(frame).unwrap();
+        }
+        writer.flush().unwrap();
+
+        let mut reader = Reader::new(&log[..]);
+        let mut read = Vec::new();
+        while let Some(frame) = reader.read().await.unwrap() {
+            read.push(frame);
+        }
+
+        assert_eq!(read.len(), frames.len());
+        for (a, b) in frames.iter().zip(&read) {
+            assert_eq!(a.id(), b.id());
+            assert_eq!(a.data(), b.data());
+            assert_eq!(a.timestamp(), b.timestamp());
+            assert_eq!(a.fd_flags(), b.fd_flags());
+            assert_eq!(a.interface(), b.interface());
+        }
+    }
+
+    #[test]
+    fn parses_variants() {
+        let (interface, frame) = parse_line("(12.5) can1 123#01.02.03").unwrap();
+        assert_eq!(interface, "can1");
+        assert_eq!(frame.timestamp(), Duration::from_millis(12_500));
+        assert_eq!(frame.data(), [1, 2, 3]);
+
+        let (_, frame) = parse_line("(1.000000) can1 123#R").unwrap();
+        assert!(frame.id().is_rtr());
+        assert!(frame.data().is_empty());
+    }
+
+    #[test]
+    fn rejects_invalid_lines() {
+        for (line, error) in [
+            ("1.0 can0 123#00", "invalid timestamp"),
+            ("(1.x) can0 123#00", "invalid timestamp"),
+            ("(1.0)", "missing interface"),
+            ("(1.0) can0", "invalid id"),
+            ("(1.0) can0 12345#00", "invalid id"),
+            ("(1.0) can0 800#00", "invalid id"),
+            ("(1.0) can0 123#0", "invalid data"),
+            ("(1.0) can0 123##G00", "invalid data"),
+        ] {
+            let e = parse_line(line).map(|_| ()).unwrap_err();
+            assert_eq!(e.to_string(), error, "{}", line);
+        }
+    }
+}
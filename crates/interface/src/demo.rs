@@ -1,36 +1,141 @@
+use std::sync::Arc;
 use std::time::Duration;
 
-use canbusnoop_core::Frame;
+use anyhow::Result;
+use canbusnoop_core::{CanId, Direction, FdFlags, Frame, FD_LENGTHS};
+use tokio::sync::mpsc;
+use tokio::time::Instant;
 
 pub(crate) struct Reader {
     prng: oorandom::Rand32,
-    ids: Vec<u32>,
+    /// Simulated ids, with their CAN FD flags (`None` for classic CAN)
+    ids: Vec<(CanId, Option<FdFlags>)>,
+    /// Wall clock time and virtual time when the demo started
+    origin: (Instant, Duration),
+    /// Virtual clock, advanced by the simulated delay between frames
+    clock: Duration,
+    /// Next simulated frame, kept here so a cancelled read does not lose it
+    next: Option<Frame>,
+    /// Frames sent by a [`Writer`], looped back on the virtual bus
+    sent: mpsc::UnboundedReceiver<Frame>,
+    sender: mpsc::UnboundedSender<Frame>,
+    interface: Arc<str>,
 }
 
 impl Reader {
     pub(crate) fn new() -> Self {
         let mut prng = oorandom::Rand32::new(0);
         let ids = generate_ids(&mut prng);
-        Reader { prng, ids }
+        let clock = canbusnoop_core::now();
+        let origin = (Instant::now(), clock);
+        let (sender, sent) = mpsc::unbounded_channel();
+        let interface = "demo".into();
+        Reader {
+            prng,
+            ids,
+            origin,
+            clock,
+            next: None,
+            sent,
+            sender,
+            interface,
+        }
     }
 
     pub(crate) async fn read(&mut self) -> Option<Frame> {
+        if self.next.is_none() {
+            self.next = Some(self.generate());
+        }
+
+        let (start, first) = self.origin;
+        let deadline = start + (self.clock - first);
+
+        let frame = tokio::select! {
+            _ = tokio::time::sleep_until(deadline) => self.next.take()?,
+            Some(frame) = self.sent.recv() => {
+                let timestamp = first + start.elapsed();
+                frame.with_timestamp(timestamp).with_direction(Direction::Tx)
+            }
+        };
+
+        Some(frame.with_interface(self.interface.clone()))
+    }
+
+    pub(crate) fn writer(&self) -> Writer {
+        Writer {
+            sender: self.sender.clone(),
+        }
+    }
+
+    fn generate(&mut self) -> Frame {
         let delay = self.prng.rand_range(1..100).into();
-        tokio::time::sleep(Duration::from_millis(delay)).await;
+        let delay = Duration::from_millis(delay);
+        self.clock += delay;
         let rand_id_index = self.prng.rand_range(0..(self.ids.len() as u32)) as usize;
-        let id = self.ids[rand_id_index];
-        let data = generate_data(&mut self.prng);
-        let frame = Frame::new(id, data);
-        Some(frame)
+        let (id, fd) = self.ids[rand_id_index];
+        match fd {
+            Some(flags) => {
+                let data = generate_fd_data(&mut self.prng);
+                Frame::new_fd(id, data, flags, self.clock)
+            }
+            None => {
+                let data = generate_data(&mut self.prng);
+                Frame::new(id, data, self.clock)
+            }
+        }
     }
 }
 
-fn generate_ids(prng: &mut oorandom::Rand32) -> Vec<u32> {
+/// Sends frames to the virtual bus of a demo [`Reader`]
+#[derive(Clone)]
+pub(crate) struct Writer {
+    sender: mpsc::UnboundedSender<Frame>,
+}
+
+impl Writer {
+    pub(crate) fn send(&self, frame: &Frame) -> Result<()> {
+        self.sender
+            .send(frame.clone())
+            .map_err(|_| anyhow::anyhow!("demo bus is closed"))
+    }
+}
+
+fn generate_ids(prng: &mut oorandom::Rand32) -> Vec<(CanId, Option<FdFlags>)> {
     let n = prng.rand_range(8..16);
-    (0..n).map(|_| prng.rand_u32()).collect()
+    (0..n)
+        .map(|_| (generate_id(prng), generate_fd_flags(prng)))
+        .collect()
+}
+
+/// Randomly choose between classic CAN, CAN FD and CAN FD with bit rate switch
+fn generate_fd_flags(prng: &mut oorandom::Rand32) -> Option<FdFlags> {
+    match prng.rand_range(0..4) {
+        0 => Some(FdFlags::default()),
+        1 => Some(FdFlags {
+            brs: true,
+            esi: false,
+        }),
+        _ => None,
+    }
+}
+
+/// Generate a random id, standard or extended
+fn generate_id(prng: &mut oorandom::Rand32) -> CanId {
+    let raw = prng.rand_u32();
+    if raw & 1 == 0 {
+        CanId::from_raw(raw & CanId::SFF_MASK)
+    } else {
+        CanId::from_raw((raw & CanId::EFF_MASK) | CanId::EFF_FLAG)
+    }
 }
 
 fn generate_data(prng: &mut oorandom::Rand32) -> Vec<u8> {
     let n = prng.rand_range(1..8);
     (0..n).map(|_| prng.rand_u32() as u8).collect()
 }
+
+fn generate_fd_data(prng: &mut oorandom::Rand32) -> Vec<u8> {
+    let dlc = prng.rand_range(1..(FD_LENGTHS.len() as u32)) as usize;
+    let n = FD_LENGTHS[dlc];
+    (0..n).map(|_| prng.rand_u32() as u8).collect()
+}
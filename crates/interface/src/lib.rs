@@ -1,15 +1,37 @@
+mod asc;
+mod candump;
 mod demo;
+mod pcap;
+mod record;
+mod replay;
 mod socket_can;
+mod writer;
+
+pub use record::Recorder;
+pub use replay::{Playback, Speed};
+pub use writer::{CanBusWriter, TxRequest};
 
 use anyhow::Result;
 use canbusnoop_core::Frame;
+use std::path::PathBuf;
+use std::sync::Arc;
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
-    #[error("socket can error")]
-    SocketCanError(#[from] tokio_socketcan::Error),
+    #[error("I/O error: {0}")]
+    SocketCanError(#[from] std::io::Error),
     #[error("invalid interface: {0}")]
     InvalidInterface(String),
+    #[error("interface not found: {0}")]
+    InterfaceNotFound(String),
+    #[error("not a CAN interface: {0}")]
+    NotCanInterface(String),
+    #[error("interface is down: {0}")]
+    InterfaceDown(String),
+    #[error("unsupported file format: {0:?}")]
+    UnsupportedFile(PathBuf),
+    #[error("cannot transmit while replaying a file")]
+    ReadOnly,
 }
 
 pub struct CanBusReader {
@@ -21,39 +43,152 @@ impl CanBusReader {
         let inner = match config {
             Config::SocketCan(cfg) => InnerCanBusReader::SocketCan(socket_can::Reader::new(cfg)?),
             Config::Demo => InnerCanBusReader::Demo(demo::Reader::new()),
+            Config::File(cfg) => InnerCanBusReader::Replay(Box::new(replay::Reader::new(cfg)?)),
         };
         Ok(CanBusReader { inner })
     }
 
-    pub async fn read(&mut self) -> Option<Frame> {
+    /// Returns the next frame, `None` when there are no more frames (e.g. at
+    /// the end of a replayed file)
+    pub async fn read(&mut self) -> Result<Option<Frame>> {
         match &mut self.inner {
             InnerCanBusReader::SocketCan(inner) => inner.read().await,
-            InnerCanBusReader::Demo(inner) => inner.read().await,
+            InnerCanBusReader::Demo(inner) => Ok(inner.read().await),
+            InnerCanBusReader::Replay(inner) => inner.read().await,
         }
     }
+
+    /// Create a writer sending frames to the same bus. Files cannot be
+    /// written to while they are replayed.
+    pub fn writer(&self) -> Result<CanBusWriter> {
+        let inner = match &self.inner {
+            InnerCanBusReader::SocketCan(inner) => {
+                writer::InnerCanBusWriter::SocketCan(Arc::new(inner.writer()?))
+            }
+            InnerCanBusReader::Demo(inner) => writer::InnerCanBusWriter::Demo(inner.writer()),
+            InnerCanBusReader::Replay(_) => return Err(Error::ReadOnly.into()),
+        };
+        Ok(CanBusWriter::new(inner))
+    }
 }
 
 #[derive(Debug)]
 pub enum Config {
     SocketCan(socket_can::Config),
     Demo,
+    File(replay::Config),
 }
 
 impl Config {
+    /// Parse an interface specification, in the form `scheme:argument`:
+    ///
+    /// - `socketcan:vcan0` a SocketCAN interface
+    /// - `demo:` randomly generated traffic
+    /// - `file:capture.log` replay of a candump log
+    /// - `file:trace.asc` replay of a Vector ASC trace
+    /// - `file:capture.pcapng` replay of a pcap or pcapng capture
+    ///
+    /// Without a scheme, `demo` selects the demo backend and anything else is
+    /// taken as the name of a SocketCAN interface.
     pub fn new(interface: String) -> Result<Config, Error> {
-        if interface.starts_with("can") {
-            return Ok(Config::SocketCan(socket_can::Config { interface }));
-        }
+        let Some((scheme, argument)) = interface.split_once(':') else {
+            return match interface.as_str() {
+                "demo" => Ok(Config::Demo),
+                _ => Ok(Config::SocketCan(socket_can::Config::new(interface)?)),
+            };
+        };
 
-        if interface.starts_with("demo") {
-            return Ok(Config::Demo);
+        match scheme {
+            "socketcan" if !argument.is_empty() => Ok(Config::SocketCan(socket_can::Config::new(
+                argument.to_string(),
+            )?)),
+            "demo" => Ok(Config::Demo),
+            "file" if !argument.is_empty() => {
+                Ok(Config::File(replay::Config::new(argument.into())?))
+            }
+            _ => Err(Error::InvalidInterface(interface)),
         }
+    }
 
-        Err(Error::InvalidInterface(interface))
+    /// Set the replay options. Ignored if this is not a file.
+    pub fn with_playback(mut self, playback: Playback) -> Config {
+        if let Config::File(cfg) = &mut self {
+            cfg.playback = playback;
+        }
+        self
     }
 }
 
 enum InnerCanBusReader {
     SocketCan(socket_can::Reader),
     Demo(demo::Reader),
+    Replay(Box<replay::Reader>),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(interface: &str) -> Result<Config, Error> {
+        Config::new(interface.to_string())
+    }
+
+    #[test]
+    fn demo() {
+        assert!(matches!(config("demo"), Ok(Config::Demo)));
+        assert!(matches!(config("demo:"), Ok(Config::Demo)));
+    }
+
+    #[test]
+    fn files() {
+        for (path, format) in [
+            ("capture.log", "Candump"),
+            ("trace.asc", "Asc"),
+            ("capture.pcap", "Pcap"),
+            ("dir/capture.pcapng", "Pcap"),
+        ] {
+            let Ok(Config::File(cfg)) = config(&format!("file:{}", path)) else {
+                panic!("{} is not a file", path);
+            };
+            assert_eq!(cfg.path, PathBuf::from(path));
+            assert_eq!(format!("{:?}", cfg.format), format);
+        }
+
+        for path in ["capture.txt", "capture"] {
+            assert!(matches!(
+                config(&format!("file:{}", path)),
+                Err(Error::UnsupportedFile(p)) if p.as_os_str() == path
+            ));
+        }
+    }
+
+    #[test]
+    fn invalid_schemes() {
+        for interface in ["file:", "socketcan:", "can:vcan0", ":vcan0"] {
+            assert!(
+                matches!(config(interface), Err(Error::InvalidInterface(i)) if i == interface),
+                "{}",
+                interface
+            );
+        }
+    }
+
+    #[test]
+    fn socketcan() {
+        // The links are checked, the loopback is not a CAN interface
+        for interface in ["lo", "socketcan:lo"] {
+            assert!(
+                matches!(config(interface), Err(Error::NotCanInterface(i)) if i == "lo"),
+                "{}",
+                interface
+            );
+        }
+        for interface in ["nocan0", "socketcan:nocan0"] {
+            assert!(
+                matches!(config(interface), Err(Error::InterfaceNotFound(i)) if i == "nocan0"),
+                "{}",
+                interface
+            );
+        }
+    }
 }
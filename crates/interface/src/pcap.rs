@@ -0,0 +1,890 @@
+//! pcap and pcapng capture files, with the LINKTYPE_CAN_SOCKETCAN link type
+
+use canbusnoop_core::{CanId, Direction, FdFlags, Frame};
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// SocketCAN frames, with the id in network byte order
+const LINKTYPE_CAN_SOCKETCAN: u32 = 227;
+
+const PCAP_MAGIC_MICROS: u32 = 0xA1B2_C3D4;
+const PCAP_MAGIC_NANOS: u32 = 0xA1B2_3C4D;
+
+const PCAPNG_SHB: u32 = 0x0A0D_0D0A;
+const PCAPNG_IDB: u32 = 0x0000_0001;
+const PCAPNG_SPB: u32 = 0x0000_0003;
+const PCAPNG_EPB: u32 = 0x0000_0006;
+const PCAPNG_BYTE_ORDER_MAGIC: u32 = 0x1A2B_3C4D;
+
+const OPT_END: u16 = 0;
+const OPT_IF_NAME: u16 = 2;
+const OPT_IF_TSRESOL: u16 = 9;
+const OPT_IF_TSOFFSET: u16 = 14;
+const OPT_EPB_FLAGS: u16 = 2;
+
+/// Upper limit to the size of a block, to detect corrupted files
+const MAX_BLOCK_LEN: usize = 16 * 1024 * 1024;
+
+/// Size of a classic CAN frame (struct can_frame)
+const CAN_MTU: usize = 16;
+
+/// Size of a CAN FD frame (struct canfd_frame)
+const CANFD_MTU: usize = 72;
+
+const CANFD_BRS: u8 = 0x01;
+const CANFD_ESI: u8 = 0x02;
+const CANFD_FDF: u8 = 0x04;
+
+/// Reads frames from a pcap or pcapng file, detecting the format from the
+/// magic number
+pub(crate) struct Reader<R> {
+    inner: R,
+    format: Option<Format>,
+}
+
+enum Format {
+    Pcap {
+        big_endian: bool,
+        /// Timestamp fraction in nanoseconds, instead of microseconds
+        nanos: bool,
+        linktype: u32,
+        interface: Arc<str>,
+    },
+    Pcapng {
+        big_endian: bool,
+        /// Interfaces of the current section, by id
+        interfaces: Vec<Interface>,
+    },
+}
+
+struct Interface {
+    linktype: u16,
+    name: Arc<str>,
+    /// Timestamp units per second
+    resolution: u64,
+    /// Seconds added to timestamps
+    offset: i64,
+}
+
+impl<R: AsyncRead + Unpin> Reader<R> {
+    pub(crate) fn new(inner: R) -> Self {
+        Reader {
+            inner,
+            format: None,
+        }
+    }
+
+    /// Returns the next frame, `None` at the end of the file. Packets with a
+    /// link type other than SocketCAN are skipped.
+    pub(crate) async fn read(&mut self) -> io::Result<Option<Frame>> {
+        if self.format.is_none() {
+            let mut magic = [0u8; 4];
+            if !read_exact_or_eof(&mut self.inner, &mut magic).await? {
+                return Ok(None);
+            }
+            self.format = Some(self.read_file_header(magic).await?);
+        }
+
+        loop {
+            let frame = match &self.format {
+                Some(Format::Pcap { .. }) => self.read_pcap_record().await?,
+                Some(Format::Pcapng { .. }) => self.read_pcapng_block().await?,
+                None => unreachable!(),
+            };
+
+            match frame {
+                Packet::Frame(frame) => return Ok(Some(frame)),
+                Packet::Skip => continue,
+                Packet::End => return Ok(None),
+            }
+        }
+    }
+
+    async fn read_file_header(&mut self, magic: [u8; 4]) -> io::Result<Format> {
+        if u32::from_le_bytes(magic) == PCAPNG_SHB {
+            let big_endian = self.read_section_header().await?;
+            return Ok(Format::Pcapng {
+                big_endian,
+                interfaces: Vec::new(),
+            });
+        }
+
+        let (big_endian, nanos) = match (u32::from_le_bytes(magic), u32::from_be_bytes(magic)) {
+            (PCAP_MAGIC_MICROS, _) => (false, false),
+            (PCAP_MAGIC_NANOS, _) => (false, true),
+            (_, PCAP_MAGIC_MICROS) => (true, false),
+            (_, PCAP_MAGIC_NANOS) => (true, true),
+            _ => return Err(invalid_data("not a pcap or pcapng file")),
+        };
+
+        // Version, time zone, accuracy, snap length, link type
+        let mut header = [0u8; 20];
+        self.inner.read_exact(&mut header).await?;
+        let linktype = read_u32(&header[16..], big_endian) & 0x0FFF_FFFF;
+
+        if linktype != LINKTYPE_CAN_SOCKETCAN {
+            log::warn!("pcap link type is {}, not SocketCAN", linktype);
+        }
+
+        Ok(Format::Pcap {
+            big_endian,
+            nanos,
+            linktype,
+            interface: "pcap0".into(),
+        })
+    }
+
+    async fn read_pcap_record(&mut self) -> io::Result<Packet> {
+        let Some(Format::Pcap {
+            big_endian,
+            nanos,
+            linktype,
+            interface,
+        }) = &self.format
+        else {
+            unreachable!()
+        };
+
+        let mut header = [0u8; 16];
+        if !read_exact_or_eof(&mut self.inner, &mut header).await? {
+            return Ok(Packet::End);
+        }
+
+        let secs = read_u32(&header[0..], *big_endian) as u64;
+        let frac = read_u32(&header[4..], *big_endian);
+        let len = read_u32(&header[8..], *big_endian) as usize;
+
+        if len > MAX_BLOCK_LEN {
+            return Err(invalid_data("pcap record too large"));
+        }
+
+        let nanos = match nanos {
+            true => Some(frac),
+            false => frac.checked_mul(1000),
+        };
+        let Some(nanos) = nanos.filter(|&n| n < 1_000_000_000) else {
+            return Err(invalid_data("invalid pcap record timestamp"));
+        };
+        let timestamp = Duration::new(secs, nanos);
+        let linktype = *linktype;
+        let interface = interface.clone();
+
+        let mut data = vec![0u8; len];
+        self.inner.read_exact(&mut data).await?;
+
+        if linktype != LINKTYPE_CAN_SOCKETCAN {
+            return Ok(Packet::Skip);
+        }
+
+        Ok(decode_packet(&data)
+            .map(|frame| {
+                let frame = frame.with_timestamp(timestamp).with_interface(interface);
+                Packet::Frame(frame)
+            })
+            .unwrap_or(Packet::Skip))
+    }
+
+    /// Read the rest of a section header block, after the block type. Returns
+    /// true if the section is big endian.
+    async fn read_section_header(&mut self) -> io::Result<bool> {
+        let mut header = [0u8; 8];
+        self.inner.read_exact(&mut header).await?;
+
+        let big_endian = match u32::from_be_bytes([header[4], header[5], header[6], header[7]]) {
+            PCAPNG_BYTE_ORDER_MAGIC => true,
+            x if x.swap_bytes() == PCAPNG_BYTE_ORDER_MAGIC => false,
+            _ => return Err(invalid_data("invalid pcapng byte order magic")),
+        };
+
+        let len = read_u32(&header[0..], big_endian) as usize;
+
+        if !(28..=MAX_BLOCK_LEN).contains(&len) {
+            return Err(invalid_data("invalid pcapng section header length"));
+        }
+
+        // Skip version, section length, options and trailing length
+        let mut rest = vec![0u8; len - 12];
+        self.inner.read_exact(&mut rest).await?;
+
+        Ok(big_endian)
+    }
+
+    async fn read_pcapng_block(&mut self) -> io::Result<Packet> {
+        let mut block_type = [0u8; 4];
+        if !read_exact_or_eof(&mut self.inner, &mut block_type).await? {
+            return Ok(Packet::End);
+        }
+
+        // A new section, with its own byte order and interfaces
+        if u32::from_le_bytes(block_type) == PCAPNG_SHB {
+            let big_endian = self.read_section_header().await?;
+            self.format = Some(Format::Pcapng {
+                big_endian,
+                interfaces: Vec::new(),
+            });
+            return Ok(Packet::Skip);
+        }
+
+        let Some(Format::Pcapng {
+            big_endian,
+            interfaces,
+        }) = &mut self.format
+        else {
+            unreachable!()
+        };
+        let big_endian = *big_endian;
+
+        let mut len = [0u8; 4];
+        self.inner.read_exact(&mut len).await?;
+        let len = read_u32(&len, big_endian) as usize;
+
+        if !(12..=MAX_BLOCK_LEN).contains(&len) || len != align4(len) {
+            return Err(invalid_data("invalid pcapng block length"));
+        }
+
+        // Body and trailing length
+        let mut body = vec![0u8; len - 8];
+        self.inner.read_exact(&mut body).await?;
+        let body = &body[..(len - 12)];
+
+        match read_u32(&block_type, big_endian) {
+            PCAPNG_IDB => {
+                let interface = parse_interface(body, big_endian, interfaces.len())?;
+                interfaces.push(interface);
+                Ok(Packet::Skip)
+            }
+            PCAPNG_EPB => Ok(parse_enhanced_packet(body, big_endian, interfaces)?
+                .map(Packet::Frame)
+                .unwrap_or(Packet::Skip)),
+            PCAPNG_SPB => {
+                // Simple packets have no timestamp and belong to interface 0
+                let Some(interface) = interfaces.first() else {
+                    return Ok(Packet::Skip);
+                };
+                if interface.linktype as u32 != LINKTYPE_CAN_SOCKETCAN || body.len() < 4 {
+                    return Ok(Packet::Skip);
+                }
+                Ok(decode_packet(&body[4..])
+                    .map(|frame| Packet::Frame(frame.with_interface(interface.name.clone())))
+                    .unwrap_or(Packet::Skip))
+            }
+            _ => Ok(Packet::Skip),
+        }
+    }
+}
+
+enum Packet {
+    Frame(Frame),
+    Skip,
+    End,
+}
+
+fn parse_interface(body: &[u8], big_endian: bool, index: usize) -> io::Result<Interface> {
+    if body.len() < 8 {
+        return Err(invalid_data("invalid pcapng interface block"));
+    }
+
+    let linktype = read_u16(&body[0..], big_endian);
+
+    let mut interface = Interface {
+        linktype,
+        name: format!("pcap{}", index).into(),
+        resolution: 1_000_000,
+        offset: 0,
+    };
+
+    for (code, value) in options(&body[8..], big_endian) {
+        match code {
+            OPT_IF_NAME => {
+                let name = String::from_utf8_lossy(value);
+                let name = name.trim_end_matches('\0');
+                if !name.is_empty() {
+                    interface.name = name.into();
+                }
+            }
+            OPT_IF_TSRESOL if !value.is_empty() => {
+                let exp = (value[0] & 0x7F) as u32;
+                let base: u64 = if value[0] & 0x80 != 0 { 2 } else { 10 };
+                if let Some(resolution) = base.checked_pow(exp) {
+                    interface.resolution = resolution;
+                }
+            }
+            OPT_IF_TSOFFSET if value.len() >= 8 => {
+                let bytes: [u8; 8] = value[..8].try_into().unwrap_or_default();
+                interface.offset = if big_endian {
+                    i64::from_be_bytes(bytes)
+                } else {
+                    i64::from_le_bytes(bytes)
+                };
+            }
+            _ => {}
+        }
+    }
+
+    if linktype as u32 != LINKTYPE_CAN_SOCKETCAN {
+        log::warn!(
+            "pcapng interface {} link type is {}, not SocketCAN",
+            interface.name,
+            linktype
+        );
+    }
+
+    Ok(interface)
+}
+
+/// Returns `None` for packets which are not CAN frames
+fn parse_enhanced_packet(
+    body: &[u8],
+    big_endian: bool,
+    interfaces: &[Interface],
+) -> io::Result<Option<Frame>> {
+    if body.len() < 20 {
+        return Ok(None);
+    }
+
+    let Some(interface) = interfaces.get(read_u32(&body[0..], big_endian) as usize) else {
+        return Ok(None);
+    };
+
+    if interface.linktype as u32 != LINKTYPE_CAN_SOCKETCAN {
+        return Ok(None);
+    }
+
+    let ts_high = read_u32(&body[4..], big_endian) as u64;
+    let ts_low = read_u32(&body[8..], big_endian) as u64;
+    let caplen = read_u32(&body[12..], big_endian) as usize;
+
+    let Some(data) = body.get(20..(20 + caplen)) else {
+        return Ok(None);
+    };
+    let options_start = 20 + align4(caplen);
+
+    let ticks = (ts_high << 32) | ts_low;
+    let Some(secs) = i64::try_from(ticks / interface.resolution)
+        .ok()
+        .and_then(|secs| secs.checked_add(interface.offset))
+    else {
+        return Err(invalid_data("invalid pcapng packet timestamp"));
+    };
+    let nanos =
+        (ticks % interface.resolution) as u128 * 1_000_000_000 / interface.resolution as u128;
+    let timestamp = Duration::new(secs.max(0) as u64, nanos as u32);
+
+    let mut direction = Direction::Rx;
+
+    if let Some(options_data) = body.get(options_start..) {
+        for (code, value) in options(options_data, big_endian) {
+            if code == OPT_EPB_FLAGS && value.len() >= 4 && read_u32(value, big_endian) & 0x03 == 2
+            {
+                direction = Direction::Tx;
+            }
+        }
+    }
+
+    let frame = decode_packet(data).map(|frame| {
+        frame
+            .with_timestamp(timestamp)
+            .with_interface(interface.name.clone())
+            .with_direction(direction)
+    });
+
+    Ok(frame)
+}
+
+/// Iterate over the options (code, value) of a pcapng block
+fn options(mut data: &[u8], big_endian: bool) -> impl Iterator<Item = (u16, &[u8])> {
+    std::iter::from_fn(move || {
+        if data.len() < 4 {
+            return None;
+        }
+
+        let code = read_u16(&data[0..], big_endian);
+        let len = read_u16(&data[2..], big_endian) as usize;
+
+        if code == OPT_END {
+            return None;
+        }
+
+        let value = data.get(4..(4 + len))?;
+        data = data.get((4 + align4(len))..).unwrap_or_default();
+
+        Some((code, value))
+    })
+}
+
+/// Decode a LINKTYPE_CAN_SOCKETCAN packet
+fn decode_packet(data: &[u8]) -> Option<Frame> {
+    if data.len() < 8 {
+        return None;
+    }
+
+    let id = CanId::from_raw(u32::from_be_bytes([data[0], data[1], data[2], data[3]]));
+    let len = data[4] as usize;
+    let flags = data[5];
+    let is_fd = flags & CANFD_FDF != 0 || data.len() > CAN_MTU;
+
+    let payload = &data[8..];
+    let payload = payload[..len.min(payload.len())].to_vec();
+
+    let frame = if is_fd {
+        let flags = FdFlags {
+            brs: flags & CANFD_BRS != 0,
+            esi: flags & CANFD_ESI != 0,
+        };
+        Frame::new_fd(id, payload, flags, Duration::ZERO)
+    } else {
+        Frame::new(id, payload, Duration::ZERO)
+    };
+
+    Some(frame)
+}
+
+/// Encode a frame as a LINKTYPE_CAN_SOCKETCAN packet, with the same size of
+/// the kernel structures
+fn encode_packet(frame: &Frame) -> Vec<u8> {
+    let (mtu, flags) = match frame.fd_flags() {
+        Some(fd) => {
+            let flags =
+                CANFD_FDF | if fd.brs { CANFD_BRS } else { 0 } | if fd.esi { CANFD_ESI } else { 0 };
+            (CANFD_MTU, flags)
+        }
+        None => (CAN_MTU, 0),
+    };
+
+    let data = frame.data();
+    let len = data.len().min(mtu - 8);
+
+    let mut packet = vec![0u8; mtu];
+    packet[0..4].copy_from_slice(&frame.id().raw().to_be_bytes());
+    packet[4] = len as u8;
+    packet[5] = flags;
+    packet[8..(8 + len)].copy_from_slice(&data[..len]);
+    packet
+}
+
+/// Writes frames to a pcap file, with nanosecond timestamps
+pub(crate) struct PcapWriter<W: Write> {
+    inner: W,
+    header_written: bool,
+}
+
+impl<W: Write> PcapWriter<W> {
+    pub(crate) fn new(inner: W) -> Self {
+        PcapWriter {
+            inner,
+            header_written: false,
+        }
+    }
+
+    pub(crate) fn write(&mut self, frame: &Frame) -> io::Result<()> {
+        if !self.header_written {
+            self.inner.write_all(&PCAP_MAGIC_NANOS.to_le_bytes())?;
+            self.inner.write_all(&2u16.to_le_bytes())?; // version major
+            self.inner.write_all(&4u16.to_le_bytes())?; // version minor
+            self.inner.write_all(&0i32.to_le_bytes())?; // time zone
+            self.inner.write_all(&0u32.to_le_bytes())?; // accuracy
+            self.inner.write_all(&(CANFD_MTU as u32).to_le_bytes())?; // snap length
+            self.inner
+                .write_all(&LINKTYPE_CAN_SOCKETCAN.to_le_bytes())?;
+            self.header_written = true;
+        }
+
+        let packet = encode_packet(frame);
+        let ts = frame.timestamp();
+
+        self.inner.write_all(&(ts.as_secs() as u32).to_le_bytes())?;
+        self.inner.write_all(&ts.subsec_nanos().to_le_bytes())?;
+        self.inner.write_all(&(packet.len() as u32).to_le_bytes())?;
+        self.inner.write_all(&(packet.len() as u32).to_le_bytes())?;
+        self.inner.write_all(&packet)
+    }
+
+    pub(crate) fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Writes frames to a pcapng file, with an interface for each interface name
+/// and nanosecond timestamps
+pub(crate) struct PcapngWriter<W: Write> {
+    inner: W,
+    header_written: bool,
+    interfaces: HashMap<String, u32>,
+}
+
+impl<W: Write> PcapngWriter<W> {
+    pub(crate) fn new(inner: W) -> Self {
+        PcapngWriter {
+            inner,
+            header_written: false,
+            interfaces: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn write(&mut self, frame: &Frame) -> io::Result<()> {
+        if !self.header_written {
+            let mut body = Vec::new();
+            body.extend_from_slice(&PCAPNG_BYTE_ORDER_MAGIC.to_le_bytes());
+            body.extend_from_slice(&1u16.to_le_bytes()); // version major
+            body.extend_from_slice(&0u16.to_le_bytes()); // version minor
+            body.extend_from_slice(&(-1i64).to_le_bytes()); // unknown section length
+            self.write_block(PCAPNG_SHB, &body)?;
+            self.header_written = true;
+        }
+
+        let interface_id = self.interface_id(frame.interface().unwrap_or_default())?;
+
+        let packet = encode_packet(frame);
+        let ts = frame.timestamp().as_nanos() as u64;
+
+        let flags: u32 = match frame.direction() {
+            Direction::Rx => 1,
+            Direction::Tx => 2,
+        };
+
+        let mut body = Vec::new();
+        body.extend_from_slice(&interface_id.to_le_bytes());
+        body.extend_from_slice(&((ts >> 32) as u32).to_le_bytes());
+        body.extend_from_slice(&(ts as u32).to_le_bytes());
+        body.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+        body.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+        body.extend_from_slice(&packet);
+        push_option(&mut body, OPT_EPB_FLAGS, &flags.to_le_bytes());
+        push_option(&mut body, OPT_END, &[]);
+
+        self.write_block(PCAPNG_EPB, &body)
+    }
+
+    pub(crate) fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+
+    /// Returns the id of the interface, writing its description block the
+    /// first time it is seen
+    fn interface_id(&mut self, name: &str) -> io::Result<u32> {
+        if let Some(&id) = self.interfaces.get(name) {
+            return Ok(id);
+        }
+
+        let id = self.interfaces.len() as u32;
+
+        let mut body = Vec::new();
+        body.extend_from_slice(&(LINKTYPE_CAN_SOCKETCAN as u16).to_le_bytes());
+        body.extend_from_slice(&0u16.to_le_bytes()); // reserved
+        body.extend_from_slice(&0u32.to_le_bytes()); // no snap length limit
+        if !name.is_empty() {
+            push_option(&mut body, OPT_IF_NAME, name.as_bytes());
+        }
+        push_option(&mut body, OPT_IF_TSRESOL, &[9]);
+        push_option(&mut body, OPT_END, &[]);
+        self.write_block(PCAPNG_IDB, &body)?;
+
+        self.interfaces.insert(name.to_string(), id);
+        Ok(id)
+    }
+
+    fn write_block(&mut self, block_type: u32, body: &[u8]) -> io::Result<()> {
+        let len = (12 + body.len()) as u32;
+        self.inner.write_all(&block_type.to_le_bytes())?;
+        self.inner.write_all(&len.to_le_bytes())?;
+        self.inner.write_all(body)?;
+        self.inner.write_all(&len.to_le_bytes())
+    }
+}
+
+/// Append an option to a block body, padded to 4 bytes
+fn push_option(body: &mut Vec<u8>, code: u16, value: &[u8]) {
+    body.extend_from_slice(&code.to_le_bytes());
+    body.extend_from_slice(&(value.len() as u16).to_le_bytes());
+    body.extend_from_slice(value);
+    body.resize(align4(body.len()), 0);
+}
+
+/// Fill `buf`, returning false if the end of the file is reached before the
+/// first byte
+async fn read_exact_or_eof<R: AsyncRead + Unpin>(
+    inner: &mut R,
+    buf: &mut [u8],
+) -> io::Result<bool> {
+    let mut filled = 0;
+
+    while filled < buf.len() {
+        let n = inner.read(&mut buf[filled..]).await?;
+        if n == 0 {
+            return match filled {
+                0 => Ok(false),
+                _ => Err(io::ErrorKind::UnexpectedEof.into()),
+            };
+        }
+        filled += n;
+    }
+
+    Ok(true)
+}
+
+fn read_u16(buf: &[u8], big_endian: bool) -> u16 {
+    let bytes = [buf[0], buf[1]];
+    if big_endian {
+        u16::from_be_bytes(bytes)
+    } else {
+        u16::from_le_bytes(bytes)
+    }
+}
+
+fn read_u32(buf: &[u8], big_endian: bool) -> u32 {
+    let bytes = [buf[0], buf[1], buf[2], buf[3]];
+    if big_endian {
+        u32::from_be_bytes(bytes)
+    } else {
+        u32::from_le_bytes(bytes)
+    }
+}
+
+fn align4(len: usize) -> usize {
+    (len + 3) & !3
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A pcap file with a single record header, with no data
+    fn pcap_record(magic: u32, secs: u32, frac: u32) -> Vec<u8> {
+        let mut file = Vec::new();
+        file.extend(magic.to_le_bytes());
+        file.extend([2, 0, 4, 0]);
+        file.extend([0; 12]);
+        file.extend(LINKTYPE_CAN_SOCKETCAN.to_le_bytes());
+        file.extend(secs.to_le_bytes());
+        file.extend(frac.to_le_bytes());
+        file.extend([0; 8]);
+        file
+    }
+
+    async fn read(file: &[u8]) -> io::Result<Option<Frame>> {
+        Reader::new(file).read().await
+    }
+
+    async fn read_all(file: &[u8]) -> Vec<Frame> {
+        let mut reader = Reader::new(file);
+        let mut frames = Vec::new();
+        while let Some(frame) = reader.read().await.unwrap() {
+            frames.push(frame);
+        }
+        frames
+    }
+
+    fn frames() -> Vec<Frame> {
+        let ns = |ns: u64| Duration::from_secs(1_700_000_000) + Duration::from_nanos(ns);
+        let can0: Arc<str> = "can0".into();
+        let can1: Arc<str> = "can1".into();
+        vec![
+            Frame::new(CanId::standard(0x123).unwrap(), vec![1, 2, 3], ns(1))
+                .with_interface(can0.clone()),
+            Frame::new(
+                CanId::extended(0x18FE_F100).unwrap(),
+                vec![0xAA; 8],
+                ns(1_500),
+            )
+            .with_interface(can1.clone())
+            .with_direction(Direction::Tx),
+            Frame::new(
+                CanId::standard(0x7DF).unwrap().with_rtr(true),
+                vec![],
+                ns(2_000_000),
+            )
+            .with_interface(can0.clone()),
+            Frame::new_fd(
+                CanId::standard(0x456).unwrap(),
+                (0..64).collect(),
+                FdFlags {
+                    brs: true,
+                    esi: true,
+                },
+                ns(999_999_999),
+            )
+            .with_interface(can1),
+            Frame::new(
+                CanId::from_raw(CanId::ERR_FLAG | 0x204),
+                vec![0, 0x08, 0, 0, 0, 0, 96, 0],
+                ns(1_000_000_000),
+            )
+            .with_interface(can0),
+        ]
+    }
+
+    fn assert_same_frames(written: &[Frame], read: &[Frame]) {
+        assert_eq!(written.len(), read.len());
+        for (a, b) in written.iter().zip(read) {
+            assert_eq!(a.id(), b.id());
+            assert_eq!(a.data(), b.data());
+            assert_eq!(a.timestamp(), b.timestamp());
+            assert_eq!(a.fd_flags(), b.fd_flags());
+        }
+    }
+
+    #[tokio::test]
+    async fn pcap_round_trip() {
+        let frames = frames();
+        let mut file = Vec::new();
+        let mut writer = PcapWriter::new(&mut file);
+        for frame in &frames {
+            writer.write(frame).unwrap();
+        }
+        writer.flush().unwrap();
+
+        let read = read_all(&file).await;
+        assert_same_frames(&frames, &read);
+        // A pcap file has a single interface and no direction
+        assert!(read.iter().all(|f| f.interface() == Some("pcap0")));
+        assert!(read.iter().all(|f| f.direction() == Direction::Rx));
+    }
+
+    #[tokio::test]
+    async fn pcapng_round_trip() {
+        let frames = frames();
+        let mut file = Vec::new();
+        let mut writer = PcapngWriter::new(&mut file);
+        for frame in &frames {
+            writer.write(frame).unwrap();
+        }
+        writer.flush().unwrap();
+
+        let read = read_all(&file).await;
+        assert_same_frames(&frames, &read);
+        for (a, b) in frames.iter().zip(&read) {
+            assert_eq!(a.interface(), b.interface());
+            assert_eq!(a.direction(), b.direction());
+        }
+    }
+
+    fn block(block_type: u32, body: &[u8]) -> Vec<u8> {
+        let mut block = Vec::new();
+        let mut writer = PcapngWriter::new(&mut block);
+        writer.write_block(block_type, body).unwrap();
+        block
+    }
+
+    fn interface_block(name: &str, tsresol: Option<u8>) -> Vec<u8> {
+        let mut body = Vec::new();
+        body.extend((LINKTYPE_CAN_SOCKETCAN as u16).to_le_bytes());
+        body.extend([0; 6]);
+        push_option(&mut body, OPT_IF_NAME, name.as_bytes());
+        if let Some(tsresol) = tsresol {
+            push_option(&mut body, OPT_IF_TSRESOL, &[tsresol]);
+        }
+        push_option(&mut body, OPT_END, &[]);
+        block(PCAPNG_IDB, &body)
+    }
+
+    fn packet_block(interface: u32, ticks: u64, flags: Option<u32>, id: u16) -> Vec<u8> {
+        let frame = Frame::new(CanId::standard(id).unwrap(), vec![id as u8], Duration::ZERO);
+        let packet = encode_packet(&frame);
+        let mut body = Vec::new();
+        body.extend(interface.to_le_bytes());
+        body.extend(((ticks >> 32) as u32).to_le_bytes());
+        body.extend((ticks as u32).to_le_bytes());
+        body.extend((packet.len() as u32).to_le_bytes());
+        body.extend((packet.len() as u32).to_le_bytes());
+        body.extend(packet);
+        if let Some(flags) = flags {
+            push_option(&mut body, OPT_EPB_FLAGS, &flags.to_le_bytes());
+            push_option(&mut body, OPT_END, &[]);
+        }
+        block(PCAPNG_EPB, &body)
+    }
+
+    #[tokio::test]
+    async fn pcapng_interfaces() {
+        let mut section = Vec::new();
+        section.extend(PCAPNG_BYTE_ORDER_MAGIC.to_le_bytes());
+        section.extend([1, 0, 0, 0]);
+        section.extend((-1i64).to_le_bytes());
+
+        let mut file = block(PCAPNG_SHB, &section);
+        // Microseconds by default, milliseconds, binary fractions
+        file.extend(interface_block("can0", None));
+        file.extend(interface_block("can1", Some(3)));
+        file.extend(interface_block("vcan2", Some(0x80 | 10)));
+        file.extend(packet_block(1, 1_500, Some(2), 0x101));
+        file.extend(packet_block(0, 2_000_001, Some(1), 0x100));
+        file.extend(packet_block(2, 3 * 1024 + 512, None, 0x102));
+        // Unknown interface
+        file.extend(packet_block(3, 0, None, 0x103));
+
+        let read: Vec<_> = read_all(&file)
+            .await
+            .iter()
+            .map(|f| {
+                (
+                    f.id().id(),
+                    f.interface().unwrap().to_string(),
+                    f.timestamp(),
+                    f.direction(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            read,
+            [
+                (
+                    0x101,
+                    "can1".into(),
+                    Duration::from_millis(1_500),
+                    Direction::Tx
+                ),
+                (
+                    0x100,
+                    "can0".into(),
+                    Duration::from_micros(2_000_001),
+                    Direction::Rx
+                ),
+                (
+                    0x102,
+                    "vcan2".into(),
+                    Duration::from_millis(3_500),
+                    Direction::Rx
+                ),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_pcap_timestamp_fraction_out_of_range() {
+        for (magic, frac) in [
+            (PCAP_MAGIC_MICROS, u32::MAX),
+            (PCAP_MAGIC_MICROS, 1_000_000),
+        ] {
+            let err = read(&pcap_record(magic, 1, frac)).await.unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        }
+        let err = read(&pcap_record(PCAP_MAGIC_NANOS, 1, 1_000_000_000))
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn rejects_pcapng_timestamp_overflow() {
+        let interface = |resolution, offset| Interface {
+            linktype: LINKTYPE_CAN_SOCKETCAN as u16,
+            name: "can0".into(),
+            resolution,
+            offset,
+        };
+        let mut body = vec![0u8; 20];
+        body[4..8].copy_from_slice(&u32::MAX.to_le_bytes());
+
+        for interfaces in [interface(1, 0), interface(1_000_000, i64::MAX)] {
+            let err = parse_enhanced_packet(&body, false, &[interfaces]).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+}
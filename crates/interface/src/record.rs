@@ -0,0 +1,103 @@
+use crate::{asc, candump, pcap};
+use anyhow::{bail, Result};
+use canbusnoop_core::Frame;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Maximum time frames are kept in the buffer before being written to the file
+const FLUSH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Records frames to a file. The format is chosen from the file extension.
+pub struct Recorder {
+    inner: InnerRecorder,
+    last_flush: Instant,
+}
+
+impl Recorder {
+    /// Create the file `path` and start recording. Supported extensions:
+    ///
+    /// - `.log` candump log format
+    /// - `.asc` Vector ASC format
+    /// - `.pcap` and `.pcapng` with the SocketCAN link type
+    pub fn create(path: &Path) -> Result<Recorder> {
+        let extension = path.extension().and_then(|x| x.to_str()).unwrap_or("");
+
+        let inner = match extension {
+            "log" => {
+                let file = BufWriter::new(File::create(path)?);
+                InnerRecorder::Candump(candump::Writer::new(file))
+            }
+            "asc" => {
+                let file = BufWriter::new(File::create(path)?);
+                InnerRecorder::Asc(asc::Writer::new(file))
+            }
+            "pcap" => {
+                let file = BufWriter::new(File::create(path)?);
+                InnerRecorder::Pcap(pcap::PcapWriter::new(file))
+            }
+            "pcapng" => {
+                let file = BufWriter::new(File::create(path)?);
+                InnerRecorder::Pcapng(pcap::PcapngWriter::new(file))
+            }
+            _ => bail!("unsupported recording format: {}", path.display()),
+        };
+
+        let last_flush = Instant::now();
+
+        Ok(Recorder { inner, last_flush })
+    }
+
+    pub fn write(&mut self, frame: &Frame) -> Result<()> {
+        match &mut self.inner {
+            InnerRecorder::Candump(inner) => inner.write(frame)?,
+            InnerRecorder::Asc(inner) => inner.write(frame)?,
+            InnerRecorder::Pcap(inner) => inner.write(frame)?,
+            InnerRecorder::Pcapng(inner) => inner.write(frame)?,
+        }
+
+        // Frames are buffered, but not for too long: the recording must be
+        // usable even if the application is not closed gracefully
+        if self.last_flush.elapsed() > FLUSH_INTERVAL {
+            self.flush()?;
+        }
+
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        self.last_flush = Instant::now();
+
+        match &mut self.inner {
+            InnerRecorder::Candump(inner) => inner.flush()?,
+            InnerRecorder::Asc(inner) => inner.flush()?,
+            InnerRecorder::Pcap(inner) => inner.flush()?,
+            InnerRecorder::Pcapng(inner) => inner.flush()?,
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        let result = match &mut self.inner {
+            InnerRecorder::Candump(inner) => inner.flush(),
+            InnerRecorder::Asc(inner) => inner.finish(),
+            InnerRecorder::Pcap(inner) => inner.flush(),
+            InnerRecorder::Pcapng(inner) => inner.flush(),
+        };
+
+        if let Err(e) = result {
+            log::error!("Cannot finish recording: {}", e);
+        }
+    }
+}
+
+enum InnerRecorder {
+    Candump(candump::Writer<BufWriter<File>>),
+    Asc(asc::Writer<BufWriter<File>>),
+    Pcap(pcap::PcapWriter<BufWriter<File>>),
+    Pcapng(pcap::PcapngWriter<BufWriter<File>>),
+}
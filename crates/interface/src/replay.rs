@@ -0,0 +1,262 @@
+use crate::{asc, candump, pcap, Error};
+use anyhow::Result;
+use canbusnoop_core::Frame;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::fs::File;
+use tokio::io::BufReader;
+use tokio::time::Instant;
+
+/// Replay speed
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Speed {
+    /// Same timing as the original capture
+    #[default]
+    RealTime,
+    /// N times faster than the original capture
+    Accelerated(f64),
+    /// As fast as possible, ignoring the original timing
+    Max,
+}
+
+/// Parse `max`, or a speed factor like `1` (real-time) or `10`
+impl FromStr for Speed {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "max" {
+            return Ok(Speed::Max);
+        }
+
+        let factor: f64 = s.parse().map_err(|_| format!("invalid speed: {}", s))?;
+
+        if factor == 1. {
+            Ok(Speed::RealTime)
+        } else if factor > 0. && factor.is_finite() {
+            Ok(Speed::Accelerated(factor))
+        } else {
+            Err(format!("invalid speed: {}", s))
+        }
+    }
+}
+
+/// Replay options
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Playback {
+    pub speed: Speed,
+    /// Restart from the beginning at the end of the file
+    pub looping: bool,
+}
+
+#[derive(Debug)]
+pub struct Config {
+    pub(super) path: PathBuf,
+    pub(super) format: Format,
+    pub(super) playback: Playback,
+}
+
+impl Config {
+    /// Create the configuration to replay `path`, detecting the format from
+    /// the extension
+    pub(super) fn new(path: PathBuf) -> Result<Config, Error> {
+        let extension = path.extension().and_then(|x| x.to_str()).unwrap_or("");
+
+        let format = match extension {
+            "log" => Format::Candump,
+            "asc" => Format::Asc,
+            "pcap" | "pcapng" => Format::Pcap,
+            _ => return Err(Error::UnsupportedFile(path)),
+        };
+
+        Ok(Config {
+            path,
+            format,
+            playback: Playback::default(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(super) enum Format {
+    Candump,
+    Asc,
+    Pcap,
+}
+
+pub(super) struct Reader {
+    config: Config,
+    source: Source,
+    /// Wall clock time and capture time of the first frame
+    origin: Option<(Instant, Duration)>,
+    /// Frames read in the current pass
+    pass: Option<Pass>,
+    /// Added to capture times when looping, so time keeps going forward
+    offset: Duration,
+}
+
+impl Reader {
+    pub(super) fn new(config: Config) -> Result<Reader> {
+        let source = Source::open(&config)?;
+
+        Ok(Reader {
+            config,
+            source,
+            origin: None,
+            pass: None,
+            offset: Duration::ZERO,
+        })
+    }
+
+    pub(super) async fn read(&mut self) -> Result<Option<Frame>> {
+        let frame = loop {
+            match self.source.read().await? {
+                Some(frame) => break frame,
+                None => {
+                    // An empty pass would loop forever
+                    let Some(pass) = self.pass.take() else {
+                        return Ok(None);
+                    };
+                    if !self.config.playback.looping {
+                        return Ok(None);
+                    }
+                    // The first frame of the next pass follows the last one
+                    // after the mean period, not at the same time
+                    self.offset += pass.last - pass.first + pass.mean_period();
+                    self.source = Source::open(&self.config)?;
+                }
+            }
+        };
+
+        let timestamp = frame.timestamp();
+        let pass = self.pass.get_or_insert(Pass {
+            first: timestamp,
+            last: timestamp,
+            frames: 0,
+        });
+        pass.last = pass.last.max(timestamp);
+        pass.frames += 1;
+
+        let timestamp = timestamp + self.offset;
+        self.wait(timestamp).await;
+
+        Ok(Some(frame.with_timestamp(timestamp)))
+    }
+
+    /// Wait until it is time to emit the frame captured at `timestamp`
+    async fn wait(&mut self, timestamp: Duration) {
+        let factor = match self.config.playback.speed {
+            Speed::RealTime => 1.,
+            Speed::Accelerated(factor) => factor,
+            Speed::Max => return,
+        };
+
+        let (start, first) = *self.origin.get_or_insert((Instant::now(), timestamp));
+        let elapsed = timestamp.saturating_sub(first).div_f64(factor);
+        tokio::time::sleep_until(start + elapsed).await;
+    }
+}
+
+/// Gap between passes of a file with a single frame
+const SINGLE_FRAME_PERIOD: Duration = Duration::from_secs(1);
+
+/// Capture time of the first and last frame of a pass over the file
+struct Pass {
+    first: Duration,
+    last: Duration,
+    frames: u32,
+}
+
+impl Pass {
+    fn mean_period(&self) -> Duration {
+        match self.frames {
+            0 | 1 => SINGLE_FRAME_PERIOD,
+            n => (self.last - self.first) / (n - 1),
+        }
+    }
+}
+
+enum Source {
+    Candump(candump::Reader<BufReader<File>>),
+    Asc(asc::Reader<BufReader<File>>),
+    Pcap(pcap::Reader<BufReader<File>>),
+}
+
+impl Source {
+    fn open(config: &Config) -> Result<Source> {
+        let file = File::from_std(std::fs::File::open(&config.path)?);
+
+        let file = BufReader::new(file);
+
+        match config.format {
+            Format::Candump => Ok(Source::Candump(candump::Reader::new(file))),
+            Format::Asc => Ok(Source::Asc(asc::Reader::new(file))),
+            Format::Pcap => Ok(Source::Pcap(pcap::Reader::new(file))),
+        }
+    }
+
+    async fn read(&mut self) -> std::io::Result<Option<Frame>> {
+        match self {
+            Source::Candump(inner) => inner.read().await,
+            Source::Asc(inner) => inner.read().await,
+            Source::Pcap(inner) => inner.read().await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use canbusnoop_core::CanId;
+
+    fn fixture(name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("testdata")
+            .join(name)
+    }
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    async fn replay(looping: bool, frames: usize) -> Vec<Frame> {
+        let mut config = Config::new(fixture("replay.log")).unwrap();
+        config.playback = Playback {
+            speed: Speed::Max,
+            looping,
+        };
+        let mut reader = Reader::new(config).unwrap();
+        let mut result = Vec::new();
+        while result.len() < frames {
+            match reader.read().await.unwrap() {
+                Some(frame) => result.push(frame),
+                None => break,
+            }
+        }
+        result
+    }
+
+    #[tokio::test]
+    async fn replays_candump_log() {
+        let frames = replay(false, 10).await;
+
+        let times: Vec<_> = frames.iter().map(|f| f.timestamp()).collect();
+        let base = Duration::from_secs(1_700_000_000);
+        assert_eq!(times, [base, base + ms(10), base + ms(20), base + ms(30)]);
+        assert_eq!(frames[0].id(), CanId::standard(0x123).unwrap());
+        assert_eq!(frames[0].data(), [0x01, 0x02]);
+        assert_eq!(frames[0].interface(), Some("vcan0"));
+        assert!(frames[3].id().is_extended());
+    }
+
+    #[tokio::test]
+    async fn loops_after_the_mean_period() {
+        let frames = replay(true, 9).await;
+
+        let times: Vec<_> = frames.iter().map(|f| f.timestamp()).collect();
+        let periods: Vec<_> = times.windows(2).map(|w| w[1] - w[0]).collect();
+        assert_eq!(periods, [ms(10); 8]);
+        assert_eq!(frames[4].id(), frames[0].id());
+        assert_eq!(frames[8].data(), frames[0].data());
+    }
+}
@@ -1,30 +1,127 @@
+mod netlink;
+mod raw;
+
+use crate::Error;
 use anyhow::Result;
-use canbusnoop_core::Frame;
-use tokio_socketcan::{CANFrame, CANSocket};
-use tokio_stream::StreamExt;
+use canbusnoop_core::{CanId, Direction, FdFlags, Frame};
+use raw::{RawFrame, Socket};
+use std::sync::Arc;
 
 pub(super) struct Reader {
-    socket: CANSocket,
+    socket: Socket,
+    interface: Arc<str>,
 }
 
 impl Reader {
     pub(super) fn new(config: Config) -> Result<Reader> {
-        let socket = CANSocket::open(config.interface.as_str())?;
-        Ok(Reader { socket })
+        let socket = Socket::open(config.interface.as_str())?;
+        let interface = config.interface.into();
+        Ok(Reader { socket, interface })
+    }
+
+    pub(super) async fn read(&mut self) -> Result<Option<Frame>> {
+        loop {
+            match self.socket.recv().await {
+                Ok(frame) => {
+                    let frame = raw_frame_to_frame(frame).with_interface(self.interface.clone());
+                    return Ok(Some(frame));
+                }
+                // A malformed frame is not a reason to stop reading
+                Err(e) if e.kind() == std::io::ErrorKind::InvalidData => {
+                    log::warn!("Discarding frame: {}", e);
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    pub(super) fn writer(&self) -> Result<Writer> {
+        Writer::new(&self.interface)
+    }
+}
+
+/// Sends frames through its own socket, so frames sent by canbusnoop are
+/// looped back to the reader like any other local traffic
+pub(super) struct Writer {
+    socket: Socket,
+}
+
+impl Writer {
+    fn new(interface: &str) -> Result<Writer> {
+        let socket = Socket::open_tx(interface)?;
+        Ok(Writer { socket })
     }
 
-    pub(super) async fn read(&mut self) -> Option<Frame> {
-        let frame = self.socket.next().await;
-        let frame: Frame = socket_can_frame_to_frame(frame?.ok()?);
-        Some(frame)
+    pub(super) async fn send(&self, frame: &Frame) -> Result<()> {
+        self.socket.send(&frame_to_raw_frame(frame)).await?;
+        Ok(())
     }
 }
 
-fn socket_can_frame_to_frame(frame: CANFrame) -> Frame {
-    Frame::new(frame.id(), frame.data().to_vec())
+fn raw_frame_to_frame(frame: RawFrame) -> Frame {
+    // Kernel timestamps are always enabled, but fall back to the current time
+    // if the ancillary data is missing
+    let timestamp = frame.timestamp.unwrap_or_else(canbusnoop_core::now);
+    let id = CanId::from_raw(frame.id);
+    let direction = match frame.local {
+        true => Direction::Tx,
+        false => Direction::Rx,
+    };
+    let frame = match frame.fd_flags {
+        Some(flags) => {
+            let flags = FdFlags {
+                brs: flags & libc::CANFD_BRS as u8 != 0,
+                esi: flags & libc::CANFD_ESI as u8 != 0,
+            };
+            Frame::new_fd(id, frame.data, flags, timestamp)
+        }
+        None => Frame::new(id, frame.data, timestamp),
+    };
+    frame.with_direction(direction)
+}
+
+fn frame_to_raw_frame(frame: &Frame) -> RawFrame {
+    let fd_flags = frame.fd_flags().map(|flags| {
+        let mut raw = 0;
+        if flags.brs {
+            raw |= libc::CANFD_BRS as u8;
+        }
+        if flags.esi {
+            raw |= libc::CANFD_ESI as u8;
+        }
+        raw
+    });
+    RawFrame {
+        id: frame.id().raw(),
+        data: frame.data().to_vec(),
+        timestamp: None,
+        fd_flags,
+        local: true,
+    }
 }
 
 #[derive(Debug)]
 pub struct Config {
     pub(super) interface: String,
 }
+
+impl Config {
+    /// Create the configuration for the SocketCAN interface `interface`,
+    /// checking that it exists, it is a CAN interface and it is up.
+    pub(super) fn new(interface: String) -> Result<Config, Error> {
+        let link = match netlink::get_link(&interface)? {
+            Some(link) => link,
+            None => return Err(Error::InterfaceNotFound(interface)),
+        };
+
+        if !link.is_can() {
+            return Err(Error::NotCanInterface(interface));
+        }
+
+        if !link.is_up() {
+            return Err(Error::InterfaceDown(interface));
+        }
+
+        Ok(Config { interface })
+    }
+}
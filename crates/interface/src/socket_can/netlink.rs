@@ -0,0 +1,145 @@
+use std::io;
+use std::mem;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+
+const NLMSG_HDR_LEN: usize = 16;
+const IFINFOMSG_LEN: usize = 16;
+const RTA_HDR_LEN: usize = 4;
+const RECV_BUF_LEN: usize = 8192;
+
+/// Link information, as reported by the kernel
+#[derive(Debug)]
+pub(super) struct Link {
+    /// ARPHRD_* device type
+    pub(super) kind: u16,
+    /// IFF_* device flags
+    pub(super) flags: u32,
+}
+
+impl Link {
+    pub(super) fn is_can(&self) -> bool {
+        self.kind == libc::ARPHRD_CAN
+    }
+
+    pub(super) fn is_up(&self) -> bool {
+        self.flags & libc::IFF_UP as u32 != 0
+    }
+}
+
+/// Query the kernel for the link named `name` with a RTM_GETLINK request.
+/// Returns `None` if there is no such interface.
+pub(super) fn get_link(name: &str) -> io::Result<Option<Link>> {
+    let fd = unsafe {
+        libc::socket(
+            libc::AF_NETLINK,
+            libc::SOCK_RAW | libc::SOCK_CLOEXEC,
+            libc::NETLINK_ROUTE,
+        )
+    };
+
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+    let request = build_request(name);
+
+    let mut addr: libc::sockaddr_nl = unsafe { mem::zeroed() };
+    addr.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+
+    let ret = unsafe {
+        libc::sendto(
+            fd.as_raw_fd(),
+            request.as_ptr() as *const libc::c_void,
+            request.len(),
+            0,
+            &addr as *const libc::sockaddr_nl as *const libc::sockaddr,
+            mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+        )
+    };
+
+    if ret < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let mut buf = vec![0u8; RECV_BUF_LEN];
+    let n = unsafe {
+        libc::recv(
+            fd.as_raw_fd(),
+            buf.as_mut_ptr() as *mut libc::c_void,
+            buf.len(),
+            0,
+        )
+    };
+
+    if n < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    parse_response(&buf[..n as usize])
+}
+
+fn build_request(name: &str) -> Vec<u8> {
+    // Interface name, null terminated, padded to 4 bytes
+    let name_len = name.len() + 1;
+    let rta_len = RTA_HDR_LEN + name_len;
+    let rta_len_aligned = align4(rta_len);
+    let len = NLMSG_HDR_LEN + IFINFOMSG_LEN + rta_len_aligned;
+
+    let mut buf = Vec::with_capacity(len);
+
+    // struct nlmsghdr
+    buf.extend_from_slice(&(len as u32).to_ne_bytes());
+    buf.extend_from_slice(&libc::RTM_GETLINK.to_ne_bytes());
+    buf.extend_from_slice(&(libc::NLM_F_REQUEST as u16).to_ne_bytes());
+    buf.extend_from_slice(&1u32.to_ne_bytes()); // sequence number
+    buf.extend_from_slice(&0u32.to_ne_bytes()); // port id
+
+    // struct ifinfomsg, all zero but the family
+    buf.push(libc::AF_UNSPEC as u8);
+    buf.resize(NLMSG_HDR_LEN + IFINFOMSG_LEN, 0);
+
+    // struct rtattr with IFLA_IFNAME
+    buf.extend_from_slice(&(rta_len as u16).to_ne_bytes());
+    buf.extend_from_slice(&libc::IFLA_IFNAME.to_ne_bytes());
+    buf.extend_from_slice(name.as_bytes());
+    buf.resize(len, 0);
+
+    buf
+}
+
+fn parse_response(buf: &[u8]) -> io::Result<Option<Link>> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "invalid netlink response");
+
+    if buf.len() < NLMSG_HDR_LEN {
+        return Err(invalid());
+    }
+
+    let msg_type = u16::from_ne_bytes([buf[4], buf[5]]);
+    let payload = &buf[NLMSG_HDR_LEN..];
+
+    if msg_type == libc::NLMSG_ERROR as u16 {
+        if payload.len() < 4 {
+            return Err(invalid());
+        }
+        let errno = -i32::from_ne_bytes([payload[0], payload[1], payload[2], payload[3]]);
+        return match errno {
+            libc::ENODEV => Ok(None),
+            errno => Err(io::Error::from_raw_os_error(errno)),
+        };
+    }
+
+    if msg_type != libc::RTM_NEWLINK || payload.len() < IFINFOMSG_LEN {
+        return Err(invalid());
+    }
+
+    let kind = u16::from_ne_bytes([payload[2], payload[3]]);
+    let flags = u32::from_ne_bytes([payload[8], payload[9], payload[10], payload[11]]);
+
+    Ok(Some(Link { kind, flags }))
+}
+
+fn align4(len: usize) -> usize {
+    (len + 3) & !3
+}
@@ -0,0 +1,289 @@
+use canbusnoop_core::FD_LENGTHS;
+use std::io;
+use std::mem;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::time::Duration;
+use tokio::io::unix::AsyncFd;
+
+/// Size of a classic CAN frame (struct can_frame)
+const CAN_MTU: usize = 16;
+
+/// Size of a CAN FD frame (struct canfd_frame)
+const CANFD_MTU: usize = 72;
+
+/// Size of the buffer used to receive ancillary data (the timestamp)
+const CMSG_BUF_LEN: usize = 64;
+
+/// A raw frame, as received from the kernel
+pub(super) struct RawFrame {
+    pub(super) id: u32,
+    pub(super) data: Vec<u8>,
+    pub(super) timestamp: Option<Duration>,
+    /// CANFD_BRS/CANFD_ESI flags, `None` for classic CAN frames
+    pub(super) fd_flags: Option<u8>,
+    /// The frame was sent by a socket on this host
+    pub(super) local: bool,
+}
+
+/// A non blocking CAN_RAW socket registered in the tokio reactor
+pub(super) struct Socket {
+    fd: AsyncFd<OwnedFd>,
+}
+
+impl Socket {
+    /// Open a CAN_RAW socket bound to the interface `ifname`, with kernel
+    /// receive timestamps, CAN FD frames and error frames enabled
+    pub(super) fn open(ifname: &str) -> io::Result<Socket> {
+        Socket::open_with(ifname, |fd| {
+            set_socket_option(fd, libc::SOL_SOCKET, libc::SO_TIMESTAMPNS, 1)?;
+            set_socket_option(fd, libc::SOL_CAN_RAW, libc::CAN_RAW_FD_FRAMES, 1)?;
+            set_socket_option(
+                fd,
+                libc::SOL_CAN_RAW,
+                libc::CAN_RAW_ERR_FILTER,
+                libc::CAN_ERR_MASK as libc::c_int,
+            )
+        })
+    }
+
+    /// Open a CAN_RAW socket bound to the interface `ifname`, only used to
+    /// send frames: nothing is received, so the receive queue never fills up
+    pub(super) fn open_tx(ifname: &str) -> io::Result<Socket> {
+        Socket::open_with(ifname, |fd| {
+            set_socket_option(fd, libc::SOL_CAN_RAW, libc::CAN_RAW_FD_FRAMES, 1)?;
+            let ret = unsafe {
+                libc::setsockopt(
+                    fd,
+                    libc::SOL_CAN_RAW,
+                    libc::CAN_RAW_FILTER,
+                    std::ptr::null(),
+                    0,
+                )
+            };
+            if ret < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        })
+    }
+
+    /// Open a socket, apply the options with `configure` and bind it
+    fn open_with(
+        ifname: &str,
+        configure: impl FnOnce(RawFd) -> io::Result<()>,
+    ) -> io::Result<Socket> {
+        let ifindex = if_nametoindex(ifname)?;
+
+        let fd = unsafe {
+            libc::socket(
+                libc::PF_CAN,
+                libc::SOCK_RAW | libc::SOCK_NONBLOCK | libc::SOCK_CLOEXEC,
+                libc::CAN_RAW,
+            )
+        };
+
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+        configure(fd.as_raw_fd())?;
+
+        let mut addr: libc::sockaddr_can = unsafe { mem::zeroed() };
+        addr.can_family = libc::AF_CAN as libc::sa_family_t;
+        addr.can_ifindex = ifindex as libc::c_int;
+
+        let ret = unsafe {
+            libc::bind(
+                fd.as_raw_fd(),
+                &addr as *const libc::sockaddr_can as *const libc::sockaddr,
+                mem::size_of::<libc::sockaddr_can>() as libc::socklen_t,
+            )
+        };
+
+        if ret < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let fd = AsyncFd::new(fd)?;
+
+        Ok(Socket { fd })
+    }
+
+    /// Receive the next frame, waiting until one is available
+    pub(super) async fn recv(&self) -> io::Result<RawFrame> {
+        loop {
+            let mut guard = self.fd.readable().await?;
+
+            match guard.try_io(|fd| recv_frame(fd.as_raw_fd())) {
+                Ok(result) => return result,
+                Err(_would_block) => continue,
+            }
+        }
+    }
+
+    /// Send a frame, waiting until there is room in the transmit queue
+    pub(super) async fn send(&self, frame: &RawFrame) -> io::Result<()> {
+        let buf = encode_frame(frame)?;
+
+        loop {
+            let mut guard = self.fd.writable().await?;
+
+            match guard.try_io(|fd| send_frame(fd.as_raw_fd(), &buf)) {
+                Ok(result) => return result,
+                Err(_would_block) => continue,
+            }
+        }
+    }
+}
+
+fn if_nametoindex(ifname: &str) -> io::Result<libc::c_uint> {
+    let name = std::ffi::CString::new(ifname)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+
+    let ifindex = unsafe { libc::if_nametoindex(name.as_ptr()) };
+
+    if ifindex == 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(ifindex)
+}
+
+fn set_socket_option(
+    fd: RawFd,
+    level: libc::c_int,
+    name: libc::c_int,
+    value: libc::c_int,
+) -> io::Result<()> {
+    let ret = unsafe {
+        libc::setsockopt(
+            fd,
+            level,
+            name,
+            &value as *const libc::c_int as *const libc::c_void,
+            mem::size_of::<libc::c_int>() as libc::socklen_t,
+        )
+    };
+
+    if ret < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(())
+}
+
+/// Receive a single frame with `recvmsg`, so the kernel timestamp can be
+/// extracted from the ancillary data
+fn recv_frame(fd: RawFd) -> io::Result<RawFrame> {
+    let mut buf = [0u8; CANFD_MTU];
+    let mut cmsg_buf = [0u8; CMSG_BUF_LEN];
+
+    let mut iov = libc::iovec {
+        iov_base: buf.as_mut_ptr() as *mut libc::c_void,
+        iov_len: buf.len(),
+    };
+
+    let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = cmsg_buf.as_mut_ptr() as *mut libc::c_void;
+    msg.msg_controllen = cmsg_buf.len() as _;
+
+    let n = unsafe { libc::recvmsg(fd, &mut msg, 0) };
+
+    if n < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    // The kernel tells classic and FD frames apart by their size
+    let (max_len, fd_flags) = match n as usize {
+        CAN_MTU => (8, None),
+        CANFD_MTU => (64, Some(buf[5] & (libc::CANFD_BRS | libc::CANFD_ESI) as u8)),
+        n => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unexpected CAN frame size: {} bytes", n),
+            ));
+        }
+    };
+
+    let id = u32::from_ne_bytes([buf[0], buf[1], buf[2], buf[3]]);
+    let len = (buf[4] as usize).min(max_len);
+    let data = buf[8..(8 + len)].to_vec();
+    let timestamp = timestamp_from_cmsg(&msg);
+    // The kernel marks the frames looped back from local sockets
+    let local = msg.msg_flags & libc::MSG_DONTROUTE != 0;
+
+    Ok(RawFrame {
+        id,
+        data,
+        timestamp,
+        fd_flags,
+        local,
+    })
+}
+
+/// Serialize a frame as a struct can_frame or struct canfd_frame
+fn encode_frame(frame: &RawFrame) -> io::Result<Vec<u8>> {
+    let (mtu, max_len) = match frame.fd_flags {
+        Some(_) => (CANFD_MTU, 64),
+        None => (CAN_MTU, 8),
+    };
+
+    if frame.data.len() > max_len {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("too much data for a CAN frame: {} bytes", frame.data.len()),
+        ));
+    }
+
+    let mut buf = vec![0u8; mtu];
+    buf[0..4].copy_from_slice(&frame.id.to_ne_bytes());
+    buf[4] = frame.data.len() as u8;
+    if let Some(flags) = frame.fd_flags {
+        // CAN FD only has a few valid lengths, the padding is already zeroed
+        let len = FD_LENGTHS.iter().find(|&&n| n >= frame.data.len());
+        buf[4] = len.copied().unwrap_or(max_len) as u8;
+        buf[5] = flags;
+    }
+    buf[8..(8 + frame.data.len())].copy_from_slice(&frame.data);
+
+    Ok(buf)
+}
+
+fn send_frame(fd: RawFd, buf: &[u8]) -> io::Result<()> {
+    let n = unsafe { libc::write(fd, buf.as_ptr() as *const libc::c_void, buf.len()) };
+
+    if n < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    if n as usize != buf.len() {
+        return Err(io::Error::new(
+            io::ErrorKind::WriteZero,
+            "incomplete CAN frame written",
+        ));
+    }
+
+    Ok(())
+}
+
+fn timestamp_from_cmsg(msg: &libc::msghdr) -> Option<Duration> {
+    let mut cmsg = unsafe { libc::CMSG_FIRSTHDR(msg) };
+
+    while !cmsg.is_null() {
+        let hdr = unsafe { &*cmsg };
+
+        if hdr.cmsg_level == libc::SOL_SOCKET && hdr.cmsg_type == libc::SCM_TIMESTAMPNS {
+            let ts: libc::timespec =
+                unsafe { std::ptr::read_unaligned(libc::CMSG_DATA(cmsg) as *const libc::timespec) };
+            return Some(Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32));
+        }
+
+        cmsg = unsafe { libc::CMSG_NXTHDR(msg, cmsg) };
+    }
+
+    None
+}
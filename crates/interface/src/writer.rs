@@ -0,0 +1,163 @@
+use crate::{demo, socket_can};
+use anyhow::Result;
+use canbusnoop_core::{CanId, Frame};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// A request to transmit frames, for the task owning the [`CanBusWriter`]
+#[derive(Debug, Clone)]
+pub enum TxRequest {
+    /// Send a single frame
+    Send(Frame),
+    /// Send a frame every `period`, replacing the periodic transmission with
+    /// the same id, if any
+    StartPeriodic { frame: Frame, period: Duration },
+    /// Stop the periodic transmission of the frame with this id
+    StopPeriodic(CanId),
+}
+
+/// Sends frames to the bus a [`CanBusReader`](crate::CanBusReader) is
+/// reading from. Sent frames are read back like any other frame.
+pub struct CanBusWriter {
+    inner: InnerCanBusWriter,
+    /// Periodic transmissions, by id
+    periodic: BTreeMap<CanId, JoinHandle<()>>,
+}
+
+impl CanBusWriter {
+    pub(crate) fn new(inner: InnerCanBusWriter) -> CanBusWriter {
+        CanBusWriter {
+            inner,
+            periodic: BTreeMap::new(),
+        }
+    }
+
+    /// Send a single frame
+    pub async fn send(&self, frame: &Frame) -> Result<()> {
+        self.inner.send(frame).await
+    }
+
+    /// Send `frame` every `period`, in a background task. A periodic
+    /// transmission with the same id is replaced. Failed sends are retried
+    /// at the next period, until the transmission is stopped.
+    pub fn start_periodic(&mut self, frame: Frame, period: Duration) {
+        let inner = self.inner.clone();
+        let id = frame.id();
+        let task = tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            // Only the first error is logged, e.g. while the queue is full
+            let mut failing = false;
+            loop {
+                interval.tick().await;
+                match inner.send(&frame).await {
+                    Ok(()) => failing = false,
+                    Err(e) if !failing => {
+                        log::error!("Cannot send periodic frame {}: {}", frame.id(), e);
+                        failing = true;
+                    }
+                    Err(_) => {}
+                }
+            }
+        });
+
+        if let Some(previous) = self.periodic.insert(id, task) {
+            previous.abort();
+        }
+    }
+
+    /// Stop the periodic transmission of the frame with id `id`
+    pub fn stop_periodic(&mut self, id: CanId) {
+        if let Some(task) = self.periodic.remove(&id) {
+            task.abort();
+        }
+    }
+
+    /// Execute a [`TxRequest`]
+    pub async fn handle(&mut self, request: TxRequest) -> Result<()> {
+        match request {
+            TxRequest::Send(frame) => self.send(&frame).await?,
+            TxRequest::StartPeriodic { frame, period } => self.start_periodic(frame, period),
+            TxRequest::StopPeriodic(id) => self.stop_periodic(id),
+        }
+        Ok(())
+    }
+}
+
+impl Drop for CanBusWriter {
+    fn drop(&mut self) {
+        for task in self.periodic.values() {
+            task.abort();
+        }
+    }
+}
+
+#[derive(Clone)]
+pub(crate) enum InnerCanBusWriter {
+    SocketCan(Arc<socket_can::Writer>),
+    Demo(demo::Writer),
+}
+
+impl InnerCanBusWriter {
+    async fn send(&self, frame: &Frame) -> Result<()> {
+        match self {
+            InnerCanBusWriter::SocketCan(inner) => inner.send(frame).await,
+            InnerCanBusWriter::Demo(inner) => inner.send(frame),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CanBusReader, Config};
+    use canbusnoop_core::{CanId, Direction, Frame};
+    use std::time::Duration;
+    use tokio::time::{timeout_at, Instant};
+
+    /// Frames sent by the writer and looped back, read for `duration`
+    async fn read_sent(reader: &mut CanBusReader, duration: Duration) -> Vec<Frame> {
+        let deadline = Instant::now() + duration;
+        let mut sent = Vec::new();
+        while let Ok(frame) = timeout_at(deadline, reader.read()).await {
+            let frame = frame.unwrap().unwrap();
+            if frame.direction() == Direction::Tx {
+                sent.push(frame);
+            }
+        }
+        sent
+    }
+
+    #[tokio::test]
+    async fn demo_loopback() {
+        let mut reader = CanBusReader::new(Config::Demo).unwrap();
+        let mut writer = reader.writer().unwrap();
+        let id = CanId::standard(0x7AB).unwrap();
+        let frame = Frame::new(id, vec![1, 2, 3], Duration::ZERO);
+
+        writer.send(&frame).await.unwrap();
+        let sent = read_sent(&mut reader, Duration::from_millis(50)).await;
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].id(), id);
+        assert_eq!(sent[0].data(), [1, 2, 3]);
+        assert_eq!(sent[0].interface(), Some("demo"));
+        assert!(sent[0].timestamp() > Duration::ZERO);
+
+        writer.start_periodic(frame.clone(), Duration::from_millis(10));
+        let sent = read_sent(&mut reader, Duration::from_millis(200)).await;
+        assert!((10..=25).contains(&sent.len()), "{}", sent.len());
+
+        // Replaced by a transmission with the same id
+        let other = Frame::new(id, vec![4], Duration::ZERO);
+        writer.start_periodic(other, Duration::from_millis(10));
+        let sent = read_sent(&mut reader, Duration::from_millis(100)).await;
+        assert!(sent.iter().skip(1).all(|f| f.data() == [4]));
+
+        writer.stop_periodic(id);
+        // Frames sent before the stop
+        read_sent(&mut reader, Duration::from_millis(20)).await;
+        let sent = read_sent(&mut reader, Duration::from_millis(100)).await;
+        assert!(sent.is_empty());
+    }
+}
@@ -0,0 +1,404 @@
+//! CANopen (CiA 301) with the predefined connection set: the 4 most
+//! significant bits of the 11 bit identifier are the function code, the
+//! others are the node id.
+
+mod emcy;
+mod sdo;
+
+pub use emcy::{emcy_description, Emergency};
+pub use sdo::{sdo_abort_description, SdoAccess, SdoTransfer};
+
+use canbusnoop_core::{CanId, Frame};
+use sdo::SdoTracker;
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::Display;
+use std::time::Duration;
+
+/// Emergencies and SDO transfers kept for each node
+const HISTORY: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Function {
+    Nmt,
+    Sync,
+    Emcy,
+    Time,
+    /// Transmit PDO 1 to 4, sent by the node
+    Tpdo(u8),
+    /// Receive PDO 1 to 4, sent to the node
+    Rpdo(u8),
+    /// SDO response, sent by the server
+    SdoTx,
+    /// SDO request, sent by the client
+    SdoRx,
+    /// NMT error control: heartbeat, boot-up and node guarding
+    Heartbeat,
+    Lss,
+}
+
+impl Display for Function {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Function::Nmt => write!(f, "NMT"),
+            Function::Sync => write!(f, "SYNC"),
+            Function::Emcy => write!(f, "EMCY"),
+            Function::Time => write!(f, "TIME"),
+            Function::Tpdo(n) => write!(f, "TPDO{}", n),
+            Function::Rpdo(n) => write!(f, "RPDO{}", n),
+            Function::SdoTx => write!(f, "SDO tx"),
+            Function::SdoRx => write!(f, "SDO rx"),
+            Function::Heartbeat => write!(f, "Heartbeat"),
+            Function::Lss => write!(f, "LSS"),
+        }
+    }
+}
+
+/// Function and node of a CANopen identifier
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CobId {
+    pub function: Function,
+    /// `None` for broadcast objects (NMT, SYNC, TIME, LSS)
+    pub node: Option<u8>,
+}
+
+impl CobId {
+    /// Classify a standard identifier. Returns `None` for extended ids and
+    /// ids outside of the predefined connection set.
+    pub fn from_can_id(id: CanId) -> Option<CobId> {
+        if id.is_extended() || id.is_error() {
+            return None;
+        }
+
+        let id = id.id();
+        let node = (id & 0x7F) as u8;
+        let broadcast = |function| {
+            Some(CobId {
+                function,
+                node: None,
+            })
+        };
+
+        let function = match (id >> 7, node) {
+            _ if id == 0x7E4 || id == 0x7E5 => return broadcast(Function::Lss),
+            (0x0, 0) => return broadcast(Function::Nmt),
+            (0x1, 0) => return broadcast(Function::Sync),
+            (0x2, 0) => return broadcast(Function::Time),
+            (_, 0) => return None,
+            (0x1, _) => Function::Emcy,
+            (0x3, _) => Function::Tpdo(1),
+            (0x4, _) => Function::Rpdo(1),
+            (0x5, _) => Function::Tpdo(2),
+            (0x6, _) => Function::Rpdo(2),
+            (0x7, _) => Function::Tpdo(3),
+            (0x8, _) => Function::Rpdo(3),
+            (0x9, _) => Function::Tpdo(4),
+            (0xA, _) => Function::Rpdo(4),
+            (0xB, _) => Function::SdoTx,
+            (0xC, _) => Function::SdoRx,
+            (0xE, _) => Function::Heartbeat,
+            _ => return None,
+        };
+
+        Some(CobId {
+            function,
+            node: Some(node),
+        })
+    }
+}
+
+impl Display for CobId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.node {
+            Some(node) => write!(f, "{} node {}", self.function, node),
+            None => write!(f, "{}", self.function),
+        }
+    }
+}
+
+/// NMT state, as reported by the heartbeat
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NmtState {
+    BootUp,
+    Stopped,
+    Operational,
+    PreOperational,
+    Unknown(u8),
+}
+
+impl NmtState {
+    fn from_byte(state: u8) -> NmtState {
+        // The most significant bit is the node guarding toggle bit
+        match state & 0x7F {
+            0 => NmtState::BootUp,
+            4 => NmtState::Stopped,
+            5 => NmtState::Operational,
+            127 => NmtState::PreOperational,
+            x => NmtState::Unknown(x),
+        }
+    }
+}
+
+impl Display for NmtState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NmtState::BootUp => write!(f, "Boot-up"),
+            NmtState::Stopped => write!(f, "Stopped"),
+            NmtState::Operational => write!(f, "Operational"),
+            NmtState::PreOperational => write!(f, "Pre-operational"),
+            NmtState::Unknown(x) => write!(f, "Unknown ({})", x),
+        }
+    }
+}
+
+/// NMT command sent by the master
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NmtCommand {
+    Start,
+    Stop,
+    EnterPreOperational,
+    ResetNode,
+    ResetCommunication,
+}
+
+impl NmtCommand {
+    fn from_byte(command: u8) -> Option<NmtCommand> {
+        match command {
+            1 => Some(NmtCommand::Start),
+            2 => Some(NmtCommand::Stop),
+            128 => Some(NmtCommand::EnterPreOperational),
+            129 => Some(NmtCommand::ResetNode),
+            130 => Some(NmtCommand::ResetCommunication),
+            _ => None,
+        }
+    }
+}
+
+impl Display for NmtCommand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NmtCommand::Start => write!(f, "Start"),
+            NmtCommand::Stop => write!(f, "Stop"),
+            NmtCommand::EnterPreOperational => write!(f, "Enter pre-operational"),
+            NmtCommand::ResetNode => write!(f, "Reset node"),
+            NmtCommand::ResetCommunication => write!(f, "Reset communication"),
+        }
+    }
+}
+
+/// What is known about a node
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Node {
+    state: Option<NmtState>,
+    last_heartbeat: Option<Duration>,
+    heartbeat_period: Option<Duration>,
+    last_command: Option<(NmtCommand, Duration)>,
+    emergencies: VecDeque<Emergency>,
+    emergency_count: usize,
+    sdo: SdoTracker,
+    sdo_transfers: VecDeque<SdoTransfer>,
+    pdos: BTreeMap<Function, usize>,
+}
+
+impl Node {
+    /// NMT state from the last heartbeat
+    pub fn state(&self) -> Option<NmtState> {
+        self.state
+    }
+
+    pub fn last_heartbeat(&self) -> Option<Duration> {
+        self.last_heartbeat
+    }
+
+    /// Time between the last two heartbeats
+    pub fn heartbeat_period(&self) -> Option<Duration> {
+        self.heartbeat_period
+    }
+
+    /// Last NMT command sent to the node, with its time
+    pub fn last_command(&self) -> Option<(NmtCommand, Duration)> {
+        self.last_command
+    }
+
+    /// Most recent emergencies, oldest first
+    pub fn emergencies(&self) -> impl DoubleEndedIterator<Item = &Emergency> {
+        self.emergencies.iter()
+    }
+
+    pub fn emergency_count(&self) -> usize {
+        self.emergency_count
+    }
+
+    /// Most recent SDO transfers, oldest first
+    pub fn sdo_transfers(&self) -> impl DoubleEndedIterator<Item = &SdoTransfer> {
+        self.sdo_transfers.iter()
+    }
+
+    /// PDOs sent by or to the node, with the number of frames
+    pub fn pdos(&self) -> impl Iterator<Item = (Function, usize)> + '_ {
+        self.pdos.iter().map(|(f, count)| (*f, *count))
+    }
+}
+
+fn push_bounded<T>(history: &mut VecDeque<T>, item: T) {
+    if history.len() == HISTORY {
+        history.pop_front();
+    }
+    history.push_back(item);
+}
+
+/// State of the CANopen nodes, by node id, interpreted from the traffic
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct CanOpen {
+    nodes: BTreeMap<u8, Node>,
+}
+
+impl CanOpen {
+    pub fn push(&mut self, frame: &Frame) {
+        if frame.id().is_rtr() {
+            return;
+        }
+        let Some(cob_id) = CobId::from_can_id(frame.id()) else {
+            return;
+        };
+        let data = frame.data();
+        let now = frame.timestamp();
+
+        let Some(node) = cob_id.node else {
+            if cob_id.function == Function::Nmt {
+                self.nmt(data, now);
+            }
+            return;
+        };
+        let node = self.nodes.entry(node).or_default();
+
+        match cob_id.function {
+            Function::Heartbeat => {
+                let Some(&state) = data.first() else {
+                    return;
+                };
+                node.state = Some(NmtState::from_byte(state));
+                node.heartbeat_period = node.last_heartbeat.map(|t| now.saturating_sub(t));
+                node.last_heartbeat = Some(now);
+            }
+            Function::Emcy => {
+                if let Some(emergency) = Emergency::parse(data, now) {
+                    node.emergency_count += 1;
+                    push_bounded(&mut node.emergencies, emergency);
+                }
+            }
+            Function::SdoRx | Function::SdoTx => {
+                let request = cob_id.function == Function::SdoRx;
+                if let Some(transfer) = node.sdo.push(request, data, now) {
+                    push_bounded(&mut node.sdo_transfers, transfer);
+                }
+            }
+            Function::Tpdo(_) | Function::Rpdo(_) => {
+                *node.pdos.entry(cob_id.function).or_default() += 1;
+            }
+            _ => {}
+        }
+    }
+
+    /// `[command, node]`, node 0 addresses all the nodes
+    fn nmt(&mut self, data: &[u8], now: Duration) {
+        let (Some(command), Some(&target)) = (
+            data.first().copied().and_then(NmtCommand::from_byte),
+            data.get(1),
+        ) else {
+            return;
+        };
+
+        if target == 0 {
+            for node in self.nodes.values_mut() {
+                node.last_command = Some((command, now));
+            }
+        } else {
+            self.nodes.entry(target).or_default().last_command = Some((command, now));
+        }
+    }
+
+    /// Nodes seen on the bus, by node id
+    pub fn nodes(&self) -> impl Iterator<Item = (u8, &Node)> {
+        self.nodes.iter().map(|(id, node)| (*id, node))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cob_id(id: u16) -> Option<CobId> {
+        CobId::from_can_id(CanId::standard(id).unwrap())
+    }
+
+    fn frame(id: u16, data: &[u8], ms: u64) -> Frame {
+        let time = Duration::from_millis(ms);
+        Frame::new(CanId::standard(id).unwrap(), data.to_vec(), time)
+    }
+
+    #[test]
+    fn classifies_ids() {
+        let node = |function, node| {
+            Some(CobId {
+                function,
+                node: Some(node),
+            })
+        };
+        let broadcast = |function| {
+            Some(CobId {
+                function,
+                node: None,
+            })
+        };
+
+        assert_eq!(cob_id(0x000), broadcast(Function::Nmt));
+        assert_eq!(cob_id(0x080), broadcast(Function::Sync));
+        assert_eq!(cob_id(0x7E5), broadcast(Function::Lss));
+        assert_eq!(cob_id(0x081), node(Function::Emcy, 1));
+        assert_eq!(cob_id(0x1A5), node(Function::Tpdo(1), 0x25));
+        assert_eq!(cob_id(0x50A), node(Function::Rpdo(4), 0x0A));
+        assert_eq!(cob_id(0x5FF), node(Function::SdoTx, 0x7F));
+        assert_eq!(cob_id(0x601), node(Function::SdoRx, 1));
+        assert_eq!(cob_id(0x701), node(Function::Heartbeat, 1));
+        assert_eq!(cob_id(0x700), None);
+        assert_eq!(cob_id(0x681), None);
+        assert_eq!(CobId::from_can_id(CanId::extended(0x701).unwrap()), None);
+    }
+
+    #[test]
+    fn tracks_nodes() {
+        let mut canopen = CanOpen::default();
+        for frame in [
+            frame(0x705, &[0x00], 0),
+            frame(0x705, &[0x7F], 100),
+            frame(0x185, &[1, 2], 120),
+            frame(0x185, &[1, 2], 130),
+            frame(0x000, &[0x01, 0x00], 150),
+            frame(0x705, &[0x85], 300),
+            frame(0x085, &[0x10, 0x81, 0x11, 0, 0, 0, 0, 0], 310),
+        ] {
+            canopen.push(&frame);
+        }
+
+        let (id, node) = canopen.nodes().next().unwrap();
+        assert_eq!(id, 5);
+        // The toggle bit is ignored
+        assert_eq!(node.state(), Some(NmtState::Operational));
+        assert_eq!(node.heartbeat_period(), Some(Duration::from_millis(200)));
+        assert_eq!(
+            node.last_command(),
+            Some((NmtCommand::Start, Duration::from_millis(150)))
+        );
+        assert_eq!(node.pdos().collect::<Vec<_>>(), [(Function::Tpdo(1), 2)]);
+        assert_eq!(node.emergency_count(), 1);
+        assert_eq!(node.emergencies().next().unwrap().code, 0x8110);
+    }
+}
@@ -0,0 +1,91 @@
+use std::fmt::Display;
+use std::time::Duration;
+
+/// Emergency object: error code, error register and manufacturer specific
+/// data
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Emergency {
+    pub code: u16,
+    pub register: u8,
+    pub data: Vec<u8>,
+    pub timestamp: Duration,
+}
+
+impl Emergency {
+    pub(crate) fn parse(data: &[u8], timestamp: Duration) -> Option<Emergency> {
+        let code = u16::from_le_bytes([*data.first()?, *data.get(1)?]);
+        let register = *data.get(2)?;
+        Some(Emergency {
+            code,
+            register,
+            data: data[3..].to_vec(),
+            timestamp,
+        })
+    }
+
+    /// An emergency with code 0 signals that all errors were reset
+    pub fn is_reset(&self) -> bool {
+        self.code == 0
+    }
+}
+
+impl Display for Emergency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:04X} {} (register {:02X})",
+            self.code,
+            emcy_description(self.code),
+            self.register
+        )
+    }
+}
+
+/// Description of an emergency error code, from the specific code or from
+/// its class (the most significant byte)
+pub fn emcy_description(code: u16) -> &'static str {
+    match code {
+        0x0000 => "error reset or no error",
+        0x8110 => "CAN overrun",
+        0x8120 => "CAN error passive",
+        0x8130 => "life guard or heartbeat error",
+        0x8140 => "recovered from bus off",
+        0x8150 => "CAN-ID collision",
+        0x8210 => "PDO not processed due to length error",
+        0x8220 => "PDO length exceeded",
+        0x8230 => "DAM MPDO not processed",
+        0x8240 => "unexpected SYNC data length",
+        0x8250 => "RPDO timeout",
+        _ => class_description(code),
+    }
+}
+
+fn class_description(code: u16) -> &'static str {
+    match code >> 8 {
+        0x10 => "generic error",
+        0x20 => "current",
+        0x21 => "current, device input side",
+        0x22 => "current inside the device",
+        0x23 => "current, device output side",
+        0x30 => "voltage",
+        0x31 => "mains voltage",
+        0x32 => "voltage inside the device",
+        0x33 => "output voltage",
+        0x40 => "temperature",
+        0x41 => "ambient temperature",
+        0x42 => "device temperature",
+        0x50 => "device hardware",
+        0x60 => "device software",
+        0x61 => "internal software",
+        0x62 => "user software",
+        0x63 => "data set",
+        0x70 => "additional modules",
+        0x80 => "monitoring",
+        0x81 => "communication",
+        0x82 => "protocol error",
+        0x90 => "external error",
+        0xF0 => "additional functions",
+        0xFF => "device specific",
+        _ => "unknown",
+    }
+}
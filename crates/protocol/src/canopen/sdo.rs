@@ -0,0 +1,318 @@
+//! Service data objects: reads (uploads) and writes (downloads) of the
+//! object dictionary of a node. Expedited and segmented transfers are
+//! decoded, block transfers are not.
+
+use std::fmt::Display;
+use std::time::Duration;
+
+/// Command specifiers, in the 3 most significant bits of the first byte
+const DOWNLOAD_SEGMENT: u8 = 0;
+const INITIATE_DOWNLOAD: u8 = 1;
+const INITIATE_UPLOAD: u8 = 2;
+const UPLOAD_SEGMENT: u8 = 3;
+const ABORT: u8 = 4;
+
+/// Server command specifiers, where they differ from the client ones
+const UPLOAD_SEGMENT_RESPONSE: u8 = 0;
+const DOWNLOAD_SEGMENT_RESPONSE: u8 = 1;
+const INITIATE_UPLOAD_RESPONSE: u8 = 2;
+const INITIATE_DOWNLOAD_RESPONSE: u8 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SdoAccess {
+    /// Upload, from the server to the client
+    Read,
+    /// Download, from the client to the server
+    Write,
+}
+
+/// A completed or aborted SDO transfer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SdoTransfer {
+    pub access: SdoAccess,
+    pub index: u16,
+    pub subindex: u8,
+    /// The value read or written, what was transferred when aborted
+    pub data: Vec<u8>,
+    /// Abort code, `None` when the transfer completed
+    pub abort: Option<u32>,
+    /// Time of the last frame of the transfer
+    pub timestamp: Duration,
+}
+
+impl Display for SdoTransfer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let access = match self.access {
+            SdoAccess::Read => "read",
+            SdoAccess::Write => "write",
+        };
+        write!(f, "{} {:04X}:{:02X}", access, self.index, self.subindex)?;
+        if let Some(code) = self.abort {
+            return write!(f, " aborted {:08X} {}", code, sdo_abort_description(code));
+        }
+        write!(f, " =")?;
+        for b in &self.data {
+            write!(f, " {:02X}", b)?;
+        }
+        Ok(())
+    }
+}
+
+/// Description of an SDO abort code
+pub fn sdo_abort_description(code: u32) -> &'static str {
+    match code {
+        0x0503_0000 => "toggle bit not alternated",
+        0x0504_0000 => "SDO protocol timed out",
+        0x0504_0001 => "command specifier not valid or unknown",
+        0x0504_0002 => "invalid block size",
+        0x0504_0003 => "invalid sequence number",
+        0x0504_0004 => "CRC error",
+        0x0504_0005 => "out of memory",
+        0x0601_0000 => "unsupported access to an object",
+        0x0601_0001 => "attempt to read a write only object",
+        0x0601_0002 => "attempt to write a read only object",
+        0x0602_0000 => "object does not exist",
+        0x0604_0041 => "object cannot be mapped to the PDO",
+        0x0604_0042 => "PDO length exceeded",
+        0x0604_0043 => "general parameter incompatibility",
+        0x0604_0047 => "general internal incompatibility",
+        0x0606_0000 => "access failed due to a hardware error",
+        0x0607_0010 => "data type does not match",
+        0x0607_0012 => "data type does not match, length too high",
+        0x0607_0013 => "data type does not match, length too low",
+        0x0609_0011 => "sub-index does not exist",
+        0x0609_0030 => "value range exceeded",
+        0x0609_0031 => "value too high",
+        0x0609_0032 => "value too low",
+        0x0609_0036 => "maximum value less than minimum value",
+        0x060A_0023 => "resource not available",
+        0x0800_0000 => "general error",
+        0x0800_0020 => "data cannot be transferred or stored",
+        0x0800_0021 => "data cannot be transferred, local control",
+        0x0800_0022 => "data cannot be transferred, device state",
+        0x0800_0023 => "object dictionary not present",
+        0x0800_0024 => "no data available",
+        _ => "unknown",
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Session {
+    access: SdoAccess,
+    index: u16,
+    subindex: u8,
+    data: Vec<u8>,
+    /// All the data was sent, waiting for the server to confirm
+    complete: bool,
+}
+
+impl Session {
+    fn finish(self, abort: Option<u32>, timestamp: Duration) -> SdoTransfer {
+        SdoTransfer {
+            access: self.access,
+            index: self.index,
+            subindex: self.subindex,
+            data: self.data,
+            abort,
+            timestamp,
+        }
+    }
+}
+
+/// Follow the SDO transfers with a server. Only one transfer at a time can
+/// be active with each server.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub(crate) struct SdoTracker {
+    session: Option<Session>,
+}
+
+impl SdoTracker {
+    /// Push a request (from the client) or a response (from the server).
+    /// Returns the transfer when it completes or is aborted.
+    pub(crate) fn push(
+        &mut self,
+        request: bool,
+        data: &[u8],
+        now: Duration,
+    ) -> Option<SdoTransfer> {
+        let data: &[u8; 8] = data.try_into().ok()?;
+        let command = data[0] >> 5;
+
+        if command == ABORT {
+            let code = u32::from_le_bytes([data[4], data[5], data[6], data[7]]);
+            let session = self.session.take().unwrap_or_else(|| Session {
+                access: SdoAccess::Read,
+                index: u16::from_le_bytes([data[1], data[2]]),
+                subindex: data[3],
+                data: Vec::new(),
+                complete: false,
+            });
+            return Some(session.finish(Some(code), now));
+        }
+
+        if request {
+            self.request(command, data);
+            None
+        } else {
+            self.response(command, data, now)
+        }
+    }
+
+    fn request(&mut self, command: u8, data: &[u8; 8]) {
+        let index = u16::from_le_bytes([data[1], data[2]]);
+        let subindex = data[3];
+
+        match command {
+            INITIATE_DOWNLOAD => {
+                let (payload, complete) = match expedited(data) {
+                    Some(payload) => (payload.to_vec(), true),
+                    None => (Vec::new(), false),
+                };
+                self.session = Some(Session {
+                    access: SdoAccess::Write,
+                    index,
+                    subindex,
+                    data: payload,
+                    complete,
+                });
+            }
+            INITIATE_UPLOAD => {
+                self.session = Some(Session {
+                    access: SdoAccess::Read,
+                    index,
+                    subindex,
+                    data: Vec::new(),
+                    complete: false,
+                });
+            }
+            DOWNLOAD_SEGMENT => {
+                if let Some(session) = &mut self.session {
+                    session.complete = segment(data, &mut session.data);
+                }
+            }
+            UPLOAD_SEGMENT => {}
+            // Block transfers
+            _ => self.session = None,
+        }
+    }
+
+    fn response(&mut self, command: u8, data: &[u8; 8], now: Duration) -> Option<SdoTransfer> {
+        let session = self.session.as_mut()?;
+
+        match (session.access, command) {
+            (SdoAccess::Write, INITIATE_DOWNLOAD_RESPONSE)
+            | (SdoAccess::Write, DOWNLOAD_SEGMENT_RESPONSE) => {
+                if !session.complete {
+                    return None;
+                }
+            }
+            (SdoAccess::Read, INITIATE_UPLOAD_RESPONSE) => match expedited(data) {
+                Some(payload) => session.data = payload.to_vec(),
+                // Segments follow
+                None => return None,
+            },
+            (SdoAccess::Read, UPLOAD_SEGMENT_RESPONSE) => {
+                if !segment(data, &mut session.data) {
+                    return None;
+                }
+            }
+            _ => return None,
+        }
+
+        self.session.take().map(|s| s.finish(None, now))
+    }
+}
+
+/// The data of an expedited initiate frame, `None` for segmented transfers
+fn expedited(data: &[u8; 8]) -> Option<&[u8]> {
+    let expedited = data[0] & 0x02 != 0;
+    let size_indicated = data[0] & 0x01 != 0;
+    if !expedited {
+        return None;
+    }
+    // Number of bytes that do not contain data
+    let n = if size_indicated {
+        ((data[0] >> 2) & 0x3) as usize
+    } else {
+        0
+    };
+    Some(&data[4..8 - n])
+}
+
+/// Append the data of a segment. Returns true if it is the last one.
+fn segment(data: &[u8; 8], out: &mut Vec<u8>) -> bool {
+    let n = ((data[0] >> 1) & 0x7) as usize;
+    out.extend_from_slice(&data[1..8 - n]);
+    data[0] & 0x01 != 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn expedited_write() {
+        let mut sdo = SdoTracker::default();
+        let request = [0x2B, 0x17, 0x10, 0x00, 0xE8, 0x03, 0x00, 0x00];
+        assert_eq!(sdo.push(true, &request, ms(0)), None);
+
+        let response = [0x60, 0x17, 0x10, 0x00, 0, 0, 0, 0];
+        let transfer = sdo.push(false, &response, ms(1)).unwrap();
+        assert_eq!(transfer.access, SdoAccess::Write);
+        assert_eq!((transfer.index, transfer.subindex), (0x1017, 0));
+        assert_eq!(transfer.data, [0xE8, 0x03]);
+        assert_eq!(transfer.abort, None);
+        assert_eq!(transfer.timestamp, ms(1));
+        assert_eq!(transfer.to_string(), "write 1017:00 = E8 03");
+    }
+
+    #[test]
+    fn segmented_read() {
+        let mut sdo = SdoTracker::default();
+        let frames: [(bool, [u8; 8]); 6] = [
+            (true, [0x40, 0x08, 0x10, 0x00, 0, 0, 0, 0]),
+            (false, [0x41, 0x08, 0x10, 0x00, 9, 0, 0, 0]),
+            (true, [0x60, 0, 0, 0, 0, 0, 0, 0]),
+            (false, [0x00, b'c', b'a', b'n', b'b', b'u', b's', b'n']),
+            (true, [0x70, 0, 0, 0, 0, 0, 0, 0]),
+            // Toggle bit, 5 bytes without data, last segment
+            (false, [0x1B, b'o', b'o', 0, 0, 0, 0, 0]),
+        ];
+        let transfers: Vec<_> = frames
+            .iter()
+            .enumerate()
+            .filter_map(|(i, (request, data))| sdo.push(*request, data, ms(i as u64)))
+            .collect();
+
+        let [transfer] = &transfers[..] else {
+            panic!("unexpected {:?}", transfers);
+        };
+        assert_eq!(transfer.access, SdoAccess::Read);
+        assert_eq!((transfer.index, transfer.subindex), (0x1008, 0));
+        assert_eq!(transfer.data, b"canbusnoo");
+        assert_eq!(transfer.timestamp, ms(5));
+    }
+
+    #[test]
+    fn aborted() {
+        let mut sdo = SdoTracker::default();
+        sdo.push(true, &[0x40, 0x00, 0x20, 0x01, 0, 0, 0, 0], ms(0));
+        let abort = [0x80, 0x00, 0x20, 0x01, 0x00, 0x00, 0x02, 0x06];
+        let transfer = sdo.push(false, &abort, ms(1)).unwrap();
+        assert_eq!(transfer.abort, Some(0x0602_0000));
+        assert_eq!(
+            transfer.to_string(),
+            "read 2000:01 aborted 06020000 object does not exist"
+        );
+
+        // No transfer is left to complete
+        assert_eq!(
+            sdo.push(false, &[0x43, 0x00, 0x20, 0x01, 1, 2, 3, 4], ms(2)),
+            None
+        );
+    }
+}
@@ -0,0 +1,566 @@
+//! ISO-TP (ISO 15765-2): messages up to 4 GiB carried by single, first and
+//! consecutive frames, paced by flow control frames sent by the receiver.
+//!
+//! Only the request and response identifiers of the configured channels are
+//! considered, other traffic can't be told apart from ISO-TP.
+
+use canbusnoop_core::{CanId, Frame};
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::str::FromStr;
+use std::time::Duration;
+
+const SINGLE_FRAME: u8 = 0;
+const FIRST_FRAME: u8 = 1;
+const CONSECUTIVE_FRAME: u8 = 2;
+const FLOW_CONTROL: u8 = 3;
+
+/// Flow status of a flow control frame telling the sender the message is
+/// too long
+const OVERFLOW: u8 = 2;
+
+/// Maximum time to wait for a consecutive frame (N_Cr)
+const TIMEOUT: Duration = Duration::from_millis(1000);
+
+/// Where the addressing information is
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Addressing {
+    /// Only the CAN identifier
+    #[default]
+    Normal,
+    /// The first data byte is the target address
+    Extended,
+    /// The first data byte is the address extension
+    Mixed,
+}
+
+/// A pair of identifiers used by a tester and an ECU to talk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IsoTpChannel {
+    /// Sent by the tester
+    pub request: CanId,
+    /// Sent by the ECU
+    pub response: CanId,
+    pub addressing: Addressing,
+}
+
+impl Display for IsoTpChannel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.request, self.response)?;
+        match self.addressing {
+            Addressing::Normal => Ok(()),
+            Addressing::Extended => write!(f, ",extended"),
+            Addressing::Mixed => write!(f, ",mixed"),
+        }
+    }
+}
+
+/// Parse `REQUEST:RESPONSE[,normal|extended|mixed]`, ids in hex
+impl FromStr for IsoTpChannel {
+    type Err = ParseIsoTpChannelError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || ParseIsoTpChannelError(s.to_string());
+        let (ids, addressing) = match s.split_once(',') {
+            Some((ids, addressing)) => (ids, Some(addressing)),
+            None => (s, None),
+        };
+        let addressing = match addressing.map(str::trim) {
+            None | Some("normal") => Addressing::Normal,
+            Some("extended") => Addressing::Extended,
+            Some("mixed") => Addressing::Mixed,
+            Some(_) => return Err(error()),
+        };
+        let (request, response) = ids.split_once(':').ok_or_else(error)?;
+        Ok(IsoTpChannel {
+            request: request.trim().parse().map_err(|_| error())?,
+            response: response.trim().parse().map_err(|_| error())?,
+            addressing,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseIsoTpChannelError(String);
+
+impl Display for ParseIsoTpChannelError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "invalid ISO-TP channel {:?}, expected REQUEST:RESPONSE[,extended|mixed]",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for ParseIsoTpChannelError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum IsoTpDirection {
+    /// From the tester to the ECU
+    Request,
+    /// From the ECU to the tester
+    Response,
+}
+
+/// A reassembled message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IsoTpMessage {
+    /// Index of the channel in the configuration
+    pub channel: usize,
+    pub direction: IsoTpDirection,
+    /// Target address or address extension, `None` with normal addressing
+    pub address: Option<u8>,
+    pub data: Vec<u8>,
+    /// Time of the last frame
+    pub timestamp: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IsoTpError {
+    /// A consecutive frame without a first frame
+    UnexpectedConsecutive,
+    /// A consecutive frame was lost or sent out of order
+    BadSequence,
+    /// A new message started before the end of the previous one
+    Interrupted,
+    /// The receiver can't accept a message this long
+    Overflow,
+    /// No consecutive frames for too long
+    Timeout,
+}
+
+impl Display for IsoTpError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            IsoTpError::UnexpectedConsecutive => "unexpected consecutive frame",
+            IsoTpError::BadSequence => "bad sequence number",
+            IsoTpError::Interrupted => "interrupted by a new message",
+            IsoTpError::Overflow => "overflow",
+            IsoTpError::Timeout => "timeout",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IsoTpEvent {
+    Message(IsoTpMessage),
+    Error {
+        channel: usize,
+        direction: IsoTpDirection,
+        address: Option<u8>,
+        error: IsoTpError,
+        timestamp: Duration,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Session {
+    size: usize,
+    data: Vec<u8>,
+    next_sequence: u8,
+    last_time: Duration,
+}
+
+/// Messages being received, by identifier and address byte
+type SessionKey = (CanId, Option<u8>);
+
+/// Reassemble the messages of the configured channels
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct IsoTp {
+    channels: Vec<IsoTpChannel>,
+    sessions: BTreeMap<SessionKey, Session>,
+}
+
+impl IsoTp {
+    pub fn new(channels: Vec<IsoTpChannel>) -> IsoTp {
+        IsoTp {
+            channels,
+            sessions: BTreeMap::new(),
+        }
+    }
+
+    pub fn channels(&self) -> &[IsoTpChannel] {
+        &self.channels
+    }
+
+    pub fn push(&mut self, frame: &Frame) -> Vec<IsoTpEvent> {
+        let now = frame.timestamp();
+        let mut events = self.expire(now);
+
+        let id = frame.id();
+        let Some((channel, direction)) = self.channels.iter().enumerate().find_map(|(i, c)| {
+            if c.request == id {
+                Some((i, IsoTpDirection::Request))
+            } else if c.response == id {
+                Some((i, IsoTpDirection::Response))
+            } else {
+                None
+            }
+        }) else {
+            return events;
+        };
+
+        let (address, data) = match self.channels[channel].addressing {
+            Addressing::Normal => (None, frame.data()),
+            Addressing::Extended | Addressing::Mixed => match frame.data().split_first() {
+                Some((address, data)) => (Some(*address), data),
+                None => return events,
+            },
+        };
+        let Some(&pci) = data.first() else {
+            return events;
+        };
+
+        let key = (id, address);
+        let error = |error| IsoTpEvent::Error {
+            channel,
+            direction,
+            address,
+            error,
+            timestamp: now,
+        };
+        let message = |data| {
+            IsoTpEvent::Message(IsoTpMessage {
+                channel,
+                direction,
+                address,
+                data,
+                timestamp: now,
+            })
+        };
+
+        match pci >> 4 {
+            SINGLE_FRAME => {
+                if self.sessions.remove(&key).is_some() {
+                    events.push(error(IsoTpError::Interrupted));
+                }
+                // CAN FD single frames longer than 7 bytes have the length in
+                // the second byte
+                let (size, payload) = match pci & 0x0F {
+                    0 => match data.get(1) {
+                        Some(&size) => (size as usize, &data[2..]),
+                        None => return events,
+                    },
+                    size => (size as usize, &data[1..]),
+                };
+                if let Some(payload) = payload.get(..size) {
+                    events.push(message(payload.to_vec()));
+                }
+            }
+            FIRST_FRAME => {
+                if self.sessions.remove(&key).is_some() {
+                    events.push(error(IsoTpError::Interrupted));
+                }
+                let Some(&low) = data.get(1) else {
+                    return events;
+                };
+                // Messages longer than 4095 bytes have a 32 bit length
+                let (size, payload) = match ((pci & 0x0F) as usize) << 8 | low as usize {
+                    0 => match data.get(2..6) {
+                        Some(b) => (
+                            u32::from_be_bytes([b[0], b[1], b[2], b[3]]) as usize,
+                            &data[6..],
+                        ),
+                        None => return events,
+                    },
+                    size => (size, &data[2..]),
+                };
+                let session = Session {
+                    size,
+                    data: payload.to_vec(),
+                    next_sequence: 1,
+                    last_time: now,
+                };
+                self.sessions.insert(key, session);
+            }
+            CONSECUTIVE_FRAME => {
+                let Some(session) = self.sessions.get_mut(&key) else {
+                    events.push(error(IsoTpError::UnexpectedConsecutive));
+                    return events;
+                };
+                if pci & 0x0F != session.next_sequence {
+                    self.sessions.remove(&key);
+                    events.push(error(IsoTpError::BadSequence));
+                    return events;
+                }
+                session.data.extend_from_slice(&data[1..]);
+                session.next_sequence = (session.next_sequence + 1) & 0x0F;
+                session.last_time = now;
+                if session.data.len() >= session.size {
+                    let mut session = self.sessions.remove(&key).expect("session exists");
+                    session.data.truncate(session.size);
+                    events.push(message(session.data));
+                }
+            }
+            FLOW_CONTROL if pci & 0x0F == OVERFLOW => {
+                // Sent by the receiver, the message is from the other side
+                let (other, direction) = match direction {
+                    IsoTpDirection::Request => {
+                        (self.channels[channel].response, IsoTpDirection::Response)
+                    }
+                    IsoTpDirection::Response => {
+                        (self.channels[channel].request, IsoTpDirection::Request)
+                    }
+                };
+                self.sessions.remove(&(other, address));
+                events.push(IsoTpEvent::Error {
+                    channel,
+                    direction,
+                    address,
+                    error: IsoTpError::Overflow,
+                    timestamp: now,
+                });
+            }
+            _ => {}
+        }
+
+        events
+    }
+
+    /// Drop the messages that had no consecutive frames for too long
+    fn expire(&mut self, now: Duration) -> Vec<IsoTpEvent> {
+        let expired: Vec<_> = self
+            .sessions
+            .iter()
+            .filter(|(_, s)| now.saturating_sub(s.last_time) > TIMEOUT)
+            .map(|(key, _)| *key)
+            .collect();
+
+        expired
+            .into_iter()
+            .filter_map(|key| {
+                self.sessions.remove(&key);
+                let (id, address) = key;
+                let (channel, c) = self
+                    .channels
+                    .iter()
+                    .enumerate()
+                    .find(|(_, c)| c.request == id || c.response == id)?;
+                let direction = match c.request == id {
+                    true => IsoTpDirection::Request,
+                    false => IsoTpDirection::Response,
+                };
+                Some(IsoTpEvent::Error {
+                    channel,
+                    direction,
+                    address,
+                    error: IsoTpError::Timeout,
+                    timestamp: now,
+                })
+            })
+            .collect()
+    }
+
+    pub fn clear(&mut self) {
+        self.sessions.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    fn isotp(channel: &str) -> IsoTp {
+        IsoTp::new(vec![channel.parse().unwrap()])
+    }
+
+    fn frame(id: u16, data: &[u8], time: Duration) -> Frame {
+        Frame::new(CanId::standard(id).unwrap(), data.to_vec(), time)
+    }
+
+    /// Consecutive frames carrying `payload`, 7 bytes each, from sequence 1
+    fn consecutive(id: u16, payload: &[u8], start: Duration) -> Vec<Frame> {
+        payload
+            .chunks(7)
+            .enumerate()
+            .map(|(i, chunk)| {
+                let pci = 0x20 | ((i + 1) & 0x0F) as u8;
+                frame(id, &[&[pci][..], chunk].concat(), start + ms(i as u64))
+            })
+            .collect()
+    }
+
+    fn messages(events: &[IsoTpEvent]) -> Vec<&IsoTpMessage> {
+        events
+            .iter()
+            .filter_map(|e| match e {
+                IsoTpEvent::Message(message) => Some(message),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn errors(events: &[IsoTpEvent]) -> Vec<IsoTpError> {
+        events
+            .iter()
+            .filter_map(|e| match e {
+                IsoTpEvent::Error { error, .. } => Some(*error),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn push_all(isotp: &mut IsoTp, frames: &[Frame]) -> Vec<IsoTpEvent> {
+        frames.iter().flat_map(|f| isotp.push(f)).collect()
+    }
+
+    #[test]
+    fn parses_channels() {
+        let channel: IsoTpChannel = "6F1:612,extended".parse().unwrap();
+        assert_eq!(channel.request, CanId::standard(0x6F1).unwrap());
+        assert_eq!(channel.response, CanId::standard(0x612).unwrap());
+        assert_eq!(channel.addressing, Addressing::Extended);
+        assert_eq!(channel.to_string(), "6F1:612,extended");
+        assert!("7E0".parse::<IsoTpChannel>().is_err());
+        assert!("7E0:7E8,other".parse::<IsoTpChannel>().is_err());
+    }
+
+    #[test]
+    fn single_frame() {
+        let mut tp = isotp("7E0:7E8");
+        let events = tp.push(&frame(0x7E0, &[0x02, 0x10, 0x03, 0xAA, 0xAA], ms(5)));
+        assert_eq!(
+            events,
+            [IsoTpEvent::Message(IsoTpMessage {
+                channel: 0,
+                direction: IsoTpDirection::Request,
+                address: None,
+                data: vec![0x10, 0x03],
+                timestamp: ms(5),
+            })]
+        );
+
+        // Other ids are not ISO-TP
+        assert!(tp
+            .push(&frame(0x7E1, &[0x02, 0x10, 0x03], ms(6)))
+            .is_empty());
+    }
+
+    #[test]
+    fn single_frame_with_escape_length() {
+        let mut tp = isotp("7E0:7E8");
+        let payload: Vec<u8> = (0..20).collect();
+        let data = [&[0x00, 20][..], &payload, &[0xCC; 4]].concat();
+        let events = tp.push(&frame(0x7E8, &data, ms(0)));
+        let [message] = messages(&events)[..] else {
+            panic!("unexpected {:?}", events);
+        };
+        assert_eq!(message.direction, IsoTpDirection::Response);
+        assert_eq!(message.data, payload);
+    }
+
+    #[test]
+    fn first_and_consecutive_frames() {
+        let mut tp = isotp("7E0:7E8");
+        let payload: Vec<u8> = (0..20).collect();
+        let mut frames = vec![
+            frame(0x7E8, &[&[0x10, 20][..], &payload[..6]].concat(), ms(0)),
+            // Flow control from the tester
+            frame(0x7E0, &[0x30, 0, 0, 0, 0, 0, 0, 0], ms(1)),
+        ];
+        frames.extend(consecutive(0x7E8, &payload[6..], ms(2)));
+
+        let events = push_all(&mut tp, &frames);
+        let [message] = messages(&events)[..] else {
+            panic!("unexpected {:?}", events);
+        };
+        assert_eq!(message.data, payload);
+        assert_eq!(message.timestamp, ms(3));
+    }
+
+    #[test]
+    fn sequence_number_wraps() {
+        let mut tp = isotp("7E0:7E8");
+        // 17 consecutive frames: sequence numbers 1 to 15, then 0 and 1
+        let payload: Vec<u8> = (0..6 + 17 * 7).collect();
+        let mut frames = vec![frame(
+            0x7E8,
+            &[&[0x10, payload.len() as u8][..], &payload[..6]].concat(),
+            ms(0),
+        )];
+        frames.extend(consecutive(0x7E8, &payload[6..], ms(1)));
+
+        let events = push_all(&mut tp, &frames);
+        assert_eq!(errors(&events), []);
+        assert_eq!(messages(&events)[0].data, payload);
+    }
+
+    #[test]
+    fn first_frame_with_escape_length() {
+        let mut tp = isotp("7E0:7E8");
+        let payload: Vec<u8> = (0..5000).map(|i| i as u8).collect();
+        let mut frames = vec![frame(
+            0x7E8,
+            &[&[0x10, 0x00, 0x00, 0x00, 0x13, 0x88][..], &payload[..2]].concat(),
+            ms(0),
+        )];
+        frames.extend(consecutive(0x7E8, &payload[2..], ms(0)));
+
+        let events = push_all(&mut tp, &frames);
+        assert_eq!(errors(&events), []);
+        assert_eq!(messages(&events)[0].data, payload);
+    }
+
+    #[test]
+    fn extended_addressing() {
+        let mut tp = isotp("6F1:612,extended");
+        let events = push_all(
+            &mut tp,
+            &[
+                frame(0x612, &[0xF1, 0x10, 10, 0, 1, 2, 3, 4], ms(0)),
+                // Another target on the same id is a separate message
+                frame(0x612, &[0x40, 0x02, 0x50, 0x01], ms(1)),
+                frame(0x612, &[0xF1, 0x21, 5, 6, 7, 8, 9], ms(2)),
+            ],
+        );
+
+        let found: Vec<_> = messages(&events)
+            .iter()
+            .map(|m| (m.address, m.data.clone()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (Some(0x40), vec![0x50, 0x01]),
+                (Some(0xF1), (0..10).collect()),
+            ]
+        );
+    }
+
+    #[test]
+    fn errors_are_reported() {
+        let mut tp = isotp("7E0:7E8");
+        let first = |time| frame(0x7E8, &[0x10, 20, 0, 1, 2, 3, 4, 5], time);
+        let events = push_all(
+            &mut tp,
+            &[
+                frame(0x7E8, &[0x21, 0, 0, 0, 0, 0, 0, 0], ms(0)),
+                first(ms(10)),
+                frame(0x7E8, &[0x22, 0, 0, 0, 0, 0, 0, 0], ms(20)),
+                first(ms(30)),
+                first(ms(40)),
+                // The tester can't take it
+                frame(0x7E0, &[0x32, 0, 0, 0, 0, 0, 0, 0], ms(50)),
+                first(ms(60)),
+                frame(0x7E8, &[0x02, 0x50, 0x03], ms(2000)),
+            ],
+        );
+        assert_eq!(
+            errors(&events),
+            [
+                IsoTpError::UnexpectedConsecutive,
+                IsoTpError::BadSequence,
+                IsoTpError::Interrupted,
+                IsoTpError::Overflow,
+                IsoTpError::Timeout,
+            ]
+        );
+        assert_eq!(messages(&events).len(), 1);
+    }
+}
@@ -0,0 +1,13 @@
+//! SAE J1939 higher layers
+
+mod dm;
+mod tp;
+
+pub use dm::{
+    fmi_description, DiagnosticMessage, Diagnostics, Dtc, DtcRecord, LampStatus, Lamps,
+    NodeDiagnostics, DM1, DM2,
+};
+pub use tp::{Failure, TransportEvent, TransportMessage, TransportReassembler};
+
+/// Global destination address, used by broadcast messages
+pub const GLOBAL_ADDRESS: u8 = 0xFF;
@@ -0,0 +1,323 @@
+//! Diagnostic messages (J1939-73): DM1 carries the active trouble codes of a
+//! node, DM2 the previously active ones
+
+use canbusnoop_core::{Frame, J1939Id};
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::time::Duration;
+
+pub const DM1: u32 = 65226;
+pub const DM2: u32 = 65227;
+
+/// State of a lamp, two bits
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LampStatus {
+    #[default]
+    Off,
+    On,
+    Error,
+    NotAvailable,
+}
+
+impl LampStatus {
+    fn from_bits(bits: u8) -> LampStatus {
+        match bits & 0x3 {
+            0 => LampStatus::Off,
+            1 => LampStatus::On,
+            2 => LampStatus::Error,
+            _ => LampStatus::NotAvailable,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Lamps {
+    /// Malfunction indicator lamp, emission related faults
+    pub malfunction: LampStatus,
+    pub red_stop: LampStatus,
+    pub amber_warning: LampStatus,
+    pub protect: LampStatus,
+}
+
+/// Diagnostic trouble code
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Dtc {
+    /// Suspect parameter number, the faulty parameter
+    pub spn: u32,
+    /// Failure mode identifier
+    pub fmi: u8,
+    /// Occurrence count, 127 when not available
+    pub occurrence: u8,
+    /// Conversion method. When set the node uses one of the older SPN
+    /// layouts, which one can't be told from the message: the SPN is still
+    /// decoded with the current layout and may be wrong.
+    pub conversion_method: bool,
+}
+
+impl Dtc {
+    fn from_bytes(b: &[u8]) -> Dtc {
+        Dtc {
+            spn: b[0] as u32 | (b[1] as u32) << 8 | ((b[2] >> 5) as u32) << 16,
+            fmi: b[2] & 0x1F,
+            occurrence: b[3] & 0x7F,
+            conversion_method: b[3] & 0x80 != 0,
+        }
+    }
+}
+
+/// Description of a failure mode identifier
+pub fn fmi_description(fmi: u8) -> &'static str {
+    match fmi {
+        0 => "above normal, most severe",
+        1 => "below normal, most severe",
+        2 => "erratic or intermittent",
+        3 => "voltage above normal",
+        4 => "voltage below normal",
+        5 => "current below normal",
+        6 => "current above normal",
+        7 => "not responding properly",
+        8 => "abnormal frequency",
+        9 => "abnormal update rate",
+        10 => "abnormal rate of change",
+        11 => "root cause not known",
+        12 => "bad device",
+        13 => "out of calibration",
+        14 => "special instructions",
+        15 => "above normal, least severe",
+        16 => "above normal, moderately severe",
+        17 => "below normal, least severe",
+        18 => "below normal, moderately severe",
+        19 => "received network data in error",
+        20 => "drifted high",
+        21 => "drifted low",
+        31 => "condition exists",
+        _ => "reserved",
+    }
+}
+
+/// SPNs decoded from an older layout are marked with a `?`
+impl Display for Dtc {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "SPN {}{} FMI {} ({}) OC {}",
+            self.spn,
+            if self.conversion_method { "?" } else { "" },
+            self.fmi,
+            fmi_description(self.fmi),
+            self.occurrence
+        )
+    }
+}
+
+/// Payload of a DM1 or DM2 message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiagnosticMessage {
+    pub lamps: Lamps,
+    pub dtcs: Vec<Dtc>,
+}
+
+impl DiagnosticMessage {
+    /// Decode the payload. Returns `None` when it is too short.
+    pub fn parse(data: &[u8]) -> Option<DiagnosticMessage> {
+        let lamps = *data.first()?;
+        let lamps = Lamps {
+            malfunction: LampStatus::from_bits(lamps >> 6),
+            red_stop: LampStatus::from_bits(lamps >> 4),
+            amber_warning: LampStatus::from_bits(lamps >> 2),
+            protect: LampStatus::from_bits(lamps),
+        };
+
+        // The second byte is the flash status of the lamps. A single DTC
+        // with all bits clear (or set, as padding) means no DTCs.
+        let dtcs = data
+            .get(2..)?
+            .chunks_exact(4)
+            .filter(|b| b.iter().any(|&x| x != 0) && b.iter().any(|&x| x != 0xFF))
+            .map(Dtc::from_bytes)
+            .collect();
+
+        Some(DiagnosticMessage { lamps, dtcs })
+    }
+}
+
+/// A trouble code reported by a node, with the time it was first and last
+/// reported
+#[derive(Debug, Clone, PartialEq)]
+pub struct DtcRecord {
+    pub dtc: Dtc,
+    pub first_seen: Duration,
+    pub last_seen: Duration,
+}
+
+/// Trouble codes are identified by the SPN and the failure mode
+type DtcKey = (u32, u8);
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct NodeDiagnostics {
+    lamps: Lamps,
+    active: BTreeMap<DtcKey, DtcRecord>,
+    previously_active: BTreeMap<DtcKey, DtcRecord>,
+}
+
+impl NodeDiagnostics {
+    /// Lamp status from the last DM1
+    pub fn lamps(&self) -> Lamps {
+        self.lamps
+    }
+
+    /// Trouble codes in the last DM1
+    pub fn active(&self) -> impl Iterator<Item = &DtcRecord> {
+        self.active.values()
+    }
+
+    /// Trouble codes in the last DM2, or that were in a DM1 and are no
+    /// longer active
+    pub fn previously_active(&self) -> impl Iterator<Item = &DtcRecord> {
+        self.previously_active.values()
+    }
+}
+
+/// Replace the records with the DTCs of a new message, keeping the first
+/// seen time of those already present. Returns the records no longer
+/// reported.
+fn update(
+    records: &mut BTreeMap<DtcKey, DtcRecord>,
+    dtcs: &[Dtc],
+    now: Duration,
+) -> BTreeMap<DtcKey, DtcRecord> {
+    let mut previous = std::mem::take(records);
+    for dtc in dtcs {
+        let key = (dtc.spn, dtc.fmi);
+        let first_seen = previous.remove(&key).map(|r| r.first_seen).unwrap_or(now);
+        let record = DtcRecord {
+            dtc: *dtc,
+            first_seen,
+            last_seen: now,
+        };
+        records.insert(key, record);
+    }
+    previous
+}
+
+/// Diagnostic state of each node, by source address, decoded from the DM1
+/// and DM2 messages. Multi-packet messages must be reassembled before being
+/// pushed.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Diagnostics {
+    nodes: BTreeMap<u8, NodeDiagnostics>,
+}
+
+impl Diagnostics {
+    pub fn push(&mut self, frame: &Frame) {
+        let Some(id) = J1939Id::from_can_id(frame.id()) else {
+            return;
+        };
+        let pgn = id.pgn().value();
+        if pgn != DM1 && pgn != DM2 {
+            return;
+        }
+        let Some(message) = DiagnosticMessage::parse(frame.data()) else {
+            return;
+        };
+
+        let now = frame.timestamp();
+        let node = self.nodes.entry(id.source_address()).or_default();
+        if pgn == DM1 {
+            node.lamps = message.lamps;
+            // DM2 is usually only sent on request, so keep track of the
+            // codes that went inactive
+            let inactive = update(&mut node.active, &message.dtcs, now);
+            node.previously_active.extend(inactive);
+        } else {
+            update(&mut node.previously_active, &message.dtcs, now);
+        }
+    }
+
+    /// Nodes that sent at least one diagnostic message, by source address
+    pub fn iter(&self) -> impl Iterator<Item = (u8, &NodeDiagnostics)> {
+        self.nodes.iter().map(|(sa, node)| (*sa, node))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use canbusnoop_core::CanId;
+
+    fn dm(pgn: u32, source: u8, data: &[u8], secs: u64) -> Frame {
+        let id = (6 << 26) | (pgn << 8) | source as u32;
+        let time = Duration::from_secs(secs);
+        Frame::new(CanId::extended(id).unwrap(), data.to_vec(), time)
+    }
+
+    #[test]
+    fn parses_lamps_and_dtcs() {
+        // SPN 0x5_1234: the 3 high bits are in the top of the third byte
+        let data = [0b01_00_10_11, 0xFF, 0x34, 0x12, 0xA3, 0x85];
+        let message = DiagnosticMessage::parse(&data).unwrap();
+
+        assert_eq!(message.lamps.malfunction, LampStatus::On);
+        assert_eq!(message.lamps.red_stop, LampStatus::Off);
+        assert_eq!(message.lamps.amber_warning, LampStatus::Error);
+        assert_eq!(message.lamps.protect, LampStatus::NotAvailable);
+        assert_eq!(
+            message.dtcs,
+            [Dtc {
+                spn: 0x5_1234,
+                fmi: 3,
+                occurrence: 5,
+                conversion_method: true,
+            }]
+        );
+        assert_eq!(
+            message.dtcs[0].to_string(),
+            "SPN 332340? FMI 3 (voltage above normal) OC 5"
+        );
+    }
+
+    #[test]
+    fn empty_dtc_is_no_dtc() {
+        for padding in [0x00, 0xFF] {
+            let message = DiagnosticMessage::parse(&[0, 0xFF, padding, padding, padding, padding]);
+            assert!(message.unwrap().dtcs.is_empty());
+        }
+        assert!(DiagnosticMessage::parse(&[0]).is_none());
+    }
+
+    #[test]
+    fn inactive_codes_move_to_previously_active() {
+        let spn_100 = [0x64, 0x00, 0x01, 0x01];
+        let spn_110 = [0x6E, 0x00, 0x00, 0x01];
+        let mut diagnostics = Diagnostics::default();
+        diagnostics.push(&dm(
+            DM1,
+            0x00,
+            &[&[0x04, 0xFF][..], &spn_100, &spn_110].concat(),
+            1,
+        ));
+        diagnostics.push(&dm(DM1, 0x00, &[&[0x04, 0xFF][..], &spn_110].concat(), 2));
+
+        let (sa, node) = diagnostics.iter().next().unwrap();
+        assert_eq!(sa, 0x00);
+        assert_eq!(node.lamps().amber_warning, LampStatus::On);
+
+        let active: Vec<_> = node.active().collect();
+        assert_eq!(active.len(), 1);
+        assert_eq!((active[0].dtc.spn, active[0].dtc.fmi), (110, 0));
+        assert_eq!(active[0].first_seen, Duration::from_secs(1));
+        assert_eq!(active[0].last_seen, Duration::from_secs(2));
+
+        let previous: Vec<_> = node.previously_active().collect();
+        assert_eq!(previous.len(), 1);
+        assert_eq!((previous[0].dtc.spn, previous[0].dtc.fmi), (100, 1));
+    }
+}
@@ -0,0 +1,602 @@
+//! Transport protocol (J1939-21), used to send parameter groups longer than
+//! 8 bytes as a sequence of TP.DT frames announced by a TP.CM frame.
+//!
+//! Broadcast (BAM) and connection mode (RTS/CTS) sessions are tracked for
+//! each source and destination pair. Timeouts are measured on the capture
+//! timestamps, so they are detected in replays too.
+
+use super::GLOBAL_ADDRESS;
+use canbusnoop_core::{CanId, Direction, Frame, J1939Id, Pgn};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+const TP_CM: u32 = 60416;
+const TP_DT: u32 = 60160;
+
+const RTS: u8 = 16;
+const CTS: u8 = 17;
+const END_OF_MSG_ACK: u8 = 19;
+const BAM: u8 = 32;
+const ABORT: u8 = 255;
+
+/// Data bytes carried by each TP.DT frame
+const PACKET_SIZE: usize = 7;
+
+/// Maximum time between the packets of a broadcast (T1)
+const BAM_TIMEOUT: Duration = Duration::from_millis(750);
+
+/// Maximum time between the frames of a connection mode session (T2/T3)
+const CMDT_TIMEOUT: Duration = Duration::from_millis(1250);
+
+/// Priority given to reassembled messages, the default of most parameter
+/// groups. It lets them share a row with the same group sent in one frame.
+const MESSAGE_PRIORITY: u32 = 6;
+
+/// A parameter group reassembled from a transport session
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransportMessage {
+    pub pgn: Pgn,
+    pub source: u8,
+    /// `None` for broadcast messages
+    pub destination: Option<u8>,
+    pub data: Vec<u8>,
+    /// Time of the last packet
+    pub timestamp: Duration,
+    interface: Option<Arc<str>>,
+    direction: Direction,
+}
+
+impl TransportMessage {
+    /// The identifier the message would have if it fit in a single frame
+    pub fn id(&self) -> CanId {
+        let pgn = self.pgn.value();
+        let pdu_specific = match (pgn >> 8) & 0xFF {
+            pf if pf < 240 => self.destination.unwrap_or(GLOBAL_ADDRESS) as u32,
+            _ => pgn & 0xFF,
+        };
+        let id = (MESSAGE_PRIORITY << 26)
+            | ((pgn & 0x3_FF00) << 8)
+            | (pdu_specific << 8)
+            | self.source as u32;
+        CanId::extended(id).expect("J1939 ids are 29 bit")
+    }
+
+    /// A virtual frame carrying the whole message, to be handled as if it
+    /// was received in one piece
+    pub fn to_frame(&self) -> Frame {
+        let frame =
+            Frame::new(self.id(), self.data.clone(), self.timestamp).with_direction(self.direction);
+        match &self.interface {
+            Some(interface) => frame.with_interface(interface.clone()),
+            None => frame,
+        }
+    }
+}
+
+/// Why a transport session did not complete
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Failure {
+    /// Connection abort, with the reason sent by the node
+    Aborted(u8),
+    /// No packets for too long
+    Timeout,
+    /// A packet was lost or sent out of order
+    BadSequence,
+    /// A new session was opened by the same nodes before the end
+    Interrupted,
+}
+
+impl Failure {
+    pub fn description(&self) -> &'static str {
+        match self {
+            Failure::Aborted(reason) => abort_reason(*reason),
+            Failure::Timeout => "timeout",
+            Failure::BadSequence => "bad sequence number",
+            Failure::Interrupted => "interrupted by a new session",
+        }
+    }
+}
+
+fn abort_reason(reason: u8) -> &'static str {
+    match reason {
+        1 => "already in a session",
+        2 => "resources needed elsewhere",
+        3 => "timeout",
+        4 => "CTS while transferring",
+        5 => "maximum retransmit reached",
+        6 => "unexpected data packet",
+        7 => "bad sequence number",
+        8 => "duplicate sequence number",
+        9 => "message too large",
+        _ => "aborted",
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TransportEvent {
+    Message(TransportMessage),
+    Failed {
+        pgn: Pgn,
+        source: u8,
+        destination: Option<u8>,
+        failure: Failure,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Session {
+    pgn: Pgn,
+    broadcast: bool,
+    size: usize,
+    data: Vec<u8>,
+    next_sequence: u8,
+    last_time: Duration,
+}
+
+impl Session {
+    fn timeout(&self) -> Duration {
+        if self.broadcast {
+            BAM_TIMEOUT
+        } else {
+            CMDT_TIMEOUT
+        }
+    }
+}
+
+/// Source and destination (the global address for broadcasts) addresses
+type SessionKey = (u8, u8);
+
+/// Reassemble the messages sent with the J1939 transport protocol. Frames
+/// are pushed in capture order, all the other frames are ignored.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct TransportReassembler {
+    sessions: BTreeMap<SessionKey, Session>,
+    completed: usize,
+    aborted: usize,
+    timed_out: usize,
+    bad_sequence: usize,
+}
+
+impl TransportReassembler {
+    pub fn push(&mut self, frame: &Frame) -> Vec<TransportEvent> {
+        let mut events = self.expire(frame.timestamp());
+
+        let Some(id) = J1939Id::from_can_id(frame.id()) else {
+            return events;
+        };
+        let data = frame.data();
+        if frame.id().is_rtr() || data.len() < 8 {
+            return events;
+        }
+
+        let source = id.source_address();
+        let destination = id.pdu_specific();
+        let now = frame.timestamp();
+
+        match id.pgn().value() {
+            TP_CM => self.control(source, destination, data, now, &mut events),
+            TP_DT => {
+                if let Some(message) = self.packet(source, destination, data, now, &mut events) {
+                    let message = TransportMessage {
+                        interface: frame.interface().map(Arc::from),
+                        direction: frame.direction(),
+                        ..message
+                    };
+                    events.push(TransportEvent::Message(message));
+                }
+            }
+            _ => {}
+        }
+
+        events
+    }
+
+    /// Messages reassembled so far
+    pub fn completed(&self) -> usize {
+        self.completed
+    }
+
+    /// Sessions closed by a connection abort, or interrupted by a new session
+    /// between the same nodes
+    pub fn aborted(&self) -> usize {
+        self.aborted
+    }
+
+    pub fn timed_out(&self) -> usize {
+        self.timed_out
+    }
+
+    /// Sessions dropped because of lost or out of order packets
+    pub fn bad_sequence(&self) -> usize {
+        self.bad_sequence
+    }
+
+    /// Sessions waiting for more packets
+    pub fn active(&self) -> usize {
+        self.sessions.len()
+    }
+
+    pub fn clear(&mut self) {
+        *self = TransportReassembler::default();
+    }
+
+    fn control(
+        &mut self,
+        source: u8,
+        destination: u8,
+        data: &[u8],
+        now: Duration,
+        events: &mut Vec<TransportEvent>,
+    ) {
+        let pgn = Pgn::new(u32::from_le_bytes([data[5], data[6], data[7], 0]));
+        let size = u16::from_le_bytes([data[1], data[2]]) as usize;
+
+        match data[0] {
+            BAM | RTS => {
+                let session = Session {
+                    pgn,
+                    broadcast: data[0] == BAM,
+                    size,
+                    data: Vec::with_capacity(size),
+                    next_sequence: 1,
+                    last_time: now,
+                };
+                let key = (source, destination);
+                if let Some(previous) = self.sessions.insert(key, session) {
+                    self.aborted += 1;
+                    events.push(failed(key, previous.pgn, Failure::Interrupted));
+                }
+            }
+            // Sent by the receiver, the session is keyed by the originator
+            CTS => {
+                if let Some(session) = self.sessions.get_mut(&(destination, source)) {
+                    session.last_time = now;
+                    // The receiver may ask to retransmit some packets
+                    let next = data[2].max(1);
+                    if next < session.next_sequence {
+                        session.data.truncate((next as usize - 1) * PACKET_SIZE);
+                        session.next_sequence = next;
+                    }
+                }
+            }
+            END_OF_MSG_ACK => {}
+            ABORT => {
+                // Either side of the connection can abort it
+                let key = [(source, destination), (destination, source)]
+                    .into_iter()
+                    .find(|key| self.sessions.get(key).is_some_and(|s| s.pgn == pgn));
+                if let Some(key) = key {
+                    self.sessions.remove(&key);
+                    self.aborted += 1;
+                    events.push(failed(key, pgn, Failure::Aborted(data[1])));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn packet(
+        &mut self,
+        source: u8,
+        destination: u8,
+        data: &[u8],
+        now: Duration,
+        events: &mut Vec<TransportEvent>,
+    ) -> Option<TransportMessage> {
+        let key = (source, destination);
+        let session = self.sessions.get_mut(&key)?;
+        let sequence = data[0];
+
+        if sequence < session.next_sequence {
+            // Duplicated packet
+            return None;
+        }
+
+        if sequence > session.next_sequence {
+            let pgn = session.pgn;
+            self.sessions.remove(&key);
+            self.bad_sequence += 1;
+            events.push(failed(key, pgn, Failure::BadSequence));
+            return None;
+        }
+
+        session.data.extend_from_slice(&data[1..8]);
+        session.next_sequence = session.next_sequence.wrapping_add(1);
+        session.last_time = now;
+
+        if session.data.len() < session.size {
+            return None;
+        }
+
+        let mut session = self.sessions.remove(&key)?;
+        session.data.truncate(session.size);
+        self.completed += 1;
+
+        Some(TransportMessage {
+            pgn: session.pgn,
+            source,
+            destination: (!session.broadcast).then_some(destination),
+            data: session.data,
+            timestamp: now,
+            interface: None,
+            direction: Direction::Rx,
+        })
+    }
+
+    /// Drop the sessions that had no traffic for too long
+    fn expire(&mut self, now: Duration) -> Vec<TransportEvent> {
+        let expired: Vec<_> = self
+            .sessions
+            .iter()
+            .filter(|(_, s)| now.saturating_sub(s.last_time) > s.timeout())
+            .map(|(key, s)| (*key, s.pgn))
+            .collect();
+
+        expired
+            .into_iter()
+            .map(|(key, pgn)| {
+                self.sessions.remove(&key);
+                self.timed_out += 1;
+                failed(key, pgn, Failure::Timeout)
+            })
+            .collect()
+    }
+}
+
+fn failed((source, destination): SessionKey, pgn: Pgn, failure: Failure) -> TransportEvent {
+    TransportEvent::Failed {
+        pgn,
+        source,
+        destination: (destination != GLOBAL_ADDRESS).then_some(destination),
+        failure,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DM1: u32 = 65226;
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    fn frame(pgn: u32, source: u8, destination: u8, data: [u8; 8], time: Duration) -> Frame {
+        let id = (6 << 26) | (pgn << 8) | ((destination as u32) << 8) | source as u32;
+        Frame::new(CanId::extended(id).unwrap(), data.to_vec(), time)
+    }
+
+    fn cm(source: u8, destination: u8, control: [u8; 5], pgn: u32, time: Duration) -> Frame {
+        let [a, b, c, d, e] = control;
+        let [p0, p1, p2, _] = pgn.to_le_bytes();
+        frame(
+            TP_CM,
+            source,
+            destination,
+            [a, b, c, d, e, p0, p1, p2],
+            time,
+        )
+    }
+
+    fn bam(source: u8, size: u16, time: Duration) -> Frame {
+        let [lo, hi] = size.to_le_bytes();
+        let packets = (size as usize).div_ceil(PACKET_SIZE) as u8;
+        cm(
+            source,
+            GLOBAL_ADDRESS,
+            [BAM, lo, hi, packets, 0xFF],
+            DM1,
+            time,
+        )
+    }
+
+    fn rts(source: u8, destination: u8, size: u16, time: Duration) -> Frame {
+        let [lo, hi] = size.to_le_bytes();
+        let packets = (size as usize).div_ceil(PACKET_SIZE) as u8;
+        cm(source, destination, [RTS, lo, hi, packets, 0xFF], DM1, time)
+    }
+
+    fn cts(source: u8, destination: u8, packets: u8, next: u8, time: Duration) -> Frame {
+        cm(
+            source,
+            destination,
+            [CTS, packets, next, 0xFF, 0xFF],
+            DM1,
+            time,
+        )
+    }
+
+    fn abort(source: u8, destination: u8, reason: u8, time: Duration) -> Frame {
+        cm(
+            source,
+            destination,
+            [ABORT, reason, 0xFF, 0xFF, 0xFF],
+            DM1,
+            time,
+        )
+    }
+
+    /// Packet `sequence`, carrying bytes numbered from `(sequence - 1) * 7`
+    fn dt(source: u8, destination: u8, sequence: u8, time: Duration) -> Frame {
+        let mut data = [sequence; 8];
+        for (i, b) in data[1..].iter_mut().enumerate() {
+            *b = (sequence as usize - 1) as u8 * PACKET_SIZE as u8 + i as u8;
+        }
+        frame(TP_DT, source, destination, data, time)
+    }
+
+    fn push_all(tp: &mut TransportReassembler, frames: &[Frame]) -> Vec<TransportEvent> {
+        frames.iter().flat_map(|f| tp.push(f)).collect()
+    }
+
+    fn failures(events: &[TransportEvent]) -> Vec<Failure> {
+        events
+            .iter()
+            .filter_map(|e| match e {
+                TransportEvent::Failed { failure, .. } => Some(*failure),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn reassembles_broadcast() {
+        let mut tp = TransportReassembler::default();
+        let events = push_all(
+            &mut tp,
+            &[
+                bam(0x00, 10, ms(0)),
+                dt(0x00, GLOBAL_ADDRESS, 1, ms(50)),
+                dt(0x00, GLOBAL_ADDRESS, 2, ms(100)),
+            ],
+        );
+
+        let [TransportEvent::Message(message)] = &events[..] else {
+            panic!("unexpected {:?}", events);
+        };
+        assert_eq!(message.pgn, Pgn::new(DM1));
+        assert_eq!(message.source, 0x00);
+        assert_eq!(message.destination, None);
+        assert_eq!(message.data, (0..10).collect::<Vec<u8>>());
+        assert_eq!(message.timestamp, ms(100));
+        assert_eq!(message.id(), CanId::extended(0x18FE_CA00).unwrap());
+        assert_eq!((tp.completed(), tp.active()), (1, 0));
+    }
+
+    #[test]
+    fn reassembles_connection_with_retransmission() {
+        let mut tp = TransportReassembler::default();
+        let events = push_all(
+            &mut tp,
+            &[
+                rts(0x00, 0xF9, 20, ms(0)),
+                cts(0xF9, 0x00, 2, 1, ms(10)),
+                dt(0x00, 0xF9, 1, ms(20)),
+                dt(0x00, 0xF9, 2, ms(30)),
+                // The receiver asks for packet 2 again
+                cts(0xF9, 0x00, 2, 2, ms(40)),
+                dt(0x00, 0xF9, 2, ms(50)),
+                dt(0x00, 0xF9, 3, ms(60)),
+            ],
+        );
+
+        let [TransportEvent::Message(message)] = &events[..] else {
+            panic!("unexpected {:?}", events);
+        };
+        assert_eq!(message.destination, Some(0xF9));
+        assert_eq!(message.data, (0..20).collect::<Vec<u8>>());
+        assert_eq!(message.id(), CanId::extended(0x18FE_CA00).unwrap());
+        assert_eq!(tp.completed(), 1);
+    }
+
+    #[test]
+    fn duplicated_packets_are_ignored() {
+        let mut tp = TransportReassembler::default();
+        let events = push_all(
+            &mut tp,
+            &[
+                bam(0x00, 10, ms(0)),
+                dt(0x00, GLOBAL_ADDRESS, 1, ms(50)),
+                dt(0x00, GLOBAL_ADDRESS, 1, ms(60)),
+                dt(0x00, GLOBAL_ADDRESS, 2, ms(100)),
+            ],
+        );
+        assert!(matches!(&events[..], [TransportEvent::Message(m)] if m.data.len() == 10));
+    }
+
+    #[test]
+    fn aborted_by_either_side() {
+        let mut tp = TransportReassembler::default();
+        let events = push_all(
+            &mut tp,
+            &[
+                rts(0x00, 0xF9, 20, ms(0)),
+                abort(0x00, 0xF9, 2, ms(10)),
+                rts(0x00, 0xF9, 20, ms(20)),
+                abort(0xF9, 0x00, 9, ms(30)),
+            ],
+        );
+
+        assert_eq!(
+            failures(&events),
+            [Failure::Aborted(2), Failure::Aborted(9)]
+        );
+        assert!(events.iter().all(|e| matches!(
+            e,
+            TransportEvent::Failed {
+                source: 0x00,
+                destination: Some(0xF9),
+                ..
+            }
+        )));
+        assert_eq!((tp.aborted(), tp.active()), (2, 0));
+    }
+
+    #[test]
+    fn abort_of_another_group_is_ignored() {
+        let mut tp = TransportReassembler::default();
+        let other = cm(0xF9, 0x00, [ABORT, 1, 0xFF, 0xFF, 0xFF], 65260, ms(10));
+        let events = push_all(&mut tp, &[rts(0x00, 0xF9, 20, ms(0)), other]);
+        assert!(events.is_empty());
+        assert_eq!(tp.active(), 1);
+    }
+
+    #[test]
+    fn times_out() {
+        let mut tp = TransportReassembler::default();
+        let events = push_all(
+            &mut tp,
+            &[
+                bam(0x00, 10, ms(0)),
+                dt(0x00, GLOBAL_ADDRESS, 1, ms(700)),
+                rts(0x01, 0xF9, 20, ms(700)),
+                // 750 ms after the last broadcast packet, not the announce
+                dt(0x00, GLOBAL_ADDRESS, 2, ms(1451)),
+            ],
+        );
+        assert_eq!(failures(&events), [Failure::Timeout]);
+        assert_eq!(tp.active(), 1);
+
+        // Connection mode sessions wait longer
+        assert!(tp.push(&frame(0, 0x02, 0x03, [0; 8], ms(1950))).is_empty());
+        let events = tp.push(&frame(0, 0x02, 0x03, [0; 8], ms(1951)));
+        assert_eq!(failures(&events), [Failure::Timeout]);
+        assert_eq!((tp.timed_out(), tp.active()), (2, 0));
+    }
+
+    #[test]
+    fn bad_sequence() {
+        let mut tp = TransportReassembler::default();
+        let events = push_all(
+            &mut tp,
+            &[
+                bam(0x00, 20, ms(0)),
+                dt(0x00, GLOBAL_ADDRESS, 1, ms(50)),
+                dt(0x00, GLOBAL_ADDRESS, 3, ms(100)),
+                dt(0x00, GLOBAL_ADDRESS, 2, ms(150)),
+            ],
+        );
+        assert_eq!(failures(&events), [Failure::BadSequence]);
+        assert_eq!((tp.bad_sequence(), tp.completed(), tp.active()), (1, 0, 0));
+    }
+
+    #[test]
+    fn new_session_interrupts_the_open_one() {
+        let mut tp = TransportReassembler::default();
+        let events = push_all(
+            &mut tp,
+            &[
+                bam(0x00, 20, ms(0)),
+                dt(0x00, GLOBAL_ADDRESS, 1, ms(50)),
+                bam(0x00, 10, ms(100)),
+                dt(0x00, GLOBAL_ADDRESS, 1, ms(150)),
+                dt(0x00, GLOBAL_ADDRESS, 2, ms(200)),
+            ],
+        );
+        assert_eq!(failures(&events), [Failure::Interrupted]);
+        assert!(matches!(events.last(), Some(TransportEvent::Message(m)) if m.data.len() == 10));
+        assert_eq!((tp.aborted(), tp.completed()), (1, 1));
+    }
+}
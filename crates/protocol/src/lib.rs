@@ -0,0 +1,7 @@
+//! Protocols carried over CAN, decoded from the captured frames
+
+pub mod canopen;
+pub mod isotp;
+pub mod j1939;
+pub mod nmea2000;
+pub mod uds;
@@ -0,0 +1,205 @@
+//! NMEA 2000: marine parameter groups on J1939 framing. Messages longer
+//! than 8 bytes are sent as fast packets, see [`FastPacketReassembler`].
+
+mod fast_packet;
+mod pgns;
+
+pub use fast_packet::FastPacketReassembler;
+
+use canbusnoop_core::{CanId, J1939Id};
+use std::fmt::Display;
+
+/// How the bits of a field are interpreted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldKind {
+    Unsigned,
+    Signed,
+    /// Text padded with `@`, spaces or 0xFF
+    Ascii,
+    /// Not shown
+    Reserved,
+}
+
+/// A field of a parameter group. Fields are packed in order, little endian.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Field {
+    pub name: &'static str,
+    pub bits: usize,
+    pub kind: FieldKind,
+    /// Physical value = raw * resolution + offset
+    pub resolution: f64,
+    pub offset: f64,
+    pub unit: &'static str,
+}
+
+/// A parameter group known to the database
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PgnInfo {
+    pub pgn: u32,
+    pub name: &'static str,
+    /// Sent as a fast packet, even when it fits in 8 bytes
+    pub fast_packet: bool,
+    pub fields: &'static [Field],
+}
+
+/// Look up a parameter group in the database
+pub fn lookup(pgn: u32) -> Option<&'static PgnInfo> {
+    pgns::PGNS.iter().find(|info| info.pgn == pgn)
+}
+
+/// The parameter group of an identifier, if it is in the database
+pub fn lookup_id(id: CanId) -> Option<&'static PgnInfo> {
+    lookup(J1939Id::from_can_id(id)?.pgn().value())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldValue {
+    Number(f64),
+    Text(String),
+    /// All bits set, the sender has no value
+    NotAvailable,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedField {
+    pub field: &'static Field,
+    pub value: FieldValue,
+}
+
+impl Display for DecodedField {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.value {
+            FieldValue::Number(value) => {
+                // Enough decimals to show the resolution
+                let decimals = (-self.field.resolution.log10()).ceil().clamp(0., 9.) as usize;
+                write!(f, "{:.*}", decimals, value)?;
+                if !self.field.unit.is_empty() {
+                    write!(f, " {}", self.field.unit)?;
+                }
+                Ok(())
+            }
+            FieldValue::Text(text) => write!(f, "{}", text),
+            FieldValue::NotAvailable => write!(f, "n/a"),
+        }
+    }
+}
+
+impl PgnInfo {
+    /// Decode the fields found in `data`. Reserved fields and the fields
+    /// beyond the end of the payload are omitted.
+    pub fn decode(&'static self, data: &[u8]) -> Vec<DecodedField> {
+        let mut decoded = Vec::new();
+        let mut start = 0;
+
+        for field in self.fields {
+            let end = start + field.bits;
+            if end > data.len() * 8 {
+                break;
+            }
+
+            let value = match field.kind {
+                FieldKind::Reserved => None,
+                FieldKind::Ascii => Some(ascii(&data[start / 8..end / 8])),
+                FieldKind::Unsigned => {
+                    let raw = bits(data, start, field.bits);
+                    Some(match raw == max_unsigned(field.bits) && field.bits > 1 {
+                        true => FieldValue::NotAvailable,
+                        false => FieldValue::Number(raw as f64 * field.resolution + field.offset),
+                    })
+                }
+                FieldKind::Signed => {
+                    let raw = bits(data, start, field.bits);
+                    Some(match raw == max_unsigned(field.bits - 1) {
+                        true => FieldValue::NotAvailable,
+                        false => {
+                            let raw = sign_extend(raw, field.bits);
+                            FieldValue::Number(raw as f64 * field.resolution + field.offset)
+                        }
+                    })
+                }
+            };
+
+            if let Some(value) = value {
+                decoded.push(DecodedField { field, value });
+            }
+            start = end;
+        }
+
+        decoded
+    }
+}
+
+/// `len` bits starting at bit `start`, least significant bit first
+fn bits(data: &[u8], start: usize, len: usize) -> u64 {
+    (0..len).fold(0, |acc, i| {
+        let bit = start + i;
+        let set = (data[bit / 8] >> (bit % 8)) & 1;
+        acc | (set as u64) << i
+    })
+}
+
+fn max_unsigned(bits: usize) -> u64 {
+    match bits {
+        64 => u64::MAX,
+        bits => (1 << bits) - 1,
+    }
+}
+
+fn sign_extend(raw: u64, bits: usize) -> i64 {
+    let shift = 64 - bits;
+    ((raw << shift) as i64) >> shift
+}
+
+fn ascii(bytes: &[u8]) -> FieldValue {
+    let text: String = bytes
+        .iter()
+        .take_while(|&&b| b != 0 && b != 0xFF)
+        .map(|&b| b as char)
+        .collect();
+    FieldValue::Text(text.trim_end_matches(['@', ' ']).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bits_are_little_endian() {
+        let data = [0x34, 0x12, 0xF0];
+        assert_eq!(bits(&data, 0, 16), 0x1234);
+        assert_eq!(bits(&data, 4, 8), 0x23);
+        assert_eq!(bits(&data, 20, 4), 0xF);
+        assert_eq!(bits(&[0xFF; 8], 0, 64), u64::MAX);
+    }
+
+    #[test]
+    fn sign_extension() {
+        assert_eq!(sign_extend(0x7F, 8), 127);
+        assert_eq!(sign_extend(0x80, 8), -128);
+        assert_eq!(sign_extend(0xFFFF, 16), -1);
+        assert_eq!(sign_extend(0x8000_0000, 32), i32::MIN as i64);
+    }
+
+    #[test]
+    fn decodes_fields() {
+        // Vessel Heading: SID, heading 1.5708 rad, deviation n/a,
+        // variation -0.1 rad, reference
+        let info = lookup(127250).unwrap();
+        let data = [0x01, 0x5C, 0x3D, 0xFF, 0x7F, 0x18, 0xFC, 0xFD];
+        let decoded: Vec<_> = info
+            .decode(&data)
+            .iter()
+            .map(|d| (d.field.name, d.to_string()))
+            .collect();
+        assert_eq!(
+            decoded,
+            [
+                ("SID", "1".to_string()),
+                ("Heading", "90.000 deg".to_string()),
+                ("Deviation", "n/a".to_string()),
+                ("Variation", "-5.730 deg".to_string()),
+                ("Reference", "1".to_string()),
+            ]
+        );
+    }
+}
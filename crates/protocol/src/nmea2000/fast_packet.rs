@@ -0,0 +1,200 @@
+use super::lookup;
+use canbusnoop_core::{Frame, J1939Id};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Maximum time between the frames of a message
+const TIMEOUT: Duration = Duration::from_millis(750);
+
+/// Data bytes in the first frame, after the counters and the length
+const FIRST_FRAME_DATA: usize = 6;
+
+#[derive(Debug, Clone, PartialEq)]
+struct Session {
+    /// Sequence counter, 3 bits, tells consecutive messages apart
+    sequence: u8,
+    size: usize,
+    data: Vec<u8>,
+    /// Frame counter expected next, 5 bits
+    next_frame: u8,
+    last_time: Duration,
+}
+
+/// Reassemble NMEA 2000 fast packets. Each frame starts with a sequence
+/// counter (3 bits) and a frame counter (5 bits), the first frame of a
+/// message also has the total length.
+///
+/// Only the parameter groups known to be sent as fast packets are
+/// reassembled, the protocol can't be told from the frames.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct FastPacketReassembler {
+    /// By source address and PGN
+    sessions: BTreeMap<(u8, u32), Session>,
+    completed: usize,
+    dropped: usize,
+}
+
+impl FastPacketReassembler {
+    /// Returns true if `frame` is part of a fast packet
+    pub fn is_fast_packet(frame: &Frame) -> bool {
+        let Some(id) = J1939Id::from_can_id(frame.id()) else {
+            return false;
+        };
+        lookup(id.pgn().value()).is_some_and(|info| info.fast_packet)
+    }
+
+    /// Push a frame of a fast packet. Returns the whole message, as a frame
+    /// with the same identifier, when its last frame is received.
+    pub fn push(&mut self, frame: &Frame) -> Option<Frame> {
+        let id = J1939Id::from_can_id(frame.id())?;
+        let data = frame.data();
+        let now = frame.timestamp();
+        let (&counters, rest) = data.split_first()?;
+        let sequence = counters >> 5;
+        let counter = counters & 0x1F;
+        let key = (id.source_address(), id.pgn().value());
+
+        if self
+            .sessions
+            .get(&key)
+            .is_some_and(|s| now.saturating_sub(s.last_time) > TIMEOUT)
+        {
+            self.sessions.remove(&key);
+            self.dropped += 1;
+        }
+
+        if counter == 0 {
+            let (&size, rest) = rest.split_first()?;
+            if self.sessions.remove(&key).is_some() {
+                self.dropped += 1;
+            }
+            let session = Session {
+                sequence,
+                size: size as usize,
+                data: rest.iter().take(FIRST_FRAME_DATA).copied().collect(),
+                next_frame: 1,
+                last_time: now,
+            };
+            self.sessions.insert(key, session);
+        } else {
+            let session = self.sessions.get_mut(&key)?;
+            if session.sequence != sequence || session.next_frame != counter {
+                self.sessions.remove(&key);
+                self.dropped += 1;
+                return None;
+            }
+            session.data.extend_from_slice(rest);
+            session.next_frame = (session.next_frame + 1) & 0x1F;
+            session.last_time = now;
+        }
+
+        let session = self.sessions.get(&key)?;
+        if session.data.len() < session.size {
+            return None;
+        }
+
+        let mut session = self.sessions.remove(&key)?;
+        session.data.truncate(session.size);
+        self.completed += 1;
+
+        let message = Frame::new(frame.id(), session.data, now).with_direction(frame.direction());
+        Some(match frame.interface() {
+            Some(interface) => message.with_interface(Arc::from(interface)),
+            None => message,
+        })
+    }
+
+    /// Messages reassembled so far
+    pub fn completed(&self) -> usize {
+        self.completed
+    }
+
+    /// Messages dropped because of lost frames or timeouts
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+
+    pub fn clear(&mut self) {
+        *self = FastPacketReassembler::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use canbusnoop_core::CanId;
+
+    /// Engine Parameters, Dynamic from address 0x10, a fast packet
+    const ID: u32 = 0x09F2_0110;
+
+    fn frame(data: &[u8], ms: u64) -> Frame {
+        Frame::new(
+            CanId::extended(ID).unwrap(),
+            data.to_vec(),
+            Duration::from_millis(ms),
+        )
+    }
+
+    /// The frames of a message with sequence counter `sequence`
+    fn frames(sequence: u8, payload: &[u8], start: u64) -> Vec<Frame> {
+        let mut data = vec![sequence << 5, payload.len() as u8];
+        data.extend(payload.iter().take(FIRST_FRAME_DATA));
+        let mut frames = vec![frame(&data, start)];
+        for (i, chunk) in payload[FIRST_FRAME_DATA..].chunks(7).enumerate() {
+            let counters = sequence << 5 | (i + 1) as u8;
+            let mut data = [0xFF; 8];
+            data[0] = counters;
+            data[1..=chunk.len()].copy_from_slice(chunk);
+            frames.push(frame(&data, start + i as u64 + 1));
+        }
+        frames
+    }
+
+    #[test]
+    fn reassembles_messages() {
+        let payload: Vec<u8> = (0..26).collect();
+        let frames = frames(2, &payload, 0);
+        assert!(FastPacketReassembler::is_fast_packet(&frames[0]));
+
+        let mut reassembler = FastPacketReassembler::default();
+        let messages: Vec<_> = frames.iter().filter_map(|f| reassembler.push(f)).collect();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].id(), frames[0].id());
+        assert_eq!(messages[0].data(), payload);
+        assert_eq!(messages[0].timestamp(), Duration::from_millis(3));
+        assert_eq!(reassembler.completed(), 1);
+        assert_eq!(reassembler.dropped(), 0);
+    }
+
+    #[test]
+    fn drops_lost_frames() {
+        let payload: Vec<u8> = (0..26).collect();
+        let mut reassembler = FastPacketReassembler::default();
+
+        // Frame 2 is lost
+        let mut lost = frames(0, &payload, 0);
+        lost.remove(2);
+        assert!(lost.iter().all(|f| reassembler.push(f).is_none()));
+
+        // Frames of another sequence
+        let mut mixed = frames(1, &payload, 10);
+        mixed[2] = frames(2, &payload, 10)[2].clone();
+        assert!(mixed.iter().all(|f| reassembler.push(f).is_none()));
+        assert_eq!(reassembler.dropped(), 2);
+        assert_eq!(reassembler.completed(), 0);
+    }
+
+    #[test]
+    fn drops_timed_out_messages() {
+        let payload: Vec<u8> = (0..26).collect();
+        let mut frames = frames(0, &payload, 0);
+        let last = frames.pop().unwrap();
+        let late = frame(last.data(), 1000);
+
+        let mut reassembler = FastPacketReassembler::default();
+        assert!(frames.iter().all(|f| reassembler.push(f).is_none()));
+        assert!(reassembler.push(&late).is_none());
+        assert_eq!(reassembler.dropped(), 1);
+    }
+}
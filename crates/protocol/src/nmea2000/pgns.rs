@@ -0,0 +1,366 @@
+//! The parameter groups known to the decoder. Angles are converted from
+//! radians to degrees and temperatures from kelvin to degrees Celsius.
+
+use super::{Field, FieldKind, PgnInfo};
+
+const RAD_TO_DEG: f64 = 180. / std::f64::consts::PI;
+const KELVIN: f64 = -273.15;
+
+const fn field(
+    name: &'static str,
+    bits: usize,
+    kind: FieldKind,
+    resolution: f64,
+    unit: &'static str,
+) -> Field {
+    Field {
+        name,
+        bits,
+        kind,
+        resolution,
+        offset: 0.,
+        unit,
+    }
+}
+
+const fn uint(name: &'static str, bits: usize, resolution: f64, unit: &'static str) -> Field {
+    field(name, bits, FieldKind::Unsigned, resolution, unit)
+}
+
+const fn int(name: &'static str, bits: usize, resolution: f64, unit: &'static str) -> Field {
+    field(name, bits, FieldKind::Signed, resolution, unit)
+}
+
+const fn reserved(bits: usize) -> Field {
+    field("", bits, FieldKind::Reserved, 1., "")
+}
+
+const fn ascii(name: &'static str, bytes: usize) -> Field {
+    field(name, bytes * 8, FieldKind::Ascii, 1., "")
+}
+
+const fn sid() -> Field {
+    uint("SID", 8, 1., "")
+}
+
+/// Unsigned angle, 0.0001 rad
+const fn angle(name: &'static str) -> Field {
+    uint(name, 16, 0.0001 * RAD_TO_DEG, "deg")
+}
+
+/// Signed angle, 0.0001 rad
+const fn signed_angle(name: &'static str) -> Field {
+    int(name, 16, 0.0001 * RAD_TO_DEG, "deg")
+}
+
+/// Temperature, 0.01 K
+const fn temperature(name: &'static str) -> Field {
+    Field {
+        offset: KELVIN,
+        ..uint(name, 16, 0.01, "°C")
+    }
+}
+
+/// Latitude or longitude, 1e-7 deg
+const fn coordinate(name: &'static str) -> Field {
+    int(name, 32, 1e-7, "deg")
+}
+
+const fn mmsi() -> Field {
+    uint("MMSI", 32, 1., "")
+}
+
+pub(super) static PGNS: &[PgnInfo] = &[
+    PgnInfo {
+        pgn: 126992,
+        name: "System Time",
+        fast_packet: false,
+        fields: &[
+            sid(),
+            uint("Source", 4, 1., ""),
+            reserved(4),
+            uint("Date", 16, 1., "days"),
+            uint("Time", 32, 0.0001, "s"),
+        ],
+    },
+    PgnInfo {
+        pgn: 126996,
+        name: "Product Information",
+        fast_packet: true,
+        fields: &[
+            uint("NMEA 2000 version", 16, 0.001, ""),
+            uint("Product code", 16, 1., ""),
+            ascii("Model ID", 32),
+            ascii("Software version", 32),
+            ascii("Model version", 32),
+            ascii("Serial code", 32),
+        ],
+    },
+    PgnInfo {
+        pgn: 127245,
+        name: "Rudder",
+        fast_packet: false,
+        fields: &[
+            uint("Instance", 8, 1., ""),
+            uint("Direction order", 3, 1., ""),
+            reserved(5),
+            signed_angle("Angle order"),
+            signed_angle("Position"),
+        ],
+    },
+    PgnInfo {
+        pgn: 127250,
+        name: "Vessel Heading",
+        fast_packet: false,
+        fields: &[
+            sid(),
+            angle("Heading"),
+            signed_angle("Deviation"),
+            signed_angle("Variation"),
+            uint("Reference", 2, 1., ""),
+        ],
+    },
+    PgnInfo {
+        pgn: 127257,
+        name: "Attitude",
+        fast_packet: false,
+        fields: &[
+            sid(),
+            signed_angle("Yaw"),
+            signed_angle("Pitch"),
+            signed_angle("Roll"),
+        ],
+    },
+    PgnInfo {
+        pgn: 127488,
+        name: "Engine Parameters, Rapid Update",
+        fast_packet: false,
+        fields: &[
+            uint("Instance", 8, 1., ""),
+            uint("Speed", 16, 0.25, "rpm"),
+            uint("Boost pressure", 16, 100., "Pa"),
+            int("Tilt/trim", 8, 1., "%"),
+        ],
+    },
+    PgnInfo {
+        pgn: 127489,
+        name: "Engine Parameters, Dynamic",
+        fast_packet: true,
+        fields: &[
+            uint("Instance", 8, 1., ""),
+            uint("Oil pressure", 16, 100., "Pa"),
+            Field {
+                offset: KELVIN,
+                ..uint("Oil temperature", 16, 0.1, "°C")
+            },
+            temperature("Temperature"),
+            int("Alternator potential", 16, 0.01, "V"),
+            int("Fuel rate", 16, 0.1, "L/h"),
+            uint("Total engine hours", 32, 1., "s"),
+            uint("Coolant pressure", 16, 100., "Pa"),
+            uint("Fuel pressure", 16, 1000., "Pa"),
+            reserved(8),
+            uint("Discrete status 1", 16, 1., ""),
+            uint("Discrete status 2", 16, 1., ""),
+            int("Engine load", 8, 1., "%"),
+            int("Engine torque", 8, 1., "%"),
+        ],
+    },
+    PgnInfo {
+        pgn: 127505,
+        name: "Fluid Level",
+        fast_packet: false,
+        fields: &[
+            uint("Instance", 4, 1., ""),
+            uint("Type", 4, 1., ""),
+            int("Level", 16, 0.004, "%"),
+            uint("Capacity", 32, 0.1, "L"),
+        ],
+    },
+    PgnInfo {
+        pgn: 127508,
+        name: "Battery Status",
+        fast_packet: false,
+        fields: &[
+            uint("Instance", 8, 1., ""),
+            int("Voltage", 16, 0.01, "V"),
+            int("Current", 16, 0.1, "A"),
+            temperature("Temperature"),
+            sid(),
+        ],
+    },
+    PgnInfo {
+        pgn: 128259,
+        name: "Speed",
+        fast_packet: false,
+        fields: &[
+            sid(),
+            uint("Speed water referenced", 16, 0.01, "m/s"),
+            uint("Speed ground referenced", 16, 0.01, "m/s"),
+            uint("Speed water referenced type", 8, 1., ""),
+        ],
+    },
+    PgnInfo {
+        pgn: 128267,
+        name: "Water Depth",
+        fast_packet: false,
+        fields: &[
+            sid(),
+            uint("Depth", 32, 0.01, "m"),
+            int("Offset", 16, 0.001, "m"),
+            uint("Range", 8, 10., "m"),
+        ],
+    },
+    PgnInfo {
+        pgn: 129025,
+        name: "Position, Rapid Update",
+        fast_packet: false,
+        fields: &[coordinate("Latitude"), coordinate("Longitude")],
+    },
+    PgnInfo {
+        pgn: 129026,
+        name: "COG & SOG, Rapid Update",
+        fast_packet: false,
+        fields: &[
+            sid(),
+            uint("COG reference", 2, 1., ""),
+            reserved(6),
+            angle("COG"),
+            uint("SOG", 16, 0.01, "m/s"),
+        ],
+    },
+    PgnInfo {
+        pgn: 129029,
+        name: "GNSS Position Data",
+        fast_packet: true,
+        fields: &[
+            sid(),
+            uint("Date", 16, 1., "days"),
+            uint("Time", 32, 0.0001, "s"),
+            int("Latitude", 64, 1e-16, "deg"),
+            int("Longitude", 64, 1e-16, "deg"),
+            int("Altitude", 64, 1e-6, "m"),
+            uint("GNSS type", 4, 1., ""),
+            uint("Method", 4, 1., ""),
+            uint("Integrity", 2, 1., ""),
+            reserved(6),
+            uint("Satellites", 8, 1., ""),
+            int("HDOP", 16, 0.01, ""),
+            int("PDOP", 16, 0.01, ""),
+            int("Geoidal separation", 32, 0.01, "m"),
+        ],
+    },
+    PgnInfo {
+        pgn: 129038,
+        name: "AIS Class A Position Report",
+        fast_packet: true,
+        fields: &[
+            uint("Message ID", 6, 1., ""),
+            uint("Repeat indicator", 2, 1., ""),
+            mmsi(),
+            coordinate("Longitude"),
+            coordinate("Latitude"),
+            uint("Position accuracy", 1, 1., ""),
+            uint("RAIM", 1, 1., ""),
+            uint("Time stamp", 6, 1., "s"),
+            angle("COG"),
+            uint("SOG", 16, 0.01, "m/s"),
+            uint("Communication state", 19, 1., ""),
+            uint("AIS transceiver", 5, 1., ""),
+            angle("Heading"),
+            int("Rate of turn", 16, 3.125e-5 * RAD_TO_DEG, "deg/s"),
+            uint("Navigation status", 4, 1., ""),
+        ],
+    },
+    PgnInfo {
+        pgn: 129039,
+        name: "AIS Class B Position Report",
+        fast_packet: true,
+        fields: &[
+            uint("Message ID", 6, 1., ""),
+            uint("Repeat indicator", 2, 1., ""),
+            mmsi(),
+            coordinate("Longitude"),
+            coordinate("Latitude"),
+            uint("Position accuracy", 1, 1., ""),
+            uint("RAIM", 1, 1., ""),
+            uint("Time stamp", 6, 1., "s"),
+            angle("COG"),
+            uint("SOG", 16, 0.01, "m/s"),
+            uint("Communication state", 19, 1., ""),
+            uint("AIS transceiver", 5, 1., ""),
+            angle("Heading"),
+        ],
+    },
+    PgnInfo {
+        pgn: 129794,
+        name: "AIS Class A Static and Voyage Related Data",
+        fast_packet: true,
+        fields: &[
+            uint("Message ID", 6, 1., ""),
+            uint("Repeat indicator", 2, 1., ""),
+            mmsi(),
+            uint("IMO number", 32, 1., ""),
+            ascii("Callsign", 7),
+            ascii("Name", 20),
+            uint("Type of ship", 8, 1., ""),
+            uint("Length", 16, 0.1, "m"),
+            uint("Beam", 16, 0.1, "m"),
+            uint("Position reference from starboard", 16, 0.1, "m"),
+            uint("Position reference from bow", 16, 0.1, "m"),
+            uint("ETA date", 16, 1., "days"),
+            uint("ETA time", 32, 0.0001, "s"),
+            uint("Draft", 16, 0.01, "m"),
+            ascii("Destination", 20),
+        ],
+    },
+    PgnInfo {
+        pgn: 129809,
+        name: "AIS Class B Static Data, Part A",
+        fast_packet: true,
+        fields: &[
+            uint("Message ID", 6, 1., ""),
+            uint("Repeat indicator", 2, 1., ""),
+            mmsi(),
+            ascii("Name", 20),
+        ],
+    },
+    PgnInfo {
+        pgn: 129810,
+        name: "AIS Class B Static Data, Part B",
+        fast_packet: true,
+        fields: &[
+            uint("Message ID", 6, 1., ""),
+            uint("Repeat indicator", 2, 1., ""),
+            mmsi(),
+            uint("Type of ship", 8, 1., ""),
+            ascii("Vendor ID", 7),
+            ascii("Callsign", 7),
+            uint("Length", 16, 0.1, "m"),
+            uint("Beam", 16, 0.1, "m"),
+        ],
+    },
+    PgnInfo {
+        pgn: 130306,
+        name: "Wind Data",
+        fast_packet: false,
+        fields: &[
+            sid(),
+            uint("Wind speed", 16, 0.01, "m/s"),
+            angle("Wind angle"),
+            uint("Reference", 3, 1., ""),
+        ],
+    },
+    PgnInfo {
+        pgn: 130312,
+        name: "Temperature",
+        fast_packet: false,
+        fields: &[
+            sid(),
+            uint("Instance", 8, 1., ""),
+            uint("Source", 8, 1., ""),
+            temperature("Actual temperature"),
+            temperature("Set temperature"),
+        ],
+    },
+];
@@ -0,0 +1,403 @@
+//! Unified diagnostic services (ISO 14229), carried by ISO-TP
+
+use crate::isotp::{IsoTpDirection, IsoTpMessage};
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+const NEGATIVE_RESPONSE: u8 = 0x7F;
+/// Added to the service id in positive responses
+const POSITIVE_RESPONSE_OFFSET: u8 = 0x40;
+
+const DIAGNOSTIC_SESSION_CONTROL: u8 = 0x10;
+const ECU_RESET: u8 = 0x11;
+const SECURITY_ACCESS: u8 = 0x27;
+const ROUTINE_CONTROL: u8 = 0x31;
+const TESTER_PRESENT: u8 = 0x3E;
+
+/// Sub-function bit asking the server not to send a positive response
+const SUPPRESS_POSITIVE_RESPONSE: u8 = 0x80;
+
+/// The default session, active after power on and after a reset
+pub const DEFAULT_SESSION: u8 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UdsKind {
+    Request,
+    PositiveResponse,
+    /// With the negative response code
+    NegativeResponse(u8),
+}
+
+/// A decoded UDS message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UdsMessage {
+    /// Service id, of the request for responses
+    pub service: u8,
+    pub kind: UdsKind,
+    pub sub_function: Option<u8>,
+    /// Data identifier, for the services reading or writing them
+    pub did: Option<u16>,
+    /// Routine identifier, for RoutineControl
+    pub routine: Option<u16>,
+    /// The rest of the message
+    pub data: Vec<u8>,
+}
+
+impl UdsMessage {
+    /// Decode an ISO-TP message. Returns `None` when it is empty.
+    pub fn parse(data: &[u8]) -> Option<UdsMessage> {
+        let (&sid, rest) = data.split_first()?;
+
+        if sid == NEGATIVE_RESPONSE {
+            let (&service, rest) = rest.split_first()?;
+            let nrc = rest.first().copied().unwrap_or_default();
+            return Some(UdsMessage {
+                service,
+                kind: UdsKind::NegativeResponse(nrc),
+                sub_function: None,
+                did: None,
+                routine: None,
+                data: Vec::new(),
+            });
+        }
+
+        let (service, kind) = match sid {
+            0x40..=0x7E | 0xC0..=0xFE => {
+                (sid - POSITIVE_RESPONSE_OFFSET, UdsKind::PositiveResponse)
+            }
+            _ => (sid, UdsKind::Request),
+        };
+
+        let mut rest = rest;
+        let mut take = |n: usize| -> Option<&[u8]> {
+            let (head, tail) = (rest.get(..n)?, &rest[n..]);
+            rest = tail;
+            Some(head)
+        };
+
+        let sub_function = has_sub_function(service)
+            .then(|| take(1).map(|b| b[0]))
+            .flatten();
+        let routine = (service == ROUTINE_CONTROL)
+            .then(|| take(2).map(|b| u16::from_be_bytes([b[0], b[1]])))
+            .flatten();
+        let did = has_did(service)
+            .then(|| take(2).map(|b| u16::from_be_bytes([b[0], b[1]])))
+            .flatten();
+
+        Some(UdsMessage {
+            service,
+            kind,
+            sub_function,
+            did,
+            routine,
+            data: rest.to_vec(),
+        })
+    }
+}
+
+fn has_sub_function(service: u8) -> bool {
+    matches!(
+        service,
+        0x10 | 0x11 | 0x19 | 0x27 | 0x28 | 0x29 | 0x2C | 0x31 | 0x3E | 0x83 | 0x85 | 0x86 | 0x87
+    )
+}
+
+fn has_did(service: u8) -> bool {
+    matches!(service, 0x22 | 0x24 | 0x2E | 0x2F)
+}
+
+impl Display for UdsMessage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = service_name(self.service).unwrap_or("Unknown service");
+        match self.kind {
+            UdsKind::Request => write!(f, "{}", name)?,
+            UdsKind::PositiveResponse => write!(f, "{} +", name)?,
+            UdsKind::NegativeResponse(nrc) => {
+                return write!(f, "{} - NRC {:02X} {}", name, nrc, nrc_description(nrc));
+            }
+        }
+
+        if let Some(sub_function) = self.sub_function {
+            let suppress = sub_function & SUPPRESS_POSITIVE_RESPONSE != 0;
+            let sub_function = sub_function & !SUPPRESS_POSITIVE_RESPONSE;
+            match self.service {
+                DIAGNOSTIC_SESSION_CONTROL => write!(f, " {}", session_name(sub_function))?,
+                ECU_RESET => write!(f, " {}", reset_name(sub_function))?,
+                SECURITY_ACCESS if sub_function % 2 == 1 => {
+                    write!(f, " request seed, level {}", sub_function)?
+                }
+                SECURITY_ACCESS => {
+                    write!(f, " send key, level {}", sub_function.saturating_sub(1))?
+                }
+                ROUTINE_CONTROL => write!(f, " {}", routine_control_name(sub_function))?,
+                TESTER_PRESENT => {}
+                _ => write!(f, " {:02X}", sub_function)?,
+            }
+            if suppress {
+                write!(f, " (no response)")?;
+            }
+        }
+
+        if let Some(routine) = self.routine {
+            write!(f, " routine {:04X}", routine)?;
+        }
+
+        if let Some(did) = self.did {
+            write!(f, " DID {:04X}", did)?;
+            if let Some(name) = did_name(did) {
+                write!(f, " {}", name)?;
+            }
+        }
+
+        if !self.data.is_empty() {
+            write!(f, ":")?;
+            for b in &self.data {
+                write!(f, " {:02X}", b)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+pub fn service_name(service: u8) -> Option<&'static str> {
+    let name = match service {
+        0x10 => "DiagnosticSessionControl",
+        0x11 => "ECUReset",
+        0x14 => "ClearDiagnosticInformation",
+        0x19 => "ReadDTCInformation",
+        0x22 => "ReadDataByIdentifier",
+        0x23 => "ReadMemoryByAddress",
+        0x24 => "ReadScalingDataByIdentifier",
+        0x27 => "SecurityAccess",
+        0x28 => "CommunicationControl",
+        0x29 => "Authentication",
+        0x2A => "ReadDataByPeriodicIdentifier",
+        0x2C => "DynamicallyDefineDataIdentifier",
+        0x2E => "WriteDataByIdentifier",
+        0x2F => "InputOutputControlByIdentifier",
+        0x31 => "RoutineControl",
+        0x34 => "RequestDownload",
+        0x35 => "RequestUpload",
+        0x36 => "TransferData",
+        0x37 => "RequestTransferExit",
+        0x38 => "RequestFileTransfer",
+        0x3D => "WriteMemoryByAddress",
+        0x3E => "TesterPresent",
+        0x83 => "AccessTimingParameter",
+        0x84 => "SecuredDataTransmission",
+        0x85 => "ControlDTCSetting",
+        0x86 => "ResponseOnEvent",
+        0x87 => "LinkControl",
+        _ => return None,
+    };
+    Some(name)
+}
+
+pub fn nrc_description(nrc: u8) -> &'static str {
+    match nrc {
+        0x10 => "generalReject",
+        0x11 => "serviceNotSupported",
+        0x12 => "subFunctionNotSupported",
+        0x13 => "incorrectMessageLengthOrInvalidFormat",
+        0x14 => "responseTooLong",
+        0x21 => "busyRepeatRequest",
+        0x22 => "conditionsNotCorrect",
+        0x24 => "requestSequenceError",
+        0x25 => "noResponseFromSubnetComponent",
+        0x26 => "failurePreventsExecutionOfRequestedAction",
+        0x31 => "requestOutOfRange",
+        0x33 => "securityAccessDenied",
+        0x35 => "invalidKey",
+        0x36 => "exceedNumberOfAttempts",
+        0x37 => "requiredTimeDelayNotExpired",
+        0x70 => "uploadDownloadNotAccepted",
+        0x71 => "transferDataSuspended",
+        0x72 => "generalProgrammingFailure",
+        0x73 => "wrongBlockSequenceCounter",
+        0x78 => "requestCorrectlyReceivedResponsePending",
+        0x7E => "subFunctionNotSupportedInActiveSession",
+        0x7F => "serviceNotSupportedInActiveSession",
+        _ => "reserved",
+    }
+}
+
+/// Name of the standard data identifiers
+pub fn did_name(did: u16) -> Option<&'static str> {
+    let name = match did {
+        0xF180 => "bootSoftwareIdentification",
+        0xF181 => "applicationSoftwareIdentification",
+        0xF182 => "applicationDataIdentification",
+        0xF186 => "activeDiagnosticSession",
+        0xF187 => "sparePartNumber",
+        0xF188 => "ecuSoftwareNumber",
+        0xF189 => "ecuSoftwareVersionNumber",
+        0xF18A => "systemSupplierIdentifier",
+        0xF18B => "ecuManufacturingDate",
+        0xF18C => "ecuSerialNumber",
+        0xF190 => "VIN",
+        0xF191 => "ecuHardwareNumber",
+        0xF192 => "systemSupplierEcuHardwareNumber",
+        0xF193 => "systemSupplierEcuHardwareVersionNumber",
+        0xF194 => "systemSupplierEcuSoftwareNumber",
+        0xF195 => "systemSupplierEcuSoftwareVersionNumber",
+        0xF197 => "systemName",
+        0xF198 => "repairShopCode",
+        0xF199 => "programmingDate",
+        0xF19E => "odxFile",
+        _ => return None,
+    };
+    Some(name)
+}
+
+pub fn session_name(session: u8) -> &'static str {
+    match session {
+        1 => "default",
+        2 => "programming",
+        3 => "extended",
+        4 => "safety system",
+        _ => "vendor specific",
+    }
+}
+
+fn reset_name(reset: u8) -> &'static str {
+    match reset {
+        1 => "hard reset",
+        2 => "key off on reset",
+        3 => "soft reset",
+        4 => "enable rapid power shutdown",
+        5 => "disable rapid power shutdown",
+        _ => "vendor specific reset",
+    }
+}
+
+fn routine_control_name(sub_function: u8) -> &'static str {
+    match sub_function {
+        1 => "start",
+        2 => "stop",
+        3 => "request results",
+        _ => "unknown",
+    }
+}
+
+/// Active diagnostic session of each server, by channel and address, as
+/// confirmed by its positive responses
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct UdsSessions {
+    sessions: BTreeMap<(usize, Option<u8>), u8>,
+}
+
+impl UdsSessions {
+    /// Track the session changes. Returns the new session if it changed.
+    pub fn push(&mut self, message: &IsoTpMessage, uds: &UdsMessage) -> Option<u8> {
+        if message.direction != IsoTpDirection::Response || uds.kind != UdsKind::PositiveResponse {
+            return None;
+        }
+
+        let session = match uds.service {
+            DIAGNOSTIC_SESSION_CONTROL => uds.sub_function? & !SUPPRESS_POSITIVE_RESPONSE,
+            ECU_RESET => DEFAULT_SESSION,
+            _ => return None,
+        };
+
+        let key = (message.channel, message.address);
+        let previous = self.sessions.insert(key, session);
+        (previous != Some(session)).then_some(session)
+    }
+
+    /// The session of a server, the default if it never changed
+    pub fn session(&self, channel: usize, address: Option<u8>) -> u8 {
+        self.sessions
+            .get(&(channel, address))
+            .copied()
+            .unwrap_or(DEFAULT_SESSION)
+    }
+
+    /// Servers which left the default session at least once
+    pub fn iter(&self) -> impl Iterator<Item = ((usize, Option<u8>), u8)> + '_ {
+        self.sessions.iter().map(|(k, v)| (*k, *v))
+    }
+
+    pub fn clear(&mut self) {
+        self.sessions.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn parse(data: &[u8]) -> UdsMessage {
+        UdsMessage::parse(data).unwrap()
+    }
+
+    #[test]
+    fn parses_requests_and_responses() {
+        let request = parse(&[0x22, 0xF1, 0x90]);
+        assert_eq!(request.service, 0x22);
+        assert_eq!(request.kind, UdsKind::Request);
+        assert_eq!(request.did, Some(0xF190));
+        assert_eq!(request.to_string(), "ReadDataByIdentifier DID F190 VIN");
+
+        let response = parse(&[0x62, 0xF1, 0x90, 0x57, 0x30]);
+        assert_eq!(response.service, 0x22);
+        assert_eq!(response.kind, UdsKind::PositiveResponse);
+        assert_eq!(response.data, [0x57, 0x30]);
+        assert_eq!(
+            response.to_string(),
+            "ReadDataByIdentifier + DID F190 VIN: 57 30"
+        );
+
+        let routine = parse(&[0x31, 0x01, 0xFF, 0x00, 0x01]);
+        assert_eq!(routine.sub_function, Some(1));
+        assert_eq!(routine.routine, Some(0xFF00));
+        assert_eq!(routine.data, [0x01]);
+
+        let tester_present = parse(&[0x3E, 0x80]);
+        assert_eq!(tester_present.to_string(), "TesterPresent (no response)");
+
+        // Truncated messages keep what is there
+        assert_eq!(parse(&[0x22, 0xF1]).did, None);
+        assert_eq!(UdsMessage::parse(&[]), None);
+    }
+
+    #[test]
+    fn parses_negative_responses() {
+        let response = parse(&[0x7F, 0x22, 0x31]);
+        assert_eq!(response.service, 0x22);
+        assert_eq!(response.kind, UdsKind::NegativeResponse(0x31));
+        assert_eq!(
+            response.to_string(),
+            "ReadDataByIdentifier - NRC 31 requestOutOfRange"
+        );
+        assert_eq!(UdsMessage::parse(&[0x7F]), None);
+    }
+
+    #[test]
+    fn tracks_sessions() {
+        let message = |direction, data: &[u8]| IsoTpMessage {
+            channel: 0,
+            direction,
+            address: None,
+            data: data.to_vec(),
+            timestamp: Duration::ZERO,
+        };
+        let mut sessions = UdsSessions::default();
+        let mut push =
+            |direction, data: &[u8]| sessions.push(&message(direction, data), &parse(data));
+
+        assert_eq!(push(IsoTpDirection::Request, &[0x10, 0x03]), None);
+        assert_eq!(push(IsoTpDirection::Response, &[0x7F, 0x10, 0x22]), None);
+        assert_eq!(
+            push(IsoTpDirection::Response, &[0x50, 0x03, 0, 0x32]),
+            Some(3)
+        );
+        assert_eq!(push(IsoTpDirection::Response, &[0x50, 0x03, 0, 0x32]), None);
+        assert_eq!(
+            push(IsoTpDirection::Response, &[0x51, 0x01]),
+            Some(DEFAULT_SESSION)
+        );
+    }
+}
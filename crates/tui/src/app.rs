@@ -0,0 +1,181 @@
+use super::stats::{self, SortBy};
+use canbusnoop_core::Frame;
+use canbusnoop_db::{MultiStats, Window};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::layout::{Constraint, Direction, Layout};
+use ratatui::style::{Color, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::Paragraph;
+use ratatui::Frame as TerminalFrame;
+
+/// Text field being edited
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Field {
+    Filter,
+    Mask,
+}
+
+pub(crate) struct App {
+    stats: MultiStats,
+    /// Snapshot shown while paused, new frames still go to `stats`
+    paused: Option<MultiStats>,
+    can_id_filter: String,
+    can_id_mask: String,
+    editing: Option<Field>,
+    sort: SortBy,
+    reverse: bool,
+    pub(crate) quit: bool,
+}
+
+impl App {
+    pub(crate) fn new(window: Window) -> Self {
+        Self {
+            stats: MultiStats::new(window),
+            paused: None,
+            can_id_filter: "00000000".to_string(),
+            can_id_mask: "00000000".to_string(),
+            editing: None,
+            sort: SortBy::default(),
+            reverse: false,
+            quit: false,
+        }
+    }
+
+    pub(crate) fn push(&mut self, frame: Frame) {
+        self.stats.push(frame);
+    }
+
+    pub(crate) fn on_key(&mut self, key: KeyEvent) {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            self.quit = true;
+            return;
+        }
+
+        if let Some(field) = self.editing {
+            let value = match field {
+                Field::Filter => &mut self.can_id_filter,
+                Field::Mask => &mut self.can_id_mask,
+            };
+            match key.code {
+                KeyCode::Char(c) if c.is_ascii_hexdigit() && value.len() < 8 => value.push(c),
+                KeyCode::Backspace => {
+                    value.pop();
+                }
+                KeyCode::Enter | KeyCode::Esc | KeyCode::Tab => self.editing = None,
+                _ => {}
+            }
+            return;
+        }
+
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
+            KeyCode::Char('c') => {
+                self.stats.clear();
+                if self.paused.is_some() {
+                    self.paused = Some(MultiStats::new(self.stats.window()));
+                }
+            }
+            KeyCode::Char('p') | KeyCode::Char(' ') => {
+                self.paused = match self.paused {
+                    Some(_) => None,
+                    None => Some(self.stats.clone()),
+                };
+            }
+            KeyCode::Char('f') => self.editing = Some(Field::Filter),
+            KeyCode::Char('m') => self.editing = Some(Field::Mask),
+            KeyCode::Char('s') => self.sort = self.sort.next(),
+            KeyCode::Char('S') => self.sort = self.sort.prev(),
+            KeyCode::Char('r') => self.reverse = !self.reverse,
+            _ => {}
+        }
+    }
+
+    pub(crate) fn render(&self, f: &mut TerminalFrame) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(1),
+                Constraint::Length(1),
+                Constraint::Min(0),
+                Constraint::Length(1),
+            ])
+            .split(f.size());
+
+        let stats = self.paused.as_ref().unwrap_or(&self.stats);
+        let status = self.status_line(stats);
+        let stats = {
+            let can_id_filter = u32::from_str_radix(&self.can_id_filter, 16).unwrap_or(0);
+            let can_id_mask = u32::from_str_radix(&self.can_id_mask, 16).unwrap_or(0);
+            stats.clone().filter_by_can_id(can_id_filter, can_id_mask)
+        };
+
+        f.render_widget(Paragraph::new(status), chunks[0]);
+        f.render_widget(Paragraph::new(self.filter_line()), chunks[1]);
+        f.render_widget(stats::table(&stats, self.sort, self.reverse), chunks[2]);
+        f.render_widget(Paragraph::new(help_line(self.editing.is_some())), chunks[3]);
+    }
+
+    fn status_line(&self, stats: &MultiStats) -> Line<'static> {
+        let mut spans = vec![Span::raw(format!("Total: {}", stats.count()))];
+        let errors = stats.errors().count();
+        if errors > 0 {
+            spans.push(Span::styled(
+                format!("  Error frames: {}", errors),
+                Style::default().fg(Color::Red),
+            ));
+        }
+        let tp = stats.transport();
+        let tp_failed = tp.aborted() + tp.timed_out() + tp.bad_sequence();
+        if tp.completed() + tp_failed > 0 {
+            spans.push(Span::raw(format!(
+                "  J1939 TP: {} ok, {} failed",
+                tp.completed(),
+                tp_failed
+            )));
+        }
+        let order = if self.reverse { "desc" } else { "asc" };
+        spans.push(Span::raw(format!("  Sort: {} {}", self.sort.name(), order)));
+        if self.paused.is_some() {
+            spans.push(Span::styled(
+                "  PAUSED",
+                Style::default().fg(Color::Black).bg(Color::Yellow),
+            ));
+        }
+        Line::from(spans)
+    }
+
+    fn filter_line(&self) -> Line<'static> {
+        let field = |label: &str, value: &str, editing: bool| {
+            let style = if editing {
+                Style::default().fg(Color::Black).bg(Color::Cyan)
+            } else {
+                Style::default()
+            };
+            vec![
+                Span::raw(format!("{}: ", label)),
+                Span::styled(format!("{:8}", value), style),
+                Span::raw("  "),
+            ]
+        };
+        let mut spans = field(
+            "filter",
+            &self.can_id_filter,
+            self.editing == Some(Field::Filter),
+        );
+        spans.extend(field(
+            "mask",
+            &self.can_id_mask,
+            self.editing == Some(Field::Mask),
+        ));
+        Line::from(spans)
+    }
+}
+
+fn help_line(editing: bool) -> Line<'static> {
+    let help = if editing {
+        "0-9 a-f: edit  Backspace: delete  Enter: done"
+    } else {
+        "q: quit  c: clear  p: pause  f: filter  m: mask  s/S: sort column  r: reverse"
+    };
+    Line::styled(help, Style::default().fg(Color::DarkGray))
+}
@@ -0,0 +1,75 @@
+mod app;
+mod stats;
+
+use app::App;
+use canbusnoop_core::Frame;
+use canbusnoop_db::Window;
+use crossterm::cursor::Show;
+use crossterm::event::{self, Event, KeyEventKind};
+use crossterm::execute;
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use futures_channel::mpsc::UnboundedReceiver;
+use ratatui::backend::CrosstermBackend;
+use ratatui::Terminal;
+use std::io;
+use std::time::Duration;
+
+/// Time between two redraws, frames received meanwhile are accumulated
+const TICK: Duration = Duration::from_millis(100);
+
+/// Run the terminal user interface until the user quits
+pub fn launch(rx_receiver: UnboundedReceiver<Frame>, window: Window) -> io::Result<()> {
+    install_panic_hook();
+    enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen)?;
+
+    let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+    let result = run(&mut terminal, rx_receiver, window);
+
+    // Restore the terminal even if the event loop failed
+    disable_raw_mode()?;
+    execute!(io::stdout(), LeaveAlternateScreen)?;
+    terminal.show_cursor()?;
+
+    result
+}
+
+/// Restore the terminal before the panic message is printed, or it would be
+/// lost in the alternate screen and the shell left in raw mode
+fn install_panic_hook() {
+    let hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let _ = disable_raw_mode();
+        let _ = execute!(io::stdout(), LeaveAlternateScreen, Show);
+        hook(info);
+    }));
+}
+
+fn run<B: ratatui::backend::Backend>(
+    terminal: &mut Terminal<B>,
+    mut rx_receiver: UnboundedReceiver<Frame>,
+    window: Window,
+) -> io::Result<()> {
+    let mut app = App::new(window);
+
+    while !app.quit {
+        // Ok(None) means the reader has stopped: keep showing what we have
+        while let Ok(Some(frame)) = rx_receiver.try_next() {
+            app.push(frame);
+        }
+
+        terminal.draw(|f| app.render(f))?;
+
+        if event::poll(TICK)? {
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press {
+                    app.on_key(key);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,175 @@
+use canbusnoop_core::CanId;
+use canbusnoop_db::{fmt_period, MultiStats, PeriodStats, Stats};
+use ratatui::layout::Constraint;
+use ratatui::style::{Modifier, Style};
+use ratatui::widgets::{Row, Table};
+use std::cmp::Ordering;
+
+/// Column the table is sorted by
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub(crate) enum SortBy {
+    #[default]
+    Id,
+    Count,
+    Last,
+    Min,
+    Max,
+    Avg,
+    Freq,
+    Throughput,
+    Jitter,
+}
+
+impl SortBy {
+    const ALL: [SortBy; 9] = [
+        SortBy::Id,
+        SortBy::Count,
+        SortBy::Last,
+        SortBy::Min,
+        SortBy::Max,
+        SortBy::Avg,
+        SortBy::Freq,
+        SortBy::Throughput,
+        SortBy::Jitter,
+    ];
+
+    pub(crate) fn next(self) -> SortBy {
+        let i = Self::ALL.iter().position(|&x| x == self).unwrap_or(0);
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+
+    pub(crate) fn prev(self) -> SortBy {
+        let i = Self::ALL.iter().position(|&x| x == self).unwrap_or(0);
+        Self::ALL[(i + Self::ALL.len() - 1) % Self::ALL.len()]
+    }
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            SortBy::Id => "ID",
+            SortBy::Count => "Count",
+            SortBy::Last => "Last",
+            SortBy::Min => "Min",
+            SortBy::Max => "Max",
+            SortBy::Avg => "Avg",
+            SortBy::Freq => "Freq",
+            SortBy::Throughput => "Throughput",
+            SortBy::Jitter => "Jitter",
+        }
+    }
+
+    fn compare(self, a: &Item, b: &Item) -> Ordering {
+        let (id_a, a, periods_a) = a;
+        let (id_b, b, periods_b) = b;
+        let mean = |p: &Option<PeriodStats>| p.map(|p| p.mean);
+        let frequency = |p: &Option<PeriodStats>| p.and_then(|p| p.frequency());
+        let jitter = |p: &Option<PeriodStats>| Some(p.map(|p| p.jitter).unwrap_or_default());
+        // Missing values go first, ties are broken by id
+        let ordering = match self {
+            SortBy::Id => Ordering::Equal,
+            SortBy::Count => a.count().cmp(&b.count()),
+            SortBy::Last => a.last_period().cmp(&b.last_period()),
+            SortBy::Min => a.min_period().cmp(&b.min_period()),
+            SortBy::Max => a.max_period().cmp(&b.max_period()),
+            SortBy::Avg => mean(periods_a).cmp(&mean(periods_b)),
+            SortBy::Freq => cmp_f64(frequency(periods_a), frequency(periods_b)),
+            SortBy::Throughput => cmp_f64(a.throughput(), b.throughput()),
+            SortBy::Jitter => cmp_f64(jitter(periods_a), jitter(periods_b)),
+        };
+        ordering.then(id_a.cmp(id_b))
+    }
+}
+
+fn cmp_f64(a: Option<f64>, b: Option<f64>) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => a.total_cmp(&b),
+        (a, b) => a.is_some().cmp(&b.is_some()),
+    }
+}
+
+/// A row of the table, with the statistics of its periods, which are
+/// computed once and not at each comparison
+type Item<'a> = (&'a CanId, &'a Stats, Option<PeriodStats>);
+
+const COLUMNS: [(&str, &str); 10] = [
+    ("ID", ""),
+    ("Type", ""),
+    ("Count", ""),
+    ("Last", "ms"),
+    ("Min", "ms"),
+    ("Max", "ms"),
+    ("Avg", "ms"),
+    ("Freq", "Hz"),
+    ("Throughput", "Hz"),
+    ("Jitter", "%"),
+];
+
+/// Per-id statistics, the same columns shown by the graphical interface
+pub(crate) fn table(stats: &MultiStats, sort: SortBy, reverse: bool) -> Table<'static> {
+    let mut items: Vec<Item> = stats
+        .iter()
+        .map(|(id, stats)| (id, stats, stats.period_stats()))
+        .collect();
+    items.sort_by(|a, b| sort.compare(a, b));
+    if reverse {
+        items.reverse();
+    }
+
+    let header = Row::new(
+        COLUMNS
+            .iter()
+            .map(|(name, unit)| match unit.is_empty() {
+                true => name.to_string(),
+                false => format!("{} {}", name, unit),
+            })
+            .collect::<Vec<_>>(),
+    )
+    .style(Style::default().add_modifier(Modifier::BOLD));
+
+    let rows = items.iter().map(row);
+
+    let widths = [
+        Constraint::Length(10),
+        Constraint::Length(7),
+        Constraint::Length(8),
+        Constraint::Length(10),
+        Constraint::Length(10),
+        Constraint::Length(10),
+        Constraint::Length(10),
+        Constraint::Length(9),
+        Constraint::Length(15),
+        Constraint::Length(9),
+    ];
+
+    Table::new(rows, widths).header(header)
+}
+
+fn row((id, stats, periods): &Item) -> Row<'static> {
+    let id = match id.is_rtr() {
+        true => format!("{} R", id),
+        false => id.to_string(),
+    };
+    Row::new(vec![
+        id,
+        stats
+            .fd_flags()
+            .map(|f| f.to_string())
+            .unwrap_or("CAN".to_string()),
+        stats.count().to_string(),
+        stats.last_period().map(fmt_period).unwrap_or_default(),
+        stats.min_period().map(fmt_period).unwrap_or_default(),
+        stats.max_period().map(fmt_period).unwrap_or_default(),
+        periods.map(|p| fmt_period(p.mean)).unwrap_or_default(),
+        periods
+            .and_then(|p| p.frequency())
+            .map(|x| format!("{:.2}", x))
+            .unwrap_or_default(),
+        stats
+            .throughput()
+            .map(|x| format!("{:.2}", x))
+            .unwrap_or_default(),
+        format!(
+            "{:.2}",
+            periods.map(|p| p.jitter).unwrap_or_default() * 100.
+        ),
+    ])
+}
@@ -0,0 +1,87 @@
+use canbusnoop_db::BitStats;
+use dioxus::prelude::*;
+
+#[derive(Props, PartialEq)]
+pub(crate) struct BitHeatMapProps {
+    bits: BitStats,
+}
+
+/// How often each bit of the payload changes, one row per byte with the
+/// most significant bit first. Constant bits show their value, bytes which
+/// look like counters or checksums are labeled.
+pub(crate) fn BitHeatMap(cx: Scope<BitHeatMapProps>) -> Element {
+    let bits = &cx.props.bits;
+    if bits.is_empty() {
+        return None;
+    }
+
+    let rows = (0..bits.len()).map(|byte| {
+        let cells: Vec<_> = (0..8)
+            .rev()
+            .map(|i| BitCell::new(bits, byte * 8 + i))
+            .collect();
+        let kind = bits
+            .byte_kind(byte)
+            .map(|k| k.to_string())
+            .unwrap_or_default();
+        (byte, cells, kind)
+    });
+
+    render! {
+        table {
+            class: "font-mono text-xs my-2",
+            tr {
+                th { class: "px-1", "" }
+                for i in (0..8).rev() {
+                    th { class: "w-8 text-center", "{i}" }
+                }
+                th { class: "px-2 text-left", "{bits.frames()} frames" }
+            }
+            for (byte, cells, kind) in rows {
+                tr {
+                    th { class: "px-1 text-right", "{byte}" }
+                    for cell in cells.into_iter() {
+                        td {
+                            class: "w-8 text-center border border-white",
+                            background_color: "{cell.bg_color}",
+                            title: "{cell.title}",
+                            "{cell.label}"
+                        }
+                    }
+                    td { class: "px-2 text-gray-700", "{kind}" }
+                }
+            }
+        }
+    }
+}
+
+struct BitCell {
+    bg_color: String,
+    /// The value of constant bits
+    label: &'static str,
+    title: String,
+}
+
+impl BitCell {
+    fn new(bits: &BitStats, bit: usize) -> BitCell {
+        let rate = bits.toggle_rate(bit);
+        let value = bits.value(bit).unwrap_or_default();
+        match bits.is_constant(bit) {
+            true => BitCell {
+                bg_color: "rgb(229, 231, 235)".to_string(),
+                label: if value { "1" } else { "0" },
+                title: format!("bit {}, constant", bit),
+            },
+            // From pale yellow, rarely changing, to red, changing at every frame
+            false => BitCell {
+                bg_color: format!(
+                    "hsl({:.0}, 100%, {:.0}%)",
+                    60. * (1. - rate),
+                    85. - 35. * rate
+                ),
+                label: "",
+                title: format!("bit {}, toggles in {:.1}% of the frames", bit, rate * 100.),
+            },
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use canbusnoop_protocol::canopen::{CanOpen, NmtState, Node};
+use dioxus::prelude::*;
+use std::time::Duration;
+
+/// Emergencies and SDO transfers shown for each node
+const RECENT: usize = 8;
+
+#[derive(Props, PartialEq)]
+pub(crate) struct NodesProps {
+    canopen: CanOpen,
+    /// Time of the last frame received, times are shown relative to it
+    now: Duration,
+}
+
+/// One card for each CANopen node: NMT state, emergencies, SDO transfers and
+/// PDOs
+pub(crate) fn Nodes(cx: Scope<NodesProps>) -> Element {
+    let canopen = &cx.props.canopen;
+    let now = cx.props.now;
+
+    if canopen.is_empty() {
+        return render! {
+            div { class: "p-2 text-sm text-gray-500", "No CANopen nodes" }
+        };
+    }
+
+    render! {
+        div {
+            class: "flex flex-wrap gap-2 p-2 text-xs",
+            for (id, node) in canopen.nodes() {
+                NodeCard { id: id, node: node.clone(), now: now }
+            }
+        }
+    }
+}
+
+#[derive(Props, PartialEq)]
+struct NodeCardProps {
+    id: u8,
+    node: Node,
+    now: Duration,
+}
+
+fn NodeCard(cx: Scope<NodeCardProps>) -> Element {
+    let node = &cx.props.node;
+    let now = cx.props.now;
+    let ago = |t: Duration| format!("{:.1} s ago", now.saturating_sub(t).as_secs_f64());
+
+    let state = node
+        .state()
+        .map(|s| s.to_string())
+        .unwrap_or_else(|| "No heartbeat".to_string());
+    let heartbeat = match (node.last_heartbeat(), node.heartbeat_period()) {
+        (Some(last), Some(period)) => format!("every {} ms, {}", period.as_millis(), ago(last)),
+        (Some(last), None) => ago(last),
+        _ => String::new(),
+    };
+    let command = node
+        .last_command()
+        .map(|(command, t)| format!("NMT {} {}", command, ago(t)))
+        .unwrap_or_default();
+    let emergencies: Vec<_> = node
+        .emergencies()
+        .rev()
+        .take(RECENT)
+        .map(|e| format!("{} {}", ago(e.timestamp), e))
+        .collect();
+    let emergency_count = node.emergency_count();
+    let transfers: Vec<_> = node
+        .sdo_transfers()
+        .rev()
+        .take(RECENT)
+        .map(|t| format!("{} {}", ago(t.timestamp), t))
+        .collect();
+    let pdos = node
+        .pdos()
+        .map(|(pdo, count)| format!("{} ({})", pdo, count))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    render! {
+        div {
+            class: "flex flex-col gap-1 p-2 border rounded font-mono",
+            div {
+                class: "flex gap-2 items-center",
+                span { class: "font-bold", "Node {cx.props.id}" }
+                span { class: state_class(node.state()), "{state}" }
+            }
+            div { "{heartbeat}" }
+            div { "{command}" }
+            if !pdos.is_empty() {
+                rsx! { div { "PDO: {pdos}" } }
+            }
+            if emergency_count > 0 {
+                rsx! {
+                    div { class: "text-red-800", "EMCY: {emergency_count}" }
+                    for e in emergencies.into_iter() {
+                        div { class: "pl-2 text-red-800", "{e}" }
+                    }
+                }
+            }
+            for t in transfers.into_iter() {
+                div { class: "pl-2", "SDO {t}" }
+            }
+        }
+    }
+}
+
+fn state_class(state: Option<NmtState>) -> &'static str {
+    match state {
+        Some(NmtState::Operational) => "rounded px-1 bg-green-100 text-green-800",
+        Some(NmtState::PreOperational) | Some(NmtState::BootUp) => {
+            "rounded px-1 bg-yellow-100 text-yellow-800"
+        }
+        Some(NmtState::Stopped) | Some(NmtState::Unknown(_)) => {
+            "rounded px-1 bg-red-100 text-red-800"
+        }
+        None => "rounded px-1 bg-gray-100 text-gray-500",
+    }
+}
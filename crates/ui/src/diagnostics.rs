@@ -0,0 +1,142 @@
+use canbusnoop_protocol::j1939::{fmi_description, Diagnostics, DtcRecord, LampStatus, Lamps};
+use dioxus::prelude::*;
+use std::time::Duration;
+
+#[derive(Props, PartialEq)]
+pub(crate) struct DiagnosticsPanelProps {
+    diagnostics: Diagnostics,
+    /// Time of the last frame received, times are shown relative to it
+    now: Duration,
+}
+
+/// J1939 trouble codes (DM1 and DM2) of each node, hidden until the first
+/// diagnostic message is seen
+pub(crate) fn DiagnosticsPanel(cx: Scope<DiagnosticsPanelProps>) -> Element {
+    let diagnostics = &cx.props.diagnostics;
+    if diagnostics.is_empty() {
+        return None;
+    }
+
+    let now = cx.props.now;
+    let rows = diagnostics.iter().flat_map(|(sa, node)| {
+        let active = node
+            .active()
+            .map(move |r| DtcRow::new(sa, DtcState::Active, r, now));
+        let previous = node
+            .previously_active()
+            .map(move |r| DtcRow::new(sa, DtcState::PreviouslyActive, r, now));
+        active.chain(previous)
+    });
+
+    render! {
+        div {
+            class: "p-2 text-sm",
+            div { class: "font-bold", "Diagnostics" }
+            for (sa, node) in diagnostics.iter() {
+                div {
+                    class: "flex gap-2 font-mono text-xs",
+                    div { "SA {sa:02X}" }
+                    LampsView { lamps: node.lamps() }
+                }
+            }
+            table {
+                class: "table-auto text-xs text-left font-mono",
+                thead {
+                    tr {
+                        for x in ["SA", "State", "SPN", "FMI", "OC", "CM", "First seen", "Last seen"] {
+                            th { class: "px-2", "{x}" }
+                        }
+                    }
+                }
+                tbody {
+                    for row in rows {
+                        tr {
+                            class: row.class,
+                            title: row.title,
+                            for x in row.cells.into_iter() {
+                                td { class: "px-2", "{x}" }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum DtcState {
+    Active,
+    PreviouslyActive,
+}
+
+struct DtcRow {
+    class: &'static str,
+    title: &'static str,
+    cells: [String; 8],
+}
+
+impl DtcRow {
+    fn new(sa: u8, state: DtcState, record: &DtcRecord, now: Duration) -> DtcRow {
+        let dtc = &record.dtc;
+        let ago = |t: Duration| format!("{:.1} s ago", now.saturating_sub(t).as_secs_f64());
+        let (class, state) = match state {
+            DtcState::Active => ("text-red-800", "Active"),
+            DtcState::PreviouslyActive => ("text-gray-500", "Previously active"),
+        };
+        // The older SPN layouts are decoded as the current one
+        let (spn, title) = match dtc.conversion_method {
+            true => (
+                format!("{}?", dtc.spn),
+                "SPN sent with an older layout, it may be wrong",
+            ),
+            false => (dtc.spn.to_string(), ""),
+        };
+        DtcRow {
+            class,
+            title,
+            cells: [
+                format!("{:02X}", sa),
+                state.to_string(),
+                spn,
+                format!("{} {}", dtc.fmi, fmi_description(dtc.fmi)),
+                dtc.occurrence.to_string(),
+                (dtc.conversion_method as u8).to_string(),
+                ago(record.first_seen),
+                ago(record.last_seen),
+            ],
+        }
+    }
+}
+
+#[derive(Props, PartialEq)]
+struct LampsViewProps {
+    lamps: Lamps,
+}
+
+fn LampsView(cx: Scope<LampsViewProps>) -> Element {
+    let lamps = cx.props.lamps;
+    let lamps = [
+        ("MIL", lamps.malfunction),
+        ("Red stop", lamps.red_stop),
+        ("Amber warning", lamps.amber_warning),
+        ("Protect", lamps.protect),
+    ];
+
+    render! {
+        for (label, status) in lamps.into_iter() {
+            div {
+                class: lamp_class(status),
+                "{label}"
+            }
+        }
+    }
+}
+
+fn lamp_class(status: LampStatus) -> &'static str {
+    match status {
+        LampStatus::On => "rounded px-1 bg-red-100 text-red-800",
+        LampStatus::Error => "rounded px-1 bg-yellow-100 text-yellow-800",
+        LampStatus::Off | LampStatus::NotAvailable => "rounded px-1 bg-gray-100 text-gray-400",
+    }
+}
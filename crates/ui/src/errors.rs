@@ -0,0 +1,107 @@
+use canbusnoop_db::ErrorStats;
+use dioxus::prelude::*;
+
+#[derive(Props, PartialEq)]
+pub(crate) struct ErrorsProps {
+    errors: ErrorStats,
+}
+
+/// Bus error counters, to tell wiring problems apart from missing traffic
+pub(crate) fn Errors(cx: Scope<ErrorsProps>) -> Element {
+    let errors = &cx.props.errors;
+
+    let counters = [
+        ("Error frames", errors.count()),
+        ("Bus off", errors.bus_off()),
+        ("Error passive", errors.error_passive()),
+        ("Error warning", errors.error_warning()),
+        ("Overflow", errors.overflow()),
+        ("Protocol", errors.protocol_violation()),
+        ("Bus error", errors.bus_error()),
+        ("Arbitration lost", errors.lost_arbitration()),
+        ("No ACK", errors.no_ack()),
+        ("TX timeout", errors.tx_timeout()),
+        ("Transceiver", errors.transceiver()),
+        ("Restarted", errors.restarted()),
+    ];
+
+    let tec_rec = errors
+        .counters()
+        .map(|c| format!("TEC {} / REC {}", c.tx, c.rx))
+        .unwrap_or_default();
+
+    let last_error = errors
+        .last_error()
+        .map(|e| e.to_string())
+        .unwrap_or_default();
+
+    render! {
+        div {
+            class: "flex flex-wrap gap-2 p-2 text-sm",
+            for (label, value) in counters.into_iter() {
+                Counter { label: label, value: value }
+            }
+            div { class: "p-1", "{tec_rec}" }
+            div { class: "p-1 text-red-600", "{last_error}" }
+        }
+    }
+}
+
+#[derive(Props, PartialEq)]
+pub(crate) struct TransportProps {
+    completed: usize,
+    aborted: usize,
+    timed_out: usize,
+    bad_sequence: usize,
+}
+
+/// J1939 transport sessions, hidden until the first one is seen
+pub(crate) fn Transport(cx: Scope<TransportProps>) -> Element {
+    let props = cx.props;
+    if props.completed + props.aborted + props.timed_out + props.bad_sequence == 0 {
+        return None;
+    }
+
+    let failures = [
+        ("TP aborted", props.aborted),
+        ("TP timeout", props.timed_out),
+        ("TP bad sequence", props.bad_sequence),
+    ];
+
+    render! {
+        div {
+            class: "flex flex-wrap gap-2 p-2 text-sm",
+            div {
+                class: "rounded px-2 py-1 bg-gray-100",
+                "TP messages: {props.completed}"
+            }
+            for (label, value) in failures.into_iter() {
+                Counter { label: label, value: value }
+            }
+        }
+    }
+}
+
+#[derive(Props, PartialEq)]
+struct CounterProps {
+    label: &'static str,
+    value: usize,
+}
+
+fn Counter(cx: Scope<CounterProps>) -> Element {
+    let label = cx.props.label;
+    let value = cx.props.value;
+
+    let class = if value > 0 {
+        "rounded px-2 py-1 bg-red-100 text-red-800"
+    } else {
+        "rounded px-2 py-1 bg-gray-100 text-gray-500"
+    };
+
+    render! {
+        div {
+            class: class,
+            "{label}: {value}"
+        }
+    }
+}
@@ -1,25 +1,67 @@
 #![allow(non_snake_case)]
+// Components borrowing their props return `Element` with the lifetime elided
+#![allow(mismatched_lifetime_syntaxes)]
 
+mod bits;
+mod canopen;
+mod diagnostics;
+mod errors;
+mod plot;
+mod send;
 mod stats;
 mod stats_item;
+mod trace;
+mod uds;
 mod widgets;
 
 use canbusnoop_core::Frame;
-use canbusnoop_db::MultiStats;
+use canbusnoop_db::{MultiStats, Trace, Window};
+use canbusnoop_dbc::Dbc;
+use canbusnoop_interface::{Recorder, TxRequest};
+use canbusnoop_protocol::isotp::IsoTpChannel;
+use canopen::Nodes;
+use diagnostics::DiagnosticsPanel;
 use dioxus::prelude::*;
 use dioxus_desktop::Config;
+use errors::{Errors, Transport};
 use futures::StreamExt;
+use plot::{Plot, PlotCommand, Plots};
+use send::{SendPanel, TxSender};
 use stats::Stats;
 use std::cell::Cell;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use trace::TracePanel;
+use uds::{UdsLog, UdsPanel};
 use widgets::Button;
 
 struct AppProps {
     rx_receiver: Cell<Option<UnboundedReceiver<Frame>>>,
+    tx_sender: UnboundedSender<TxRequest>,
+    dbc: Arc<Dbc>,
+    isotp: Vec<IsoTpChannel>,
+    window: Window,
 }
 
-pub fn launch(rx_receiver: UnboundedReceiver<Frame>) {
+/// Open the desktop window. Messages and signals in `dbc` are decoded, as
+/// the UDS messages exchanged on the `isotp` channels. Period statistics
+/// are computed over `window`.
+pub fn launch(
+    rx_receiver: UnboundedReceiver<Frame>,
+    tx_sender: UnboundedSender<TxRequest>,
+    dbc: Dbc,
+    isotp: Vec<IsoTpChannel>,
+    window: Window,
+) {
     let rx_receiver = Cell::new(Some(rx_receiver));
-    let props = AppProps { rx_receiver };
+    let props = AppProps {
+        rx_receiver,
+        tx_sender,
+        dbc: Arc::new(dbc),
+        isotp,
+        window,
+    };
     let config = Config::new()
         .with_custom_head(r#"<link rel="stylesheet" href="public/tailwind.css">"#.to_string());
 
@@ -27,41 +69,128 @@ pub fn launch(rx_receiver: UnboundedReceiver<Frame>) {
 }
 
 fn App(cx: Scope<AppProps>) -> Element {
-    let stats = use_ref(cx, || MultiStats::default());
+    let stats = use_ref(cx, || MultiStats::new(cx.props.window));
     let can_id_filter = use_state(cx, || "00000000".to_string());
     let can_id_mask = use_state(cx, || "00000000".to_string());
+    let recorder = use_ref(cx, || None::<Recorder>);
+    let recording_path = use_state(cx, || None::<PathBuf>);
+    let group_by_pgn = use_state(cx, || false);
+    let canopen = use_state(cx, || false);
+    let show_trace = use_state(cx, || false);
+    use_context_provider(cx, || -> TxSender { cx.props.tx_sender.clone() });
+    use_context_provider(cx, || cx.props.dbc.clone());
+    let plots = use_ref(cx, || Plots::new(plot::CAPACITY));
+    use_context_provider(cx, || plots.clone());
+    let now = use_ref(cx, Duration::default);
+    let uds = use_ref(cx, || UdsLog::new(cx.props.isotp.clone()));
+    use_context_provider(cx, || uds.clone());
+    let trace = use_ref(cx, || Trace::new(trace::CAPACITY));
+    use_context_provider(cx, || trace.clone());
 
     let _ = use_coroutine(cx, |_: UnboundedReceiver<()>| {
         let receiver = cx.props.rx_receiver.take();
-        to_owned![stats];
+        let dbc = cx.props.dbc.clone();
+        to_owned![stats, recorder, plots, now, uds, trace];
         async move {
             if let Some(mut receiver) = receiver {
                 while let Some(msg) = receiver.next().await {
                     let msg: Frame = msg;
+                    if let Some(r) = recorder.write_silent().as_mut() {
+                        if let Err(e) = r.write(&msg) {
+                            log::error!("Cannot record frame: {}", e);
+                        }
+                    }
+                    plot::push_frame(&mut plots.write_silent(), &dbc, &msg);
+                    uds.write_silent().push(&msg);
+                    *now.write_silent() = msg.timestamp();
+                    trace.write_silent().push(msg.clone());
                     stats.write().push(msg);
                 }
             }
         }
     });
 
+    let _ = use_coroutine(cx, |mut commands: UnboundedReceiver<PlotCommand>| {
+        to_owned![plots];
+        async move {
+            while let Some(command) = commands.next().await {
+                match command {
+                    PlotCommand::Add(key) => plots.write().add(key),
+                    PlotCommand::Remove(key) => plots.write().remove(&key),
+                }
+            }
+        }
+    });
+
     let clear = || {
         stats.write().clear();
+        plots.write().clear();
+        uds.write().clear();
+        trace.write().clear();
     };
 
+    let toggle_recording = || {
+        if recorder.read().is_some() {
+            recorder.set(None);
+            recording_path.set(None);
+            return;
+        }
+
+        let path = new_recording_path();
+        match Recorder::create(&path) {
+            Ok(r) => {
+                recorder.set(Some(r));
+                recording_path.set(Some(path));
+            }
+            Err(e) => log::error!("Cannot start recording: {}", e),
+        }
+    };
+
+    let recording_label = match recording_path.get() {
+        Some(path) => format!("Stop recording {}", path.display()),
+        None => "Record".to_string(),
+    };
+
+    let group_label = if **group_by_pgn {
+        "Ungroup"
+    } else {
+        "Group by PGN"
+    };
+
+    let canopen_label = if **canopen { "Hide CANopen" } else { "CANopen" };
+    let trace_label = if **show_trace { "Hide trace" } else { "Trace" };
+
+    let now = *now.read();
     let count = stats.read().count();
     let stats: MultiStats = stats.read().clone();
 
-    let stats = {
-        let can_id_filter = u32::from_str_radix(can_id_filter.as_str(), 16).unwrap_or(0x00000000);
-        let can_id_mask = u32::from_str_radix(can_id_mask.as_str(), 16).unwrap_or(0x00000000);
-        stats.filter_by_can_id(can_id_filter, can_id_mask)
-    };
+    let filter = u32::from_str_radix(can_id_filter.as_str(), 16).unwrap_or(0x00000000);
+    let mask = u32::from_str_radix(can_id_mask.as_str(), 16).unwrap_or(0x00000000);
+    let stats = stats.filter_by_can_id(filter, mask);
+
+    let nodes = canopen.then(|| stats.canopen().clone());
 
     render! {
         Button {
             on_click: move |_| { clear() },
             "Clear"
         }
+        Button {
+            on_click: move |_| { toggle_recording() },
+            "{recording_label}"
+        }
+        Button {
+            on_click: move |_| group_by_pgn.set(!**group_by_pgn),
+            "{group_label}"
+        }
+        Button {
+            on_click: move |_| canopen.set(!**canopen),
+            "{canopen_label}"
+        }
+        Button {
+            on_click: move |_| show_trace.set(!**show_trace),
+            "{trace_label}"
+        }
         div {
             "Total: {count}"
         }
@@ -77,8 +206,58 @@ fn App(cx: Scope<AppProps>) -> Element {
             oninput: move |evt| can_id_mask.set(evt.value.clone()),
           }
         }
+        SendPanel {}
+        Plot { now: now }
+        Errors {
+            errors: stats.errors().clone()
+        }
+        Transport {
+            completed: stats.transport().completed(),
+            aborted: stats.transport().aborted(),
+            timed_out: stats.transport().timed_out(),
+            bad_sequence: stats.transport().bad_sequence(),
+        }
+        if **show_trace {
+            rsx! {
+                TracePanel {
+                    now: now,
+                    filter: filter,
+                    mask: mask,
+                }
+            }
+        }
+        UdsPanel { now: now }
+        DiagnosticsPanel {
+            diagnostics: stats.diagnostics().clone(),
+            now: now,
+        }
+        if let Some(nodes) = nodes {
+            rsx! {
+                Nodes {
+                    canopen: nodes,
+                    now: now,
+                }
+            }
+        }
         Stats {
-            stats: stats
+            stats: stats,
+            group_by_pgn: **group_by_pgn,
+            canopen: **canopen,
+            now: now
         }
     }
 }
+
+/// Recordings are saved in the current directory, named after the start time
+fn new_recording_path() -> PathBuf {
+    let now = canbusnoop_core::now();
+    PathBuf::from(format!("canbusnoop-{}.log", now.as_secs()))
+}
+
+/// Bytes in hex, separated by spaces
+pub(crate) fn fmt_data(data: &[u8]) -> String {
+    data.iter()
+        .map(|b| format!("{:02X}", b))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
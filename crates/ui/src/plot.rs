@@ -0,0 +1,327 @@
+use super::widgets::Button;
+use canbusnoop_core::{CanId, Frame};
+use canbusnoop_db::{SeriesStore, TimeSeries};
+use canbusnoop_dbc::Dbc;
+use dioxus::prelude::*;
+use std::fmt::Display;
+use std::time::Duration;
+
+/// Points kept for each plotted series
+pub(crate) const CAPACITY: usize = 10_000;
+
+/// Width and height of a chart, in pixels
+const WIDTH: f64 = 800.;
+const HEIGHT: f64 = 120.;
+
+const MIN_WINDOW: Duration = Duration::from_millis(100);
+const MAX_WINDOW: Duration = Duration::from_secs(600);
+
+/// A plotted value: a DBC signal or some bytes of the payload of an id
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct PlotKey {
+    pub(crate) id: CanId,
+    pub(crate) source: PlotSource,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum PlotSource {
+    Signal(String),
+    /// Unsigned integer made of `len` bytes starting at `start`
+    Bytes {
+        start: usize,
+        len: usize,
+        big_endian: bool,
+    },
+}
+
+impl PlotKey {
+    fn value(&self, dbc: &Dbc, data: &[u8]) -> Option<f64> {
+        match &self.source {
+            PlotSource::Signal(name) => {
+                // Multiplexed signals are only present with their multiplexor
+                let message = dbc.message(self.id)?;
+                message
+                    .decode(data)
+                    .into_iter()
+                    .find(|d| &d.signal.name == name)
+                    .map(|d| d.value)
+            }
+            PlotSource::Bytes {
+                start,
+                len,
+                big_endian,
+            } => {
+                let bytes = data.get(*start..(start + len))?;
+                let fold = |acc: u64, b: &u8| (acc << 8) | *b as u64;
+                let value = match big_endian {
+                    true => bytes.iter().fold(0, fold),
+                    false => bytes.iter().rev().fold(0, fold),
+                };
+                Some(value as f64)
+            }
+        }
+    }
+}
+
+impl Display for PlotKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.source {
+            PlotSource::Signal(name) => write!(f, "{} {}", self.id, name),
+            PlotSource::Bytes {
+                start,
+                len,
+                big_endian,
+            } => {
+                let order = if *big_endian { "BE" } else { "LE" };
+                write!(f, "{} bytes {}..{} {}", self.id, start, start + len, order)
+            }
+        }
+    }
+}
+
+/// Sent by the stats table to choose what to plot
+pub(crate) enum PlotCommand {
+    Add(PlotKey),
+    Remove(PlotKey),
+}
+
+pub(crate) type Plots = SeriesStore<PlotKey>;
+
+/// Record the values carried by `frame` in the series being plotted
+pub(crate) fn push_frame(plots: &mut Plots, dbc: &Dbc, frame: &Frame) {
+    let keys: Vec<_> = plots
+        .keys()
+        .filter(|key| key.id == frame.id())
+        .cloned()
+        .collect();
+
+    for key in keys {
+        if let Some(value) = key.value(dbc, frame.data()) {
+            plots.push(&key, frame.timestamp(), value);
+        }
+    }
+}
+
+#[derive(Props, PartialEq)]
+pub(crate) struct PlotProps {
+    /// Time of the last frame received, the right edge of the charts
+    now: Duration,
+}
+
+/// Live scrolling charts of the plotted series, sharing the time axis
+pub(crate) fn Plot(cx: Scope<PlotProps>) -> Element {
+    let plots = use_context::<UseRef<Plots>>(cx)?;
+    let commands = use_coroutine_handle::<PlotCommand>(cx)?;
+    let window = use_state(cx, || Duration::from_secs(10));
+    let paused = use_state(cx, || None::<Duration>);
+    // Cursor position, as a fraction of the width
+    let cursor = use_state(cx, || None::<f64>);
+
+    let end = paused.unwrap_or(cx.props.now);
+    let start = end.saturating_sub(**window);
+    let cursor_time = cursor.map(|x| start + window.mul_f64(x));
+
+    let charts: Vec<_> = plots
+        .read()
+        .iter()
+        .map(|(key, series)| Chart::new(key, series, start, end, cursor_time))
+        .collect();
+
+    if charts.is_empty() {
+        return None;
+    }
+
+    let pause_label = if paused.is_some() { "Resume" } else { "Pause" };
+    let window_label = format!("{:.1} s", window.as_secs_f64());
+    let cursor_x = cursor.map(|x| x * WIDTH);
+    let cursor_label = cursor_time
+        .map(|t| format!("cursor {:.3} s", t.as_secs_f64() - end.as_secs_f64()))
+        .unwrap_or_default();
+
+    render! {
+        div {
+            class: "flex items-center gap-2 p-2 text-sm",
+            Button {
+                on_click: move |_| {
+                    paused.set(match **paused {
+                        Some(_) => None,
+                        None => Some(cx.props.now),
+                    })
+                },
+                "{pause_label}"
+            }
+            Button {
+                on_click: move |_| window.set((**window / 2).max(MIN_WINDOW)),
+                "Zoom in"
+            }
+            Button {
+                on_click: move |_| window.set((**window * 2).min(MAX_WINDOW)),
+                "Zoom out"
+            }
+            div { "{window_label}" }
+            div { class: "font-mono", "{cursor_label}" }
+        }
+        for chart in charts.into_iter() {
+            div {
+                class: "flex items-center gap-2 px-2",
+                svg {
+                    width: "{WIDTH}",
+                    height: "{HEIGHT}",
+                    class: "bg-gray-50 border",
+                    onmousemove: move |evt| {
+                        let x = evt.element_coordinates().x / WIDTH;
+                        cursor.set(Some(x.clamp(0., 1.)));
+                    },
+                    onmouseleave: move |_| cursor.set(None),
+                    polyline {
+                        points: "{chart.points}",
+                        fill: "none",
+                        stroke: "#0d9488",
+                        stroke_width: "1.5",
+                    }
+                    if let Some(x) = cursor_x {
+                        rsx! {
+                            line {
+                                x1: "{x}",
+                                y1: "0",
+                                x2: "{x}",
+                                y2: "{HEIGHT}",
+                                stroke: "#9ca3af",
+                            }
+                        }
+                    }
+                }
+                div {
+                    class: "flex flex-col text-xs font-mono",
+                    div { class: "font-bold", "{chart.label}" }
+                    div { "max {chart.max}" }
+                    div { "min {chart.min}" }
+                    div { "{chart.readout}" }
+                    Button {
+                        on_click: move |_| commands.send(PlotCommand::Remove(chart.key.clone())),
+                        "Remove"
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[derive(Props, PartialEq)]
+pub(crate) struct AddPlotProps {
+    plot: PlotKey,
+}
+
+/// Button to start plotting a series
+pub(crate) fn AddPlot(cx: Scope<AddPlotProps>) -> Element {
+    let commands = use_coroutine_handle::<PlotCommand>(cx)?;
+
+    render! {
+        Button {
+            on_click: move |_| commands.send(PlotCommand::Add(cx.props.plot.clone())),
+            "Plot"
+        }
+    }
+}
+
+#[derive(Props, PartialEq)]
+pub(crate) struct PlotBytesProps {
+    id: CanId,
+}
+
+/// Choose a range of bytes of the payload to plot as an unsigned integer
+pub(crate) fn PlotBytes(cx: Scope<PlotBytesProps>) -> Element {
+    let start = use_state(cx, || "0".to_string());
+    let len = use_state(cx, || "1".to_string());
+    let big_endian = use_state(cx, || false);
+
+    let plot = match (start.parse::<usize>(), len.parse::<usize>()) {
+        (Ok(start), Ok(len)) if (1..=8).contains(&len) => Some(PlotKey {
+            id: cx.props.id,
+            source: PlotSource::Bytes {
+                start,
+                len,
+                big_endian: **big_endian,
+            },
+        }),
+        _ => None,
+    };
+
+    render! {
+        div {
+            class: "flex items-center gap-2 text-xs",
+            div { "bytes from" }
+            input {
+                class: "w-12",
+                value: "{start}",
+                oninput: move |evt| start.set(evt.value.clone()),
+            }
+            div { "length" }
+            input {
+                class: "w-12",
+                value: "{len}",
+                oninput: move |evt| len.set(evt.value.clone()),
+            }
+            div { "big endian" }
+            input {
+                r#type: "checkbox",
+                checked: "{big_endian}",
+                oninput: move |evt| big_endian.set(evt.value == "true"),
+            }
+            if let Some(plot) = plot {
+                rsx! { AddPlot { plot: plot } }
+            }
+        }
+    }
+}
+
+/// What is needed to draw a series in the window `start..=end`
+struct Chart {
+    key: PlotKey,
+    label: String,
+    /// SVG polyline points
+    points: String,
+    min: String,
+    max: String,
+    /// Value at the cursor
+    readout: String,
+}
+
+impl Chart {
+    fn new(
+        key: &PlotKey,
+        series: &TimeSeries,
+        start: Duration,
+        end: Duration,
+        cursor: Option<Duration>,
+    ) -> Chart {
+        // A single sample, or a window with no time in it, has no span
+        let span = end.saturating_sub(start).as_secs_f64().max(f64::EPSILON);
+        let (min, max) = series.bounds(start, end).unwrap_or((0., 0.));
+        let x = |t: Duration| (t.saturating_sub(start).as_secs_f64() / span) * WIDTH;
+        let y = |v: f64| match max - min {
+            d if d > 0. => HEIGHT - (v - min) / d * HEIGHT,
+            _ => HEIGHT / 2.,
+        };
+
+        let points = series
+            .range(start, end)
+            .map(|(t, v)| format!("{:.1},{:.1}", x(t), y(v)))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let readout = cursor
+            .and_then(|t| series.at(t))
+            .map(|(_, v)| format!("{}", v))
+            .unwrap_or_default();
+
+        Chart {
+            key: key.clone(),
+            label: key.to_string(),
+            points,
+            min: min.to_string(),
+            max: max.to_string(),
+            readout,
+        }
+    }
+}
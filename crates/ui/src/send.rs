@@ -0,0 +1,177 @@
+use super::fmt_data;
+use super::widgets::Button;
+use canbusnoop_core::{CanId, FdFlags, Frame, FD_LENGTHS};
+use canbusnoop_interface::TxRequest;
+use dioxus::prelude::*;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// Channel to the task transmitting frames, provided as context by the App
+pub(crate) type TxSender = UnboundedSender<TxRequest>;
+
+/// Compose a frame and send it once or periodically, with the list of the
+/// active periodic transmissions
+pub(crate) fn SendPanel(cx: Scope) -> Element {
+    let tx_sender = use_context::<TxSender>(cx);
+    let id = use_state(cx, || "123".to_string());
+    let dlc = use_state(cx, || "8".to_string());
+    let data = use_state(cx, || "00 00 00 00 00 00 00 00".to_string());
+    let period = use_state(cx, || "100".to_string());
+    let fd = use_state(cx, || false);
+    let error = use_state(cx, String::new);
+    let periodic = use_ref(cx, BTreeMap::<CanId, (Frame, Duration)>::new);
+
+    // Returns false if the request cannot be sent
+    let request = move |req: TxRequest| {
+        let sent = tx_sender.is_some_and(|tx_sender| tx_sender.unbounded_send(req).is_ok());
+        if !sent {
+            error.set("Transmission is not available".to_string());
+        }
+        sent
+    };
+
+    let compose = move || match parse_frame(id, dlc, data, **fd) {
+        Ok(frame) => {
+            error.set(String::new());
+            Some(frame)
+        }
+        Err(e) => {
+            error.set(e.to_string());
+            None
+        }
+    };
+
+    let send = move || {
+        if let Some(frame) = compose() {
+            request(TxRequest::Send(frame));
+        }
+    };
+
+    let start = move || {
+        let Some(frame) = compose() else {
+            return;
+        };
+        let period = match period.parse::<u64>() {
+            Ok(ms) if ms > 0 => Duration::from_millis(ms),
+            _ => {
+                error.set("Invalid period".to_string());
+                return;
+            }
+        };
+        let (id, periodic_frame) = (frame.id(), frame.clone());
+        if request(TxRequest::StartPeriodic { frame, period }) {
+            periodic.write().insert(id, (periodic_frame, period));
+        }
+    };
+
+    let stop = move |id: CanId| {
+        periodic.write().remove(&id);
+        request(TxRequest::StopPeriodic(id));
+    };
+
+    let rows: Vec<_> = periodic
+        .read()
+        .values()
+        .map(|(frame, period)| (frame.id(), fmt_data(frame.data()), period.as_millis()))
+        .collect();
+
+    render! {
+        div {
+            class: "flex flex-wrap items-center gap-2 p-2 text-sm",
+            div { "id" }
+            input {
+                class: "w-24",
+                value: "{id}",
+                oninput: move |evt| id.set(evt.value.clone()),
+            }
+            div { "DLC" }
+            input {
+                class: "w-12",
+                value: "{dlc}",
+                oninput: move |evt| dlc.set(evt.value.clone()),
+            }
+            div { "data" }
+            input {
+                class: "w-64",
+                value: "{data}",
+                oninput: move |evt| data.set(evt.value.clone()),
+            }
+            div { "FD" }
+            input {
+                r#type: "checkbox",
+                checked: "{fd}",
+                oninput: move |evt| fd.set(evt.value == "true"),
+            }
+            div { "period (ms)" }
+            input {
+                class: "w-16",
+                value: "{period}",
+                oninput: move |evt| period.set(evt.value.clone()),
+            }
+            Button {
+                on_click: move |_| { send() },
+                "Send"
+            }
+            Button {
+                on_click: move |_| { start() },
+                "Start periodic"
+            }
+            div { class: "text-red-600", "{error}" }
+        }
+        div {
+            class: "flex flex-col gap-1 p-2 text-sm",
+            for (id, data, ms) in rows.into_iter() {
+                div {
+                    class: "flex items-center gap-2",
+                    div { class: "font-mono", "{id}" }
+                    div { class: "font-mono", "{data}" }
+                    div { "every {ms} ms" }
+                    Button {
+                        on_click: move |_| { stop(id) },
+                        "Stop"
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Build a frame from the fields of the send panel. The id is hexadecimal,
+/// extended if it has more than 3 digits or does not fit in 11 bits. Data is
+/// hexadecimal, zero padded or truncated to the length given by the DLC.
+fn parse_frame(id: &str, dlc: &str, data: &str, fd: bool) -> Result<Frame, &'static str> {
+    let id: CanId = id.parse().map_err(|_| "Invalid id")?;
+
+    let dlc: usize = dlc.trim().parse().map_err(|_| "Invalid DLC")?;
+    let len = match fd {
+        true => FD_LENGTHS.get(dlc).copied(),
+        false => (dlc <= 8).then_some(dlc),
+    }
+    .ok_or("Invalid DLC")?;
+
+    let digits: Vec<char> = data.chars().filter(|c| !c.is_whitespace()).collect();
+    let bytes = digits.chunks_exact(2);
+    if !bytes.remainder().is_empty() {
+        return Err("Invalid data");
+    }
+    let mut data = bytes
+        .map(|pair| u8::from_str_radix(&pair.iter().collect::<String>(), 16))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| "Invalid data")?;
+    data.resize(len, 0);
+
+    let timestamp = canbusnoop_core::now();
+    let frame = match fd {
+        true => Frame::new_fd(
+            id,
+            data,
+            FdFlags {
+                brs: true,
+                esi: false,
+            },
+            timestamp,
+        ),
+        false => Frame::new(id, data, timestamp),
+    };
+    Ok(frame)
+}
@@ -1,10 +1,20 @@
 use super::stats_item::StatsItem;
+use canbusnoop_core::{CanId, J1939Id, Pgn};
 use canbusnoop_db::MultiStats;
 use dioxus::prelude::*;
+use std::collections::BTreeMap;
+use std::time::Duration;
 
 #[derive(Props, PartialEq)]
 pub(crate) struct StatsProps {
     stats: MultiStats,
+    /// Group J1939 ids by PGN, so the same parameter group sent by several
+    /// source addresses is shown together
+    group_by_pgn: bool,
+    /// Label standard ids with their CANopen function and node
+    canopen: bool,
+    /// Time of the last frame received
+    now: Duration,
 }
 
 pub(crate) fn Stats(cx: Scope<StatsProps>) -> Element {
@@ -12,6 +22,36 @@ pub(crate) fn Stats(cx: Scope<StatsProps>) -> Element {
     let header1 = COLUMNS.iter().map(|(x, _)| render! { Cell { x } });
     let header0 = COLUMNS.iter().map(|(_, x)| render! { Cell { x } });
 
+    if cx.props.group_by_pgn {
+        let groups = group_by_pgn(stats);
+        return render! {
+            table {
+                class: "table-auto w-full text-sm text-left text-gray-500 dark:text-gray-400",
+                thead {
+                    class: "text-xs text-gray-700 uppercase bg-gray-50 dark:bg-gray-700 dark:text-gray-400",
+                    tr { header0 }
+                    tr { header1 }
+                }
+                tbody {
+                    for (label, items) in groups.into_iter() {
+                        tr {
+                            class: "bg-gray-100 text-gray-700 font-bold",
+                            td { colspan: COLUMNS.len() as i64, class: "p-2", "{label}" }
+                        }
+                        for (id, stats) in items.into_iter() {
+                            StatsItem {
+                                id: id,
+                                stats: stats,
+                                canopen: cx.props.canopen,
+                                now: cx.props.now
+                            }
+                        }
+                    }
+                }
+            }
+        };
+    }
+
     render! {
         table {
             class: "table-auto w-full text-sm text-left text-gray-500 dark:text-gray-400",
@@ -24,7 +64,9 @@ pub(crate) fn Stats(cx: Scope<StatsProps>) -> Element {
                 for (&id, stats) in stats.iter() {
                     StatsItem {
                         id: id,
-                        stats: stats.clone()
+                        stats: stats.clone(),
+                        canopen: cx.props.canopen,
+                        now: cx.props.now
                     }
                 }
             }
@@ -46,14 +88,61 @@ fn Cell<'a>(cx: Scope<'a, CellProps<'a>>) -> Element {
     }
 }
 
-const COLUMNS: [(&'static str, &'static str); 9] = [
+/// Split the ids by PGN, then the ids which are not J1939. Each group comes
+/// with a label describing it.
+fn group_by_pgn(stats: &MultiStats) -> Vec<(String, Vec<(CanId, canbusnoop_db::Stats)>)> {
+    let mut groups: BTreeMap<Option<Pgn>, Vec<(CanId, canbusnoop_db::Stats)>> = BTreeMap::new();
+    for (&id, stats) in stats.iter() {
+        let pgn = J1939Id::from_can_id(id).map(|j| j.pgn());
+        groups.entry(pgn).or_default().push((id, stats.clone()));
+    }
+
+    // None sorts first, but ids which are not J1939 are shown last
+    let other = groups.remove(&None);
+
+    let mut groups: Vec<_> = groups
+        .into_iter()
+        .filter_map(|(pgn, items)| Some((pgn?, items)))
+        .map(|(pgn, items)| {
+            let count: usize = items.iter().map(|(_, s)| s.count()).sum();
+            let name = pgn.name().unwrap_or_default();
+            let label = format!(
+                "PGN {} {}: {} sources, {} frames",
+                pgn,
+                name,
+                items.len(),
+                count
+            );
+            (label, items)
+        })
+        .collect();
+
+    if let Some(items) = other {
+        groups.push(("Other".to_string(), items));
+    }
+
+    groups
+}
+
+const COLUMNS: [(&str, &str); 20] = [
     ("ID", ""),
+    ("Type", ""),
+    ("PGN", ""),
+    ("SA", ""),
+    ("DA", ""),
+    ("Prio", ""),
     ("Count", ""),
     ("Last", "ms"),
     ("Min", "ms"),
     ("Max", "ms"),
     ("Avg", "ms"),
+    ("P50", "ms"),
+    ("P95", "ms"),
+    ("P99", "ms"),
+    ("Std dev", "ms"),
     ("Freq", "Hz"),
     ("Throughput", "Hz"),
     ("Jitter", "%"),
+    ("DLC", ""),
+    ("Data", ""),
 ];
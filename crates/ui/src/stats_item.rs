@@ -1,40 +1,140 @@
-use canbusnoop_db::Stats;
+use super::bits::BitHeatMap;
+use super::fmt_data;
+use super::plot::{AddPlot, PlotBytes, PlotKey, PlotSource};
+use canbusnoop_core::{CanId, J1939Id};
+use canbusnoop_db::{fmt_period, PeriodStats, Stats};
+use canbusnoop_dbc::Dbc;
+use canbusnoop_protocol::canopen::CobId;
+use canbusnoop_protocol::nmea2000;
 use colorsys::{Hsl, Rgb};
 use dioxus::prelude::*;
-use std::time::Duration;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 #[derive(Props, PartialEq)]
 pub(crate) struct StatsItemProps {
-    id: u32,
+    id: CanId,
     stats: Stats,
+    canopen: bool,
+    /// Time of the last frame received, to fade the changed bytes
+    now: Duration,
 }
 
 pub(crate) fn StatsItem(cx: Scope<StatsItemProps>) -> Element {
     let stats = &cx.props.stats;
     let id = cx.props.id;
+    let expanded = use_state(cx, || false);
 
     let stats_str = StatsStrings::from(stats);
+    let j1939 = J1939Strings::from(id);
+
+    let message = use_context::<Arc<Dbc>>(cx).and_then(|dbc| dbc.message(id));
+    let pgn_info = nmea2000::lookup_id(id);
+    let name = match (message, pgn_info) {
+        (Some(m), _) => m.name.clone(),
+        (None, _) if cx.props.canopen => CobId::from_can_id(id)
+            .map(|c| c.to_string())
+            .unwrap_or_default(),
+        (None, Some(info)) => info.name.to_string(),
+        (None, None) => String::new(),
+    };
+
+    // Decoded signals of the last frame, only when the row is expanded
+    let signals: Vec<(String, String)> = match message {
+        Some(message) if **expanded => message
+            .decode(stats.last_data())
+            .into_iter()
+            .map(|s| (s.signal.name.clone(), s.to_string()))
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    // NMEA 2000 fields, when there is no DBC message for the id
+    let fields: Vec<(&str, String)> = match (message, pgn_info) {
+        (None, Some(info)) if **expanded => info
+            .decode(stats.last_data())
+            .into_iter()
+            .map(|f| (f.field.name, f.to_string()))
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    let data = fmt_data(stats.last_data());
 
     render! {
         Row {
-            Cell { ColoredId { id: id } }
+            Cell {
+                div {
+                    class: "flex gap-2 cursor-pointer",
+                    onclick: move |_| expanded.set(!**expanded),
+                    ColoredId { id: id }
+                    span { class: "text-gray-700", "{name}" }
+                }
+            }
+            Cell { CellValue { value: stats_str.kind } }
+            Cell { CellValue { value: j1939.pgn } }
+            Cell { CellValue { value: j1939.sa } }
+            Cell { CellValue { value: j1939.da } }
+            Cell { CellValue { value: j1939.priority } }
             Cell { CellValue { value: stats_str.count } }
             Cell { CellValue { value: stats_str.last_period } }
             Cell { CellValue { value: stats_str.min_period } }
             Cell { CellValue { value: stats_str.max_period } }
             Cell { CellValue { value: stats_str.avg_period } }
+            Cell { CellValue { value: stats_str.p50 } }
+            Cell { CellValue { value: stats_str.p95 } }
+            Cell { CellValue { value: stats_str.p99 } }
+            Cell { CellValue { value: stats_str.stddev } }
             Cell { CellValue { value: stats_str.avg_freq } }
             Cell { CellValue { value: stats_str.throughput } }
             Cell { CellValue { value: stats_str.period_jitter } }
+            Cell { CellValue { value: stats_str.dlc } }
+            Cell {
+                DataBytes {
+                    data: stats.last_data().to_vec(),
+                    changes: stats.byte_changes().to_vec(),
+                    last_change: stats.last_change(),
+                    now: cx.props.now,
+                }
+            }
+        }
+        if **expanded {
+            rsx! {
+                tr {
+                    td {
+                        colspan: 20,
+                        class: "px-8 py-1 bg-gray-50",
+                        for (name, value) in signals.into_iter() {
+                            div {
+                                class: "flex items-center gap-4 font-mono text-xs",
+                                span { class: "w-48", "{name}" }
+                                span { class: "w-48", "{value}" }
+                                AddPlot {
+                                    plot: PlotKey { id, source: PlotSource::Signal(name) }
+                                }
+                            }
+                        }
+                        for (name, value) in fields.into_iter() {
+                            div {
+                                class: "flex items-center gap-4 font-mono text-xs",
+                                span { class: "w-48", "{name}" }
+                                span { class: "w-48", "{value}" }
+                            }
+                        }
+                        div {
+                            class: "flex items-center gap-4 font-mono text-xs",
+                            span { class: "w-48", "Data" }
+                            span { "{data}" }
+                        }
+                        BitHeatMap { bits: stats.bits().clone() }
+                        PlotBytes { id: id }
+                    }
+                }
+            }
         }
     }
 }
 
-fn fmt_period(x: Duration) -> String {
-    let ms = x.as_millis();
-    format!("{:6?}", ms)
-}
-
 /// Translate a nibble (0-16) to a color hex string
 fn nibble_to_color(byte: u8) -> Rgb {
     let h = byte as f64 / 16. * (360. / 16.0 * 15.0);
@@ -63,33 +163,43 @@ fn text_color_from_bg(bg: &Rgb) -> Rgb {
 }
 
 struct StatsStrings {
+    kind: String,
     count: String,
     last_period: String,
     min_period: String,
     max_period: String,
     avg_period: String,
+    p50: String,
+    p95: String,
+    p99: String,
+    stddev: String,
     throughput: String,
     period_jitter: String,
     avg_freq: String,
+    dlc: String,
 }
 
 impl From<&Stats> for StatsStrings {
     fn from(stats: &Stats) -> Self {
+        let kind = stats
+            .fd_flags()
+            .map(|f| f.to_string())
+            .unwrap_or("CAN".to_string());
         let count = stats.count().to_string();
         let last_period = stats.last_period().map(fmt_period).unwrap_or_default();
         let min_period = stats.min_period().map(fmt_period).unwrap_or_default();
         let max_period = stats.max_period().map(fmt_period).unwrap_or_default();
-        let avg_period = stats.avg_period().map(fmt_period).unwrap_or_default();
-
-        let avg_freq = stats.avg_period().map(|x| x.as_secs_f64()).and_then(|s| {
-            if s != 0. {
-                Some(1. / (s as f64))
-            } else {
-                None
-            }
-        });
+        let periods = stats.period_stats();
+        let period = |p: fn(&PeriodStats) -> Duration| {
+            periods.as_ref().map(p).map(fmt_period).unwrap_or_default()
+        };
+        let avg_period = period(|p| p.mean);
+        let p50 = period(|p| p.p50);
+        let p95 = period(|p| p.p95);
+        let p99 = period(|p| p.p99);
+        let stddev = period(|p| p.stddev);
 
-        let avg_freq = match avg_freq {
+        let avg_freq = match periods.and_then(|p| p.frequency()) {
             Some(avg_freq) => format!("{:.2}", avg_freq),
             None => "".to_string(),
         };
@@ -97,18 +207,57 @@ impl From<&Stats> for StatsStrings {
         let throughput = stats.throughput();
         let throughput = throughput.map(|x| format!("{:.2}", x)).unwrap_or_default();
 
-        let period_jitter = stats.period_jitter() * 100.;
+        let period_jitter = periods.map(|p| p.jitter).unwrap_or_default() * 100.;
         let period_jitter = format!("{:.2}", period_jitter);
 
+        let dlc = stats.dlc().map(|d| d.to_string()).unwrap_or_default();
+
         Self {
+            kind,
             count,
             last_period,
             min_period,
             max_period,
             avg_period,
+            p50,
+            p95,
+            p99,
+            stddev,
             throughput,
             period_jitter,
             avg_freq,
+            dlc,
+        }
+    }
+}
+
+/// J1939 fields, empty for ids which are not J1939
+#[derive(Default)]
+struct J1939Strings {
+    pgn: String,
+    sa: String,
+    da: String,
+    priority: String,
+}
+
+impl From<CanId> for J1939Strings {
+    fn from(id: CanId) -> Self {
+        let Some(j1939) = J1939Id::from_can_id(id) else {
+            return Self::default();
+        };
+        let pgn = j1939.pgn();
+        let pgn = match pgn.name() {
+            Some(name) => format!("{} {}", pgn, name),
+            None => pgn.to_string(),
+        };
+        Self {
+            pgn,
+            sa: format!("{:02X}", j1939.source_address()),
+            da: j1939
+                .destination_address()
+                .map(|da| format!("{:02X}", da))
+                .unwrap_or_default(),
+            priority: j1939.priority().to_string(),
         }
     }
 }
@@ -125,15 +274,121 @@ fn CellValue(cx: Scope, value: String) -> Element {
     }
 }
 
+/// How long a changed byte stays highlighted
+const CHANGE_FADE: Duration = Duration::from_secs(2);
+
+/// Time between renders while a byte fades out
+const FADE_STEP: Duration = Duration::from_millis(100);
+
+/// Bytes shown in the row, longer reassembled messages are elided
+const MAX_BYTES: usize = 64;
+
+#[derive(Props, PartialEq)]
+struct DataBytesProps {
+    data: Vec<u8>,
+    changes: Vec<Option<Duration>>,
+    #[props(!optional)]
+    last_change: Option<Duration>,
+    now: Duration,
+}
+
+/// The payload in hex, the bytes which changed are highlighted and fade
+/// out over time, like cansniffer does. The fade goes on while the bus is
+/// quiet: the time since the last frame is added to its capture time.
+fn DataBytes(cx: Scope<DataBytesProps>) -> Element {
+    // Capture time of the last frame and when it was rendered first
+    let clock = use_ref(cx, || (cx.props.now, Instant::now()));
+    if clock.read().0 != cx.props.now {
+        *clock.write_silent() = (cx.props.now, Instant::now());
+    }
+    let (last_frame, rendered) = *clock.read();
+    let now = last_frame + rendered.elapsed();
+
+    let age = |i: usize| {
+        cx.props
+            .changes
+            .get(i)
+            .copied()
+            .flatten()
+            .map(|t| now.saturating_sub(t))
+    };
+    let fading = (0..cx.props.data.len()).any(|i| age(i).is_some_and(|age| age < CHANGE_FADE));
+
+    // Render again until the bytes have faded out
+    let tick = use_state(cx, || 0u64);
+    use_future(cx, (&fading,), |(fading,)| {
+        to_owned![tick];
+        async move {
+            // Restarted when the fading stops
+            if !fading {
+                return;
+            }
+            loop {
+                tokio::time::sleep(FADE_STEP).await;
+                tick.modify(|t| t.wrapping_add(1));
+            }
+        }
+    });
+
+    let bytes = cx
+        .props
+        .data
+        .iter()
+        .take(MAX_BYTES)
+        .enumerate()
+        .map(|(i, b)| {
+            let alpha = match age(i) {
+                Some(age) if age < CHANGE_FADE => {
+                    1. - age.as_secs_f64() / CHANGE_FADE.as_secs_f64()
+                }
+                _ => 0.,
+            };
+            (
+                format!("{:02X}", b),
+                format!("rgba(250, 204, 21, {:.2})", alpha),
+            )
+        });
+    let elided = cx.props.data.len().saturating_sub(MAX_BYTES);
+    let title = match cx.props.last_change {
+        Some(t) => format!("changed {:.1} s ago", now.saturating_sub(t).as_secs_f64()),
+        None => String::new(),
+    };
+
+    render! {
+        div {
+            class: "flex flex-wrap max-w-md font-mono",
+            title: "{title}",
+            for (byte, bg_color) in bytes {
+                span {
+                    background_color: "{bg_color}",
+                    padding: "0 0.2em",
+                    "{byte}"
+                }
+            }
+            if elided > 0 {
+                rsx! { span { class: "px-1 text-gray-700", "+{elided} bytes" } }
+            }
+        }
+    }
+}
+
 #[component]
-fn ColoredId(cx: Scope, id: u32) -> Element {
-    let id_arr = id.to_be_bytes();
+fn ColoredId(cx: Scope, id: CanId) -> Element {
+    let id = *id;
+
+    // 3 nibbles for standard ids, 8 nibbles for extended ids
+    let digits = if id.is_extended() { 8 } else { 3 };
+    let nibbles = (0..digits)
+        .rev()
+        .map(move |i| ((id.id() >> (i * 4)) & 0x0F) as u8);
 
     render! {
         div {
-            for &c in id_arr.iter() {
-                ColoredNibble { nibble: c >> 4 }
-                ColoredNibble { nibble: c & 0x0F }
+            for nibble in nibbles {
+                ColoredNibble { nibble: nibble }
+            }
+            if id.is_rtr() {
+                rsx! { span { class: "px-1 text-xs", "RTR" } }
             }
         }
     }
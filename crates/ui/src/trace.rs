@@ -0,0 +1,213 @@
+use super::fmt_data;
+use super::widgets::Button;
+use canbusnoop_core::{CanId, Frame, J1939Id};
+use canbusnoop_db::Trace;
+use canbusnoop_dbc::Dbc;
+use canbusnoop_protocol::nmea2000;
+use dioxus::prelude::*;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Frames kept in the trace
+pub(crate) const CAPACITY: usize = 100_000;
+
+/// Rows rendered, only the visible part of the trace is in the DOM
+const ROWS: usize = 40;
+
+/// Rows scrolled by each step of the mouse wheel
+const WHEEL_ROWS: usize = 3;
+
+#[derive(Props, PartialEq)]
+pub(crate) struct TracePanelProps {
+    /// Time of the last frame received, to render again when it changes
+    now: Duration,
+    /// Id filter and mask, as for the statistics
+    filter: u32,
+    mask: u32,
+}
+
+/// Frames in arrival order. Follows the newest frames until scrolled back,
+/// when paused new frames are received but not shown.
+pub(crate) fn TracePanel(cx: Scope<TracePanelProps>) -> Element {
+    let trace = use_context::<UseRef<Trace>>(cx)?;
+    let dbc = use_context::<Arc<Dbc>>(cx);
+    let follow = use_state(cx, || true);
+    let paused_at = use_state(cx, || None::<u64>);
+    let offset = use_state(cx, || 0usize);
+
+    let trace = trace.read();
+    let total = trace.total();
+
+    // The trace was cleared since the pause
+    let paused = paused_at.filter(|&p| p <= total);
+    if paused_at.is_some() && paused.is_none() {
+        paused_at.set(None);
+    }
+
+    // Without a filter the window is indexed directly, not to walk the
+    // whole trace at each render
+    let unfiltered = cx.props.mask == 0;
+    let frames = || {
+        trace
+            .filter(cx.props.filter, cx.props.mask)
+            .take_while(|(seq, _)| paused.map_or(true, |p| *seq < p))
+            .map(|(_, frame)| frame)
+    };
+    let count = match unfiltered {
+        true => trace
+            .len()
+            .saturating_sub((total - paused.unwrap_or(total)) as usize),
+        false => frames().count(),
+    };
+
+    let last_start = count.saturating_sub(ROWS);
+    let start = match **follow {
+        true => last_start,
+        false => (**offset).min(last_start),
+    };
+    let start_time = trace.start().unwrap_or_default();
+
+    // The frame before the first row too, for its delta
+    let first = start.saturating_sub(1);
+    let window: Vec<_> = match unfiltered {
+        true => trace
+            .range(first..(start + ROWS).min(count))
+            .map(|(_, frame)| frame)
+            .collect(),
+        false => frames().skip(first).take(ROWS + 1).collect(),
+    };
+    let previous = |i: usize| match start {
+        0 => i.checked_sub(1).map(|i| window[i].timestamp()),
+        _ => Some(window[i].timestamp()),
+    };
+    let rows: Vec<_> = window
+        .iter()
+        .skip(usize::from(start > 0))
+        .take(ROWS)
+        .enumerate()
+        .map(|(i, frame)| TraceRow::new(frame, previous(i), start_time, dbc.map(|d| d.as_ref())))
+        .collect();
+
+    let summary = format!("{} of {} frames, {} kept", count, total, trace.len());
+    let follow_label = if **follow { "Following" } else { "Follow" };
+    let pause_label = if paused.is_some() { "Resume" } else { "Pause" };
+
+    let scroll = move |rows: isize| {
+        let start = start.saturating_add_signed(rows).min(last_start);
+        offset.set(start);
+        follow.set(start == last_start && rows > 0);
+    };
+
+    render! {
+        div {
+            class: "p-2 text-sm",
+            div {
+                class: "flex items-center gap-2",
+                div { class: "font-bold", "Trace" }
+                Button {
+                    on_click: move |_| follow.set(true),
+                    "{follow_label}"
+                }
+                Button {
+                    on_click: move |_| match paused.is_some() {
+                        true => paused_at.set(None),
+                        false => paused_at.set(Some(total)),
+                    },
+                    "{pause_label}"
+                }
+                div { class: "text-xs text-gray-700", "{summary}" }
+            }
+            div {
+                class: "flex gap-2",
+                onwheel: move |evt| {
+                    let y = evt.delta().strip_units().y;
+                    match y.partial_cmp(&0.) {
+                        Some(std::cmp::Ordering::Less) => scroll(-(WHEEL_ROWS as isize)),
+                        Some(std::cmp::Ordering::Greater) => scroll(WHEEL_ROWS as isize),
+                        _ => {}
+                    }
+                },
+                table {
+                    class: "table-fixed w-full text-xs text-left font-mono",
+                    thead {
+                        tr {
+                            for x in ["Time s", "Delta ms", "Interface", "ID", "DLC", "Data", "Name"] {
+                                th { class: "px-2", "{x}" }
+                            }
+                        }
+                    }
+                    tbody {
+                        for row in rows.into_iter() {
+                            tr {
+                                class: "h-5 whitespace-nowrap",
+                                td { class: "px-2", "{row.time}" }
+                                td { class: "px-2", "{row.delta}" }
+                                td { class: "px-2", "{row.interface}" }
+                                td { class: "px-2", "{row.id}" }
+                                td { class: "px-2", "{row.dlc}" }
+                                td { class: "px-2 overflow-hidden", "{row.data}" }
+                                td { class: "px-2", "{row.name}" }
+                            }
+                        }
+                    }
+                }
+                // Acts as the scroll bar, the list is too long for the DOM
+                input {
+                    r#type: "range",
+                    style: "writing-mode: vertical-lr",
+                    min: "0",
+                    max: "{last_start}",
+                    value: "{start}",
+                    oninput: move |evt| {
+                        let start = evt.value.parse().unwrap_or(last_start);
+                        offset.set(start);
+                        follow.set(start >= last_start);
+                    },
+                }
+            }
+        }
+    }
+}
+
+struct TraceRow {
+    time: String,
+    delta: String,
+    interface: String,
+    id: String,
+    dlc: String,
+    data: String,
+    name: String,
+}
+
+impl TraceRow {
+    fn new(frame: &Frame, previous: Option<Duration>, start: Duration, dbc: Option<&Dbc>) -> Self {
+        let timestamp = frame.timestamp();
+        let delta = previous
+            .map(|p| format!("{:.3}", timestamp.saturating_sub(p).as_secs_f64() * 1000.))
+            .unwrap_or_default();
+        let id = frame.id();
+        Self {
+            time: format!("{:.6}", timestamp.saturating_sub(start).as_secs_f64()),
+            delta,
+            interface: frame.interface().unwrap_or_default().to_string(),
+            id: id.to_string(),
+            dlc: frame.dlc().map(|d| d.to_string()).unwrap_or_default(),
+            data: fmt_data(frame.data()),
+            name: decoded_name(id, dbc),
+        }
+    }
+}
+
+/// The DBC message name, else the NMEA 2000 or J1939 parameter group name
+fn decoded_name(id: CanId, dbc: Option<&Dbc>) -> String {
+    if let Some(message) = dbc.and_then(|dbc| dbc.message(id)) {
+        return message.name.clone();
+    }
+    if let Some(info) = nmea2000::lookup_id(id) {
+        return info.name.to_string();
+    }
+    J1939Id::from_can_id(id)
+        .and_then(|j| j.pgn().name())
+        .unwrap_or_default()
+        .to_string()
+}
@@ -0,0 +1,202 @@
+use super::fmt_data;
+use canbusnoop_core::Frame;
+use canbusnoop_protocol::isotp::{IsoTp, IsoTpChannel, IsoTpDirection, IsoTpEvent};
+use canbusnoop_protocol::uds::{session_name, UdsKind, UdsMessage, UdsSessions, DEFAULT_SESSION};
+use dioxus::prelude::*;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// Messages kept in the log
+const CAPACITY: usize = 1000;
+
+/// Messages shown, the most recent ones
+const SHOWN: usize = 200;
+
+/// A line of the log: a UDS message or an ISO-TP error
+struct Entry {
+    timestamp: Duration,
+    channel: usize,
+    direction: IsoTpDirection,
+    address: Option<u8>,
+    text: String,
+    data: String,
+    error: bool,
+}
+
+/// UDS messages exchanged on the configured ISO-TP channels
+pub(crate) struct UdsLog {
+    isotp: IsoTp,
+    sessions: UdsSessions,
+    entries: VecDeque<Entry>,
+}
+
+impl UdsLog {
+    pub(crate) fn new(channels: Vec<IsoTpChannel>) -> UdsLog {
+        UdsLog {
+            isotp: IsoTp::new(channels),
+            sessions: UdsSessions::default(),
+            entries: VecDeque::new(),
+        }
+    }
+
+    /// False when no channels are configured
+    pub(crate) fn is_enabled(&self) -> bool {
+        !self.isotp.channels().is_empty()
+    }
+
+    pub(crate) fn push(&mut self, frame: &Frame) {
+        for event in self.isotp.push(frame) {
+            let entry = match event {
+                IsoTpEvent::Message(message) => {
+                    let (text, error) = match UdsMessage::parse(&message.data) {
+                        Some(uds) => {
+                            let mut text = uds.to_string();
+                            if let Some(session) = self.sessions.push(&message, &uds) {
+                                text.push_str(&format!(" => {} session", session_name(session)));
+                            }
+                            let negative = matches!(uds.kind, UdsKind::NegativeResponse(_));
+                            (text, negative)
+                        }
+                        None => ("Empty message".to_string(), true),
+                    };
+                    Entry {
+                        timestamp: message.timestamp,
+                        channel: message.channel,
+                        direction: message.direction,
+                        address: message.address,
+                        text,
+                        data: fmt_data(&message.data),
+                        error,
+                    }
+                }
+                IsoTpEvent::Error {
+                    channel,
+                    direction,
+                    address,
+                    error,
+                    timestamp,
+                } => Entry {
+                    timestamp,
+                    channel,
+                    direction,
+                    address,
+                    text: format!("ISO-TP {}", error),
+                    data: String::new(),
+                    error: true,
+                },
+            };
+
+            if self.entries.len() == CAPACITY {
+                self.entries.pop_front();
+            }
+            self.entries.push_back(entry);
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.isotp.clear();
+        self.sessions.clear();
+        self.entries.clear();
+    }
+}
+
+#[derive(Props, PartialEq)]
+pub(crate) struct UdsPanelProps {
+    /// Time of the last frame received, times are shown relative to it
+    now: Duration,
+}
+
+/// Diagnostic sessions of the configured channels, with the most recent
+/// messages first
+pub(crate) fn UdsPanel(cx: Scope<UdsPanelProps>) -> Element {
+    let log = use_context::<UseRef<UdsLog>>(cx)?;
+    let log = log.read();
+    if !log.is_enabled() {
+        return None;
+    }
+
+    let now = cx.props.now;
+    let channels: Vec<_> = log
+        .isotp
+        .channels()
+        .iter()
+        .enumerate()
+        .map(|(i, channel)| {
+            let sessions: Vec<_> = log
+                .sessions
+                .iter()
+                .filter(|((c, _), _)| *c == i)
+                .map(|((_, address), session)| match address {
+                    Some(address) => format!("{:02X} {}", address, session_name(session)),
+                    None => session_name(session).to_string(),
+                })
+                .collect();
+            let sessions = match sessions.is_empty() {
+                true => session_name(DEFAULT_SESSION).to_string(),
+                false => sessions.join(", "),
+            };
+            format!("{} {} session", channel, sessions)
+        })
+        .collect();
+
+    let rows: Vec<_> = log
+        .entries
+        .iter()
+        .rev()
+        .take(SHOWN)
+        .map(|e| {
+            let class = match e.error {
+                true => "text-red-800",
+                false => "",
+            };
+            let direction = match e.direction {
+                IsoTpDirection::Request => "Req",
+                IsoTpDirection::Response => "Resp",
+            };
+            let address = e.address.map(|a| format!("{:02X}", a)).unwrap_or_default();
+            let time = format!("-{:.3}", now.saturating_sub(e.timestamp).as_secs_f64());
+            let cells = [
+                time,
+                e.channel.to_string(),
+                direction.to_string(),
+                address,
+                e.text.clone(),
+                e.data.clone(),
+            ];
+            (class, cells)
+        })
+        .collect();
+
+    render! {
+        div {
+            class: "p-2 text-sm",
+            div { class: "font-bold", "UDS" }
+            for channel in channels.into_iter() {
+                div { class: "font-mono text-xs", "{channel}" }
+            }
+            div {
+                class: "max-h-64 overflow-y-auto",
+                table {
+                    class: "table-auto text-xs text-left font-mono",
+                    thead {
+                        tr {
+                            for x in ["Time s", "Ch", "Dir", "Addr", "Message", "Data"] {
+                                th { class: "px-2", "{x}" }
+                            }
+                        }
+                    }
+                    tbody {
+                        for (class, cells) in rows.into_iter() {
+                            tr {
+                                class: class,
+                                for x in cells.into_iter() {
+                                    td { class: "px-2", "{x}" }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -1,8 +1,8 @@
-use dioxus::prelude::*;
 use dioxus::core::{Element, Scope};
 use dioxus::core_macro::{render, Props};
 use dioxus::events::MouseEvent;
 use dioxus::prelude::EventHandler;
+use dioxus::prelude::*;
 
 #[derive(Props)]
 pub struct ButtonProps<'a> {